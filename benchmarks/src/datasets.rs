@@ -1,5 +1,4 @@
 use crate::*;
-use parity_scale_codec::Encode;
 use runtime::staking;
 use types::transaction_generator::*;
 
@@ -84,21 +83,20 @@ pub fn world_of_stakers<R: ModuleRuntime>(
 		});
 
 	let mut transactions = vec![];
+	let mut nonces = Nonces::default();
 	validators.iter().for_each(|(stash, ctrl)| {
 		// sign and submit a bond
 		let inner_call = staking::Call::TxBond(BOND, ctrl.public());
 		let id = rand::random::<TransactionId>();
 		let call = OuterCall::Staking(inner_call);
-		let signed_call = call.using_encoded(|bytes| stash.sign(bytes));
-		let tx = Transaction::new(id, call, stash.public(), signed_call);
+		let tx = sign(id, call, nonces.next(stash.public()), stash);
 		transactions.push(tx);
 
 		// sign and submit a validate.
 		let inner_call = staking::Call::TxValidate();
 		let id = rand::random::<TransactionId>();
 		let call = OuterCall::Staking(inner_call);
-		let signed_call = call.using_encoded(|bytes| ctrl.sign(bytes));
-		let tx = Transaction::new(id, call, ctrl.public(), signed_call);
+		let tx = sign(id, call, nonces.next(ctrl.public()), ctrl);
 		transactions.push(tx);
 	});
 
@@ -107,8 +105,7 @@ pub fn world_of_stakers<R: ModuleRuntime>(
 		let inner_call = staking::Call::TxBond(BOND, ctrl.public());
 		let id = rand::random::<TransactionId>();
 		let call = OuterCall::Staking(inner_call);
-		let signed_call = call.using_encoded(|bytes| stash.sign(bytes));
-		let tx = Transaction::new(id, call, stash.public(), signed_call);
+		let tx = sign(id, call, nonces.next(stash.public()), stash);
 		transactions.push(tx);
 
 		// sign and submit a nominate.
@@ -119,8 +116,7 @@ pub fn world_of_stakers<R: ModuleRuntime>(
 		let inner_call = staking::Call::TxNominate(votes);
 		let id = rand::random::<TransactionId>();
 		let call = OuterCall::Staking(inner_call);
-		let signed_call = call.using_encoded(|bytes| ctrl.sign(bytes));
-		let tx = Transaction::new(id, call, ctrl.public(), signed_call);
+		let tx = sign(id, call, nonces.next(ctrl.public()), ctrl);
 		transactions.push(tx);
 	});
 
@@ -136,8 +132,7 @@ pub fn world_of_stakers<R: ModuleRuntime>(
 				let inner_call = staking::Call::TxUnbond(BOND / 2);
 				let id = rand::random::<TransactionId>();
 				let call = OuterCall::Staking(inner_call);
-				let signed_call = call.using_encoded(|bytes| stash.sign(bytes));
-				let tx = Transaction::new(id, call, stash.public(), signed_call);
+				let tx = sign(id, call, nonces.next(stash.public()), stash);
 				transactions.push(tx);
 			}
 			1 => {
@@ -146,8 +141,7 @@ pub fn world_of_stakers<R: ModuleRuntime>(
 				let inner_call = staking::Call::TxChill();
 				let id = rand::random::<TransactionId>();
 				let call = OuterCall::Staking(inner_call);
-				let signed_call = call.using_encoded(|bytes| ctrl.sign(bytes));
-				let tx = Transaction::new(id, call, ctrl.public(), signed_call);
+				let tx = sign(id, call, nonces.next(ctrl.public()), ctrl);
 				transactions.push(tx);
 			}
 			2 => {
@@ -156,8 +150,7 @@ pub fn world_of_stakers<R: ModuleRuntime>(
 				let inner_call = staking::Call::TxBondExtra(BOND);
 				let id = rand::random::<TransactionId>();
 				let call = OuterCall::Staking(inner_call);
-				let signed_call = call.using_encoded(|bytes| stash.sign(bytes));
-				let tx = Transaction::new(id, call, stash.public(), signed_call);
+				let tx = sign(id, call, nonces.next(stash.public()), stash);
 				transactions.push(tx);
 			}
 			3 => {
@@ -166,8 +159,7 @@ pub fn world_of_stakers<R: ModuleRuntime>(
 				let inner_call = staking::Call::TxSetController(testing::random().public());
 				let id = rand::random::<TransactionId>();
 				let call = OuterCall::Staking(inner_call);
-				let signed_call = call.using_encoded(|bytes| stash.sign(bytes));
-				let tx = Transaction::new(id, call, stash.public(), signed_call);
+				let tx = sign(id, call, nonces.next(stash.public()), stash);
 				transactions.push(tx);
 			}
 			_ => unreachable!(),
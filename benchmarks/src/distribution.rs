@@ -0,0 +1,56 @@
+use csv::Writer;
+use executor::{concurrent::*, types::*, *};
+use primitives::ThreadId;
+use runtime::SequentialRuntime;
+use std::time::{Duration, Instant};
+
+/// The distributers used by [`distribution_scaling_bench`] if none are given.
+pub const DISTRIBUTION_SCALING_DISTRIBUTERS: [&str; 4] =
+	["RoundRobin", "HashPartition", "ConnectedComponents", "MinCut"];
+
+const THREADS: [ThreadId; 4] = [1, 2, 3, 4];
+
+macro_rules! bench_distribution {
+	($members:expr, $txs:expr, $dist:expr, $wtr:ident) => {
+		let time = distribution_time($dist, $members, $txs);
+		$wtr.write_record(&[
+			$dist,
+			stringify!($members),
+			stringify!($txs),
+			&time.as_millis().to_string(),
+			])
+		.unwrap();
+		$wtr.flush().unwrap();
+	};
+}
+
+/// How the time it takes to distribute a block among 4 threads scales with the size of the block.
+///
+/// Only the distribution is measured, the transactions are not executed.
+#[allow(dead_code)]
+pub fn distribution_scaling_bench(distributers: Option<&[&str]>) {
+	let mut wtr = Writer::from_path("distribution_scaling.csv").unwrap();
+	wtr.write_record(["distribution_scaling", "-", "-", "-"])
+		.unwrap();
+	wtr.write_record(["type", "members", "transactions", "distribution (ms)"])
+		.unwrap();
+
+	for dist in distributers.unwrap_or(&DISTRIBUTION_SCALING_DISTRIBUTERS) {
+		bench_distribution!(1000, 1000, dist, wtr);
+		bench_distribution!(10000, 10000, dist, wtr);
+		bench_distribution!(100000, 100000, dist, wtr);
+	}
+
+	wtr.flush().unwrap();
+}
+
+fn distribution_time(distributer: &str, members: usize, transactions: usize) -> Duration {
+	let mut distributer = tx_distribution::by_name(distributer).expect("unknown distributer");
+	let (dataset, _) = transaction_generator::bank(members, transactions, 1000);
+	let mut pool = Pool::from(dataset);
+	let runtime = SequentialRuntime::new(Default::default(), 0);
+
+	let start = Instant::now();
+	distributer.distribute(&runtime, &THREADS, &mut pool);
+	start.elapsed()
+}
@@ -5,14 +5,15 @@ use runtime::*;
 use state::StateEq;
 
 mod datasets;
+mod distribution;
 mod middle_class;
 mod millionaires;
+mod timing;
 
 const LOG_TARGET: &'static str = "benchmarks";
 const NUM_THREADS: usize = 4;
 
 // TODO: more complicated modules: multiSig is fun?
-// TODO: I wish I could impl Drop for Master and join() + terminate all workers..
 // FIXME: test cases in this crate for determinism.
 // FIXME: pool needs reordering once we have a forward, interesting that I don't have this issue yet.
 // FIXME: adding initial state to all functions of the executor could potentially make the API
@@ -34,13 +35,15 @@ fn sequential_stakers() {
 	);
 
 	let start = std::time::Instant::now();
-	executor.author_block(dataset);
+	executor.author_block(dataset).unwrap();
 	println!("Seq authoring took {:?}", start.elapsed());
 }
 
 #[allow(dead_code)]
-fn concurrent_stakers<D: tx_distribution::Distributer>() {
-	let mut executor = concurrent::ConcurrentExecutor::<Pool, D>::new(NUM_THREADS, false, None);
+fn concurrent_stakers(distributer: &str) {
+	let distributer = tx_distribution::by_name(distributer).expect("unknown distributer");
+	let mut executor =
+		concurrent::ConcurrentExecutor::<Pool>::new(NUM_THREADS, false, None, distributer);
 	let dataset = datasets::world_of_stakers(
 		&executor.master.runtime,
 		STAKERS_VALIDATORS,
@@ -51,19 +54,93 @@ fn concurrent_stakers<D: tx_distribution::Distributer>() {
 	let initial_state = executor.master.state.dump();
 
 	let start = std::time::Instant::now();
-	let (s1, block, _) = executor.author_block(dataset);
+	let (s1, block, _) = executor.author_block(dataset).unwrap();
 	println!("Concurrent authoring took {:?}", start.elapsed());
 
 	executor.clean();
 	executor.apply_state(initial_state);
-	let (s2, _) = executor.validate_block(block);
+	let (s2, _) = executor.validate_block(block).unwrap();
 	assert!(s1.state_eq(s2));
 }
 
+/// Set up a concurrent executor of `threads` workers that distributes the transactions by
+/// `distributer`, pinned as per `pinning`.
+///
+/// The benchmarks set up one executor per distributer and stream all of their blocks through it,
+/// like a long-lived validator does, rather than spawning new threads for each block.
+pub(crate) fn concurrent_executor(
+	distributer: &str,
+	threads: usize,
+	pinning: &affinity::Pinning,
+) -> concurrent::ConcurrentExecutor<Pool> {
+	let distributer = tx_distribution::by_name(distributer).expect("unknown distributer");
+	let mut executor =
+		concurrent::ConcurrentExecutor::<Pool>::new(threads, false, None, distributer);
+	executor.pin(pinning).expect("Failed to pin the threads");
+	executor
+}
+
+/// The distributers passed via `--distributer <name>`, which can be repeated.
+///
+/// Exits if a name is not known to [`tx_distribution::by_name`].
+fn distributers_from_args() -> Vec<String> {
+	let mut distributers = vec![];
+	let mut args = std::env::args().skip(1);
+	while let Some(arg) = args.next() {
+		if arg != "--distributer" {
+			continue;
+		}
+		let name = args.next().unwrap_or_default();
+		if tx_distribution::by_name::<Pool>(&name).is_none() {
+			eprintln!(
+				"unknown distributer {:?}, expected one of {:?} or Adaptive:<base>",
+				name,
+				tx_distribution::DISTRIBUTERS
+			);
+			std::process::exit(1);
+		}
+		distributers.push(name);
+	}
+	distributers
+}
+
+/// The pinning passed via `--pinning <auto|unpinned|core,core,..>`.
+///
+/// If not given, the threads are pinned as per the detected topology, since scheduling noise
+/// dominates the variance of the benchmarks otherwise. Only on Linux though, since pinning is not
+/// supported anywhere else.
+///
+/// Exits if it cannot be parsed.
+fn pinning_from_args() -> affinity::Pinning {
+	let mut args = std::env::args()
+		.skip_while(|arg| arg != "--pinning")
+		.skip(1);
+	let pinning = match args.next().as_deref() {
+		None if cfg!(target_os = "linux") => Some(affinity::Pinning::Auto),
+		None | Some("unpinned") => Some(affinity::Pinning::Unpinned),
+		Some("auto") => Some(affinity::Pinning::Auto),
+		Some(cores) => cores
+			.split(',')
+			.map(|core| core.parse().ok())
+			.collect::<Option<Vec<_>>>()
+			.map(affinity::Pinning::Cores),
+	};
+	pinning.unwrap_or_else(|| {
+		eprintln!("invalid pinning, expected auto, unpinned or a comma separated list of cores");
+		std::process::exit(1);
+	})
+}
+
 fn main() {
 	logging::init_logger();
 
-	millionaires::millionaires_playground_bench();
-	// millionaires::growing_economy();
-	// middle_class::middle_class_playground_bench();
+	let distributers = distributers_from_args();
+	let distributers = distributers.iter().map(|d| d.as_str()).collect::<Vec<_>>();
+	let distributers = (!distributers.is_empty()).then_some(distributers.as_slice());
+	let pinning = pinning_from_args();
+
+	millionaires::millionaires_playground_bench(distributers, &pinning);
+	// millionaires::growing_economy_bench(distributers, &pinning);
+	// middle_class::middle_class_playground_bench(distributers, &pinning);
+	// distribution::distribution_scaling_bench(distributers);
 }
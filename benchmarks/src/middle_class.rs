@@ -1,70 +1,138 @@
-use crate::datasets;
+use crate::{datasets, timing::*};
 use csv::Writer;
-use executor::{concurrent::*, *};
-use std::time::Duration;
-use tx_distribution::ConnectedComponents;
+use executor::{concurrent::*, optimistic::*, types::AuthoringStats, *};
+
+/// The distributers used by [`middle_class_playground_bench`] if none are given.
+pub const MIDDLE_CLASS_PLAYGROUND_DISTRIBUTERS: [&str; 1] = ["ConnectedComponents"];
 
 macro_rules! bench_seq {
 	($members:expr, $lucky:expr, $txs:expr, $wtr:ident) => {
-		let (authoring, validation) = seq_middle_class_playground($members, $txs, $lucky);
-		let authoring_tps = ($txs as f64) / ((authoring.as_millis() as f64) / 1000f64);
-		let validation_tps = ($txs as f64) / ((validation.as_millis() as f64) / 1000f64);
-		$wtr.write_record(&[
-			"seq",
-			stringify!($members),
-			stringify!($lucky),
-			stringify!($txs),
-			&authoring.as_millis().to_string(),
-			&format!("{:.2}", authoring_tps),
-			&validation.as_millis().to_string(),
-			&format!("{:.2}", validation_tps),
-			])
+		let (authoring, validation, stats) = seq_middle_class_playground($members, $txs, $lucky);
+		$wtr.write_record(
+			[
+				"seq",
+				stringify!($members),
+				stringify!($lucky),
+				stringify!($txs),
+				&authoring.wall.as_millis().to_string(),
+				&authoring.cpu.as_millis().to_string(),
+				&format!("{:.2}", authoring.tps($txs)),
+				&validation.wall.as_millis().to_string(),
+				&validation.cpu.as_millis().to_string(),
+				&format!("{:.2}", validation.tps($txs)),
+			]
+			.iter()
+			.map(|field| field.to_string())
+			.chain(stats_record(&stats)),
+		)
 		.unwrap();
 		$wtr.flush().unwrap();
 	};
 }
 
 macro_rules! bench_concurrent {
-	($members:expr, $lucky:expr, $txs:expr, $dist:ty, $threads:expr, $wtr:ident) => {
-		let (authoring, validation) =
-			concurrent_middle_class_playground::<$dist>($members, $txs, $lucky, $threads);
-		$wtr.write_record(&[
-			concat!("Concurrent(", stringify!($dist), "-", $threads, ")"),
-			stringify!($members),
-			stringify!($lucky),
-			stringify!($txs),
-			&authoring.as_millis().to_string(),
-			&validation.as_millis().to_string(),
-			])
+	(
+		$members:expr,
+		$lucky:expr,
+		$txs:expr,
+		$dist:expr,
+		$executor:ident,
+		$wtr:ident
+	) => {
+		let (authoring, validation, stats) =
+			concurrent_middle_class_playground(&mut $executor, $members, $txs, $lucky);
+		$wtr.write_record(
+			[
+				&format!("Concurrent({}-{})", $dist, $executor.master.num_workers()),
+				stringify!($members),
+				stringify!($lucky),
+				stringify!($txs),
+				&authoring.wall.as_millis().to_string(),
+				&authoring.cpu.as_millis().to_string(),
+				&format!("{:.2}", authoring.tps($txs)),
+				&validation.wall.as_millis().to_string(),
+				&validation.cpu.as_millis().to_string(),
+				&format!("{:.2}", validation.tps($txs)),
+			]
+			.iter()
+			.map(|field| field.to_string())
+			.chain(stats_record(&stats)),
+		)
+		.unwrap();
+		$wtr.flush().unwrap();
+	};
+}
+
+macro_rules! bench_optimistic {
+	($members:expr, $lucky:expr, $txs:expr, $threads:expr, $wtr:ident) => {
+		let (authoring, validation, stats) =
+			optimistic_middle_class_playground($members, $txs, $lucky, $threads);
+		$wtr.write_record(
+			[
+				concat!("Optimistic(", $threads, ")"),
+				stringify!($members),
+				stringify!($lucky),
+				stringify!($txs),
+				&authoring.wall.as_millis().to_string(),
+				&authoring.cpu.as_millis().to_string(),
+				&format!("{:.2}", authoring.tps($txs)),
+				&validation.wall.as_millis().to_string(),
+				&validation.cpu.as_millis().to_string(),
+				&format!("{:.2}", validation.tps($txs)),
+			]
+			.iter()
+			.map(|field| field.to_string())
+			.chain(stats_record(&stats)),
+		)
 		.unwrap();
 		$wtr.flush().unwrap();
 	};
 }
 
 #[allow(dead_code)]
-pub fn middle_class_playground_bench() {
+pub fn middle_class_playground_bench(
+	distributers: Option<&[&str]>,
+	pinning: &affinity::Pinning,
+) {
 	let mut wtr = Writer::from_path("middle_class_playground.csv").unwrap();
-	wtr.write_record(&["middle_class_playground", "-", "-", "-", "-", "-", "-", "-"])
-		.unwrap();
-	wtr.write_record(&[
-		"type",
-		"members",
-		"lucky",
-		"transactions",
-		"authoring (ms)",
-		"authoring tps",
-		"validation (ms)",
-		"validation tps",
-	])
+	wtr.write_record(
+		["middle_class_playground", "-", "-", "-", "-", "-", "-", "-", "-", "-"]
+			.iter()
+			.chain(["-"; STATS_HEADER.len()].iter()),
+	)
+	.unwrap();
+	wtr.write_record(
+		[
+			"type",
+			"members",
+			"lucky",
+			"transactions",
+			"authoring (ms)",
+			"authoring cpu (ms)",
+			"authoring tps",
+			"validation (ms)",
+			"validation cpu (ms)",
+			"validation tps",
+		]
+		.iter()
+		.chain(STATS_HEADER.iter()),
+	)
 	.unwrap();
 
 	bench_seq!(1000, 250, 500, wtr);
 	bench_seq!(1000, 500, 500, wtr);
 	bench_seq!(1000, 1000, 500, wtr);
 
-	bench_concurrent!(1000, 250, 500, ConnectedComponents, 4, wtr);
-	bench_concurrent!(1000, 500, 500, ConnectedComponents, 4, wtr);
-	bench_concurrent!(1000, 1000, 500, ConnectedComponents, 4, wtr);
+	for dist in distributers.unwrap_or(&MIDDLE_CLASS_PLAYGROUND_DISTRIBUTERS) {
+		let mut executor = crate::concurrent_executor(dist, 4, pinning);
+		bench_concurrent!(1000, 250, 500, dist, executor, wtr);
+		bench_concurrent!(1000, 500, 500, dist, executor, wtr);
+		bench_concurrent!(1000, 1000, 500, dist, executor, wtr);
+	}
+
+	bench_optimistic!(1000, 250, 500, 4, wtr);
+	bench_optimistic!(1000, 500, 500, 4, wtr);
+	bench_optimistic!(1000, 1000, 500, 4, wtr);
 
 	wtr.flush().unwrap();
 }
@@ -73,7 +141,7 @@ fn seq_middle_class_playground(
 	members: usize,
 	transfers: usize,
 	lucky_members: usize,
-) -> (Duration, Duration) {
+) -> (Timing, Timing, AuthoringStats) {
 	let mut executor = sequential::SequentialExecutor::new();
 	let dataset = datasets::middle_class_playground(
 		&executor.runtime,
@@ -84,19 +152,16 @@ fn seq_middle_class_playground(
 	);
 	let initial_state = executor.runtime.state.dump();
 
-	let (valid, authoring_time, validation_time) =
-		executor.author_and_validate(dataset, Some(initial_state));
-	assert!(valid);
-	(authoring_time, validation_time)
+	author_and_validate(&mut executor, dataset, initial_state)
 }
 
-fn concurrent_middle_class_playground<D: tx_distribution::Distributer>(
+/// Runs on `executor`, which is left clean for the next run.
+fn concurrent_middle_class_playground(
+	executor: &mut ConcurrentExecutor<Pool>,
 	members: usize,
 	transfers: usize,
 	lucky_members: usize,
-	num_threads: usize,
-) -> (Duration, Duration) {
-	let mut executor = concurrent::ConcurrentExecutor::<Pool, D>::new(num_threads, false, None);
+) -> (Timing, Timing, AuthoringStats) {
 	let dataset = datasets::middle_class_playground(
 		&executor.master.runtime,
 		members,
@@ -106,10 +171,24 @@ fn concurrent_middle_class_playground<D: tx_distribution::Distributer>(
 	);
 	let initial_state = executor.master.state.dump();
 
-	let (valid, authoring_time, validation_time) =
-		executor.author_and_validate(dataset, Some(initial_state));
-	assert!(valid);
-	executor.master.run_terminate();
-	executor.master.join_all().unwrap();
-	(authoring_time, validation_time)
+	author_and_validate(executor, dataset, initial_state)
+}
+
+fn optimistic_middle_class_playground(
+	members: usize,
+	transfers: usize,
+	lucky_members: usize,
+	num_threads: usize,
+) -> (Timing, Timing, AuthoringStats) {
+	let mut executor = OptimisticExecutor::new(num_threads, None);
+	let dataset = datasets::middle_class_playground(
+		&executor.runtime,
+		members,
+		transfers,
+		100,
+		lucky_members,
+	);
+	let initial_state = executor.state.dump();
+
+	author_and_validate(&mut executor, dataset, initial_state)
 }
@@ -1,65 +1,116 @@
-use crate::datasets;
+use crate::{datasets, timing::*};
 use csv::Writer;
-use executor::{concurrent::*, sequential::*, *};
-use std::time::Duration;
-use tx_distribution::{ConnectedComponents, RoundRobin};
+use executor::{concurrent::*, optimistic::*, sequential::*, types::AuthoringStats, *};
 
 #[allow(dead_code)]
 const LOG_TARGET: &'static str = "millionaires_playground";
 
+/// The distributers used by [`growing_economy_bench`] if none are given.
+pub const GROWING_ECONOMY_DISTRIBUTERS: [&str; 3] = ["RoundRobin", "ConnectedComponents", "MinCut"];
+/// The distributers used by [`millionaires_playground_bench`] if none are given.
+pub const MILLIONAIRES_PLAYGROUND_DISTRIBUTERS: [&str; 2] = ["RoundRobin", "ConnectedComponents"];
+
 macro_rules! bench_seq {
 	($members:expr, $txs:expr, $wtr:ident) => {
-		let (authoring, validation) = seq_millionaires_playground($members, $txs);
-		let authoring_tps = ($txs as f64) / ((authoring.as_millis() as f64) / 1000f64);
-		let validation_tps = ($txs as f64) / ((validation.as_millis() as f64) / 1000f64);
-		$wtr.write_record(&[
-			"Sequential",
-			stringify!($members),
-			stringify!($txs),
-			&authoring.as_millis().to_string(),
-			&format!("{:.2}", authoring_tps),
-			&validation.as_millis().to_string(),
-			&format!("{:.2}", validation_tps),
-			])
+		let (authoring, validation, stats) = seq_millionaires_playground($members, $txs);
+		$wtr.write_record(
+			[
+				"Sequential",
+				stringify!($members),
+				stringify!($txs),
+				&authoring.wall.as_millis().to_string(),
+				&authoring.cpu.as_millis().to_string(),
+				&format!("{:.2}", authoring.tps($txs)),
+				&validation.wall.as_millis().to_string(),
+				&validation.cpu.as_millis().to_string(),
+				&format!("{:.2}", validation.tps($txs)),
+			]
+			.iter()
+			.map(|field| field.to_string())
+			.chain(stats_record(&stats)),
+		)
 		.unwrap();
 		$wtr.flush().unwrap();
 	};
 }
 
 macro_rules! bench_concurrent {
-	($members:expr, $txs:expr, $dist:ty, $threads:expr, $wtr:ident) => {
-		let (authoring, validation) =
-			concurrent_millionaires_playground::<$dist>($members, $txs, $threads);
-		let authoring_tps = ($txs as f64) / ((authoring.as_millis() as f64) / 1000f64);
-		let validation_tps = ($txs as f64) / ((validation.as_millis() as f64) / 1000f64);
-		$wtr.write_record(&[
-			concat!("Concurrent(", stringify!($dist), "-", $threads, ")"),
-			stringify!($members),
-			stringify!($txs),
-			&authoring.as_millis().to_string(),
-			&format!("{:.2}", authoring_tps),
-			&validation.as_millis().to_string(),
-			&format!("{:.2}", validation_tps),
-			])
+	($members:expr, $txs:expr, $dist:expr, $executor:ident, $wtr:ident) => {
+		let (authoring, validation, stats) =
+			concurrent_millionaires_playground(&mut $executor, $members, $txs);
+		$wtr.write_record(
+			[
+				&format!("Concurrent({}-{})", $dist, $executor.master.num_workers()),
+				stringify!($members),
+				stringify!($txs),
+				&authoring.wall.as_millis().to_string(),
+				&authoring.cpu.as_millis().to_string(),
+				&format!("{:.2}", authoring.tps($txs)),
+				&validation.wall.as_millis().to_string(),
+				&validation.cpu.as_millis().to_string(),
+				&format!("{:.2}", validation.tps($txs)),
+			]
+			.iter()
+			.map(|field| field.to_string())
+			.chain(stats_record(&stats)),
+		)
+		.unwrap();
+		$wtr.flush().unwrap();
+	};
+}
+
+macro_rules! bench_optimistic {
+	($members:expr, $txs:expr, $threads:expr, $wtr:ident) => {
+		let (authoring, validation, stats) =
+			optimistic_millionaires_playground($members, $txs, $threads);
+		$wtr.write_record(
+			[
+				concat!("Optimistic(", $threads, ")"),
+				stringify!($members),
+				stringify!($txs),
+				&authoring.wall.as_millis().to_string(),
+				&authoring.cpu.as_millis().to_string(),
+				&format!("{:.2}", authoring.tps($txs)),
+				&validation.wall.as_millis().to_string(),
+				&validation.cpu.as_millis().to_string(),
+				&format!("{:.2}", validation.tps($txs)),
+			]
+			.iter()
+			.map(|field| field.to_string())
+			.chain(stats_record(&stats)),
+		)
 		.unwrap();
 		$wtr.flush().unwrap();
 	};
 }
 
 #[allow(dead_code)]
-pub fn growing_economy_bench() {
+pub fn growing_economy_bench(
+	distributers: Option<&[&str]>,
+	pinning: &affinity::Pinning,
+) {
 	let mut wtr = Writer::from_path("growing_economy.csv").unwrap();
-	wtr.write_record(&["growing_economy", "-", "-", "-", "-", "-", "-"])
-		.unwrap();
-	wtr.write_record(&[
-		"type",
-		"members",
-		"transactions",
-		"authoring (ms)",
-		"authoring tps",
-		"validation (ms)",
-		"validation tps",
-	])
+	wtr.write_record(
+		["growing_economy", "-", "-", "-", "-", "-", "-", "-", "-"]
+			.iter()
+			.chain(["-"; STATS_HEADER.len()].iter()),
+	)
+	.unwrap();
+	wtr.write_record(
+		[
+			"type",
+			"members",
+			"transactions",
+			"authoring (ms)",
+			"authoring cpu (ms)",
+			"authoring tps",
+			"validation (ms)",
+			"validation cpu (ms)",
+			"validation tps",
+		]
+		.iter()
+		.chain(STATS_HEADER.iter()),
+	)
 	.unwrap();
 
 	bench_seq!(1000, 2000, wtr);
@@ -67,33 +118,44 @@ pub fn growing_economy_bench() {
 	bench_seq!(3000, 2000, wtr);
 	bench_seq!(4000, 2000, wtr);
 
-	bench_concurrent!(1000, 2000, RoundRobin, 4, wtr);
-	bench_concurrent!(2000, 2000, RoundRobin, 4, wtr);
-	bench_concurrent!(3000, 2000, RoundRobin, 4, wtr);
-	bench_concurrent!(4000, 2000, RoundRobin, 4, wtr);
-
-	bench_concurrent!(1000, 2000, ConnectedComponents, 4, wtr);
-	bench_concurrent!(2000, 2000, ConnectedComponents, 4, wtr);
-	bench_concurrent!(3000, 2000, ConnectedComponents, 4, wtr);
-	bench_concurrent!(4000, 2000, ConnectedComponents, 4, wtr);
+	for dist in distributers.unwrap_or(&GROWING_ECONOMY_DISTRIBUTERS) {
+		let mut executor = crate::concurrent_executor(dist, 4, pinning);
+		bench_concurrent!(1000, 2000, dist, executor, wtr);
+		bench_concurrent!(2000, 2000, dist, executor, wtr);
+		bench_concurrent!(3000, 2000, dist, executor, wtr);
+		bench_concurrent!(4000, 2000, dist, executor, wtr);
+	}
 
 	wtr.flush().unwrap();
 }
 
 #[allow(dead_code)]
-pub fn millionaires_playground_bench() {
+pub fn millionaires_playground_bench(
+	distributers: Option<&[&str]>,
+	pinning: &affinity::Pinning,
+) {
 	let mut wtr = Writer::from_path("millionaires_playground.csv").unwrap();
-	wtr.write_record(&["millionaires_playground", "-", "-", "-", "-", "-", "-"])
-		.unwrap();
-	wtr.write_record(&[
-		"type",
-		"members",
-		"transactions",
-		"authoring (ms)",
-		"authoring tps",
-		"validation (ms)",
-		"validation tps",
-	])
+	wtr.write_record(
+		["millionaires_playground", "-", "-", "-", "-", "-", "-", "-", "-"]
+			.iter()
+			.chain(["-"; STATS_HEADER.len()].iter()),
+	)
+	.unwrap();
+	wtr.write_record(
+		[
+			"type",
+			"members",
+			"transactions",
+			"authoring (ms)",
+			"authoring cpu (ms)",
+			"authoring tps",
+			"validation (ms)",
+			"validation cpu (ms)",
+			"validation tps",
+		]
+		.iter()
+		.chain(STATS_HEADER.iter()),
+	)
 	.unwrap();
 
 	bench_seq!(1000, 250, wtr);
@@ -101,44 +163,51 @@ pub fn millionaires_playground_bench() {
 	bench_seq!(1000, 1000, wtr);
 	bench_seq!(1000, 2000, wtr);
 
-	bench_concurrent!(1000, 250, RoundRobin, 4, wtr);
-	bench_concurrent!(1000, 500, RoundRobin, 4, wtr);
-	bench_concurrent!(1000, 1000, RoundRobin, 4, wtr);
-	bench_concurrent!(1000, 2000, RoundRobin, 4, wtr);
+	for dist in distributers.unwrap_or(&MILLIONAIRES_PLAYGROUND_DISTRIBUTERS) {
+		let mut executor = crate::concurrent_executor(dist, 4, pinning);
+		bench_concurrent!(1000, 250, dist, executor, wtr);
+		bench_concurrent!(1000, 500, dist, executor, wtr);
+		bench_concurrent!(1000, 1000, dist, executor, wtr);
+		bench_concurrent!(1000, 2000, dist, executor, wtr);
+	}
 
-	bench_concurrent!(1000, 250, ConnectedComponents, 4, wtr);
-	bench_concurrent!(1000, 500, ConnectedComponents, 4, wtr);
-	bench_concurrent!(1000, 1000, ConnectedComponents, 4, wtr);
-	bench_concurrent!(1000, 2000, ConnectedComponents, 4, wtr);
+	bench_optimistic!(1000, 250, 4, wtr);
+	bench_optimistic!(1000, 500, 4, wtr);
+	bench_optimistic!(1000, 1000, 4, wtr);
+	bench_optimistic!(1000, 2000, 4, wtr);
 
 	wtr.flush().unwrap();
 }
 
-fn seq_millionaires_playground(members: usize, transactions: usize) -> (Duration, Duration) {
+fn seq_millionaires_playground(members: usize, transactions: usize) -> (Timing, Timing, AuthoringStats) {
 	let mut executor = SequentialExecutor::new();
 	let dataset = datasets::millionaires_playground(&executor.runtime, members, transactions);
 	let initial_state = executor.runtime.state.dump();
 
-	let (valid, authoring_time, validation_time) =
-		executor.author_and_validate(dataset, Some(initial_state));
-	assert!(valid);
-	(authoring_time, validation_time)
+	author_and_validate(&mut executor, dataset, initial_state)
 }
 
-fn concurrent_millionaires_playground<D: tx_distribution::Distributer>(
+/// Runs on `executor`, which is left clean for the next run.
+fn concurrent_millionaires_playground(
+	executor: &mut ConcurrentExecutor<Pool>,
 	members: usize,
 	transactions: usize,
-	num_threads: usize,
-) -> (Duration, Duration) {
-	let mut executor = ConcurrentExecutor::<Pool, D>::new(num_threads, false, None);
+) -> (Timing, Timing, AuthoringStats) {
 	let dataset =
 		datasets::millionaires_playground(&executor.master.runtime, members, transactions);
 	let initial_state = executor.master.state.dump();
 
-	let (valid, authoring_time, validation_time) =
-		executor.author_and_validate(dataset, Some(initial_state));
-	assert!(valid);
-	executor.master.run_terminate();
-	executor.master.join_all().unwrap();
-	(authoring_time, validation_time)
+	author_and_validate(executor, dataset, initial_state)
+}
+
+fn optimistic_millionaires_playground(
+	members: usize,
+	transactions: usize,
+	num_threads: usize,
+) -> (Timing, Timing, AuthoringStats) {
+	let mut executor = OptimisticExecutor::new(num_threads, None);
+	let dataset = datasets::millionaires_playground(&executor.runtime, members, transactions);
+	let initial_state = executor.state.dump();
+
+	author_and_validate(&mut executor, dataset, initial_state)
 }
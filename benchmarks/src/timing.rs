@@ -0,0 +1,113 @@
+use cpu_time::ProcessTime;
+use executor::{
+	affinity::Core,
+	types::{AuthoringStats, Transaction},
+	Executor,
+};
+use runtime::StateMap;
+use state::StateEq;
+use std::time::Duration;
+
+/// The time it took to carry out a task.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timing {
+	/// The wall-clock time, as reported by the executor.
+	pub wall: Duration,
+	/// The cpu time of the whole process, summed over all threads.
+	///
+	/// Unlike `wall`, this grows with the number of threads that are burning a core, be it for
+	/// useful work or not.
+	pub cpu: Duration,
+}
+
+impl Timing {
+	/// Transactions per second of `count` transactions, based on the wall-clock time.
+	pub fn tps(&self, count: usize) -> f64 {
+		(count as f64) / ((self.wall.as_millis() as f64) / 1000f64)
+	}
+}
+
+/// The names of the columns written by [`stats_record`].
+pub const STATS_HEADER: [&str; 10] = [
+	"orphans",
+	"pre-orphaned",
+	"pipelined orphans",
+	"logic errors",
+	"distribution (ms)",
+	"concurrent phase (ms)",
+	"collection (ms)",
+	"orphan phase (ms)",
+	"workers (received/executed/forwarded)",
+	"pinning (master/workers)",
+];
+
+/// The csv fields of the given authoring stats, as named by [`STATS_HEADER`].
+pub fn stats_record(stats: &AuthoringStats) -> Vec<String> {
+	let workers = stats
+		.workers
+		.values()
+		.map(|w| format!("{}/{}/{}", w.received, w.executed, w.forwarded))
+		.collect::<Vec<_>>()
+		.join(" ");
+	vec![
+		stats.orphaned.to_string(),
+		stats.pre_orphaned.to_string(),
+		stats.pipelined_orphans.to_string(),
+		stats.logic_errors.to_string(),
+		stats.distribution.as_millis().to_string(),
+		stats.concurrent_phase.as_millis().to_string(),
+		stats.collection.as_millis().to_string(),
+		stats.orphan_phase.as_millis().to_string(),
+		workers,
+		pinning_record(stats),
+	]
+}
+
+/// The cores of the master and the workers, or `-` if none of them is pinned.
+fn pinning_record(stats: &AuthoringStats) -> String {
+	let core = |core: Option<Core>| core.map_or("-".to_string(), |core| core.to_string());
+	if stats.master_core.is_none() && stats.workers.values().all(|w| w.core.is_none()) {
+		return "-".to_string();
+	}
+	let workers = stats
+		.workers
+		.values()
+		.map(|w| core(w.core))
+		.collect::<Vec<_>>()
+		.join(" ");
+	format!("{}/{}", core(stats.master_core), workers)
+}
+
+/// Same as [`Executor::author_and_validate`], but also measures the cpu time of each task.
+///
+/// Returns the statistics of authoring as well. Panics if the block is not valid.
+pub fn author_and_validate<E: Executor>(
+	executor: &mut E,
+	transactions: Vec<Transaction>,
+	initial_state: StateMap,
+) -> (Timing, Timing, AuthoringStats) {
+	executor.apply_state(initial_state.clone());
+	let start = ProcessTime::now();
+	let (authoring_state, block, stats) = executor
+		.author_block(transactions)
+		.expect("Authoring must not fail");
+	let authoring = Timing {
+		wall: stats.total,
+		cpu: start.elapsed(),
+	};
+	executor.clean();
+
+	executor.apply_state(initial_state);
+	let start = ProcessTime::now();
+	let (validation_state, validation_wall) = executor
+		.validate_block(block)
+		.expect("Authored block must be valid");
+	let validation = Timing {
+		wall: validation_wall,
+		cpu: start.elapsed(),
+	};
+	executor.clean();
+
+	assert!(authoring_state.state_eq(validation_state));
+	(authoring, validation, stats)
+}
@@ -0,0 +1,338 @@
+//! Pinning threads to cpu cores.
+//!
+//! Only Linux is supported. On any other platform, pinning fails with
+//! [`io::ErrorKind::Unsupported`] and the threads are left to the scheduler of the operating
+//! system.
+
+use std::{cmp::Reverse, fs, io, thread::JoinHandle};
+
+/// A logical cpu core, as numbered by the operating system.
+pub type Core = usize;
+
+/// How the threads of an executor are placed on the cpu cores.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Pinning {
+	/// Leave the placement to the scheduler of the operating system.
+	Unpinned,
+	/// Pin to the cores chosen by [`Topology::placement`] of the detected topology.
+	Auto,
+	/// Pin to the given cores, in order. If there are more threads than cores, the cores are
+	/// reused in a round robin fashion.
+	Cores(Vec<Core>),
+}
+
+impl Pinning {
+	/// The cores of `threads` threads, in order. Empty if the threads are not to be pinned.
+	pub fn cores(&self, threads: usize) -> Vec<Core> {
+		match self {
+			Self::Unpinned => vec![],
+			Self::Auto => Topology::detect().placement(threads),
+			Self::Cores(cores) => cores.iter().copied().cycle().take(threads).collect(),
+		}
+	}
+}
+
+/// The cpu cores of the machine that this process is allowed to run on, grouped by NUMA node.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Topology {
+	/// The cores of each node. Within a node, one core of each physical core comes first, and
+	/// their hyper-thread siblings come after.
+	nodes: Vec<Vec<Core>>,
+}
+
+impl Topology {
+	/// Detect the topology from sysfs.
+	///
+	/// If the NUMA nodes are not known, all cores are assumed to be on the same node. If the
+	/// siblings of a core are not known, they are guessed from the number of physical cores.
+	pub fn detect() -> Self {
+		let allowed = allowed_cores();
+		let mut nodes = numa_nodes()
+			.into_iter()
+			.map(|node| {
+				node.into_iter()
+					.filter(|core| allowed.contains(core))
+					.collect::<Vec<_>>()
+			})
+			.filter(|node| !node.is_empty())
+			.collect::<Vec<_>>();
+		if nodes.is_empty() {
+			nodes = vec![allowed];
+		}
+
+		let physical = num_cpus::get_physical().max(1);
+		let nodes = nodes
+			.into_iter()
+			.map(|node| {
+				siblings_last(node, |core| {
+					physical_core(core).unwrap_or((0, core % physical))
+				})
+			})
+			.collect();
+		Self { nodes }
+	}
+
+	/// The cores of `threads` threads, in order.
+	///
+	/// The threads are kept on the largest node as long as they fit, since they all share the same
+	/// state. Within a node, each thread gets its own physical core before any of them is put on a
+	/// hyper-thread sibling. If there are more threads than cores, the cores are reused.
+	pub fn placement(&self, threads: usize) -> Vec<Core> {
+		let mut nodes = self.nodes.iter().collect::<Vec<_>>();
+		nodes.sort_by_key(|node| Reverse(node.len()));
+		nodes
+			.into_iter()
+			.flatten()
+			.copied()
+			.cycle()
+			.take(threads)
+			.collect()
+	}
+}
+
+/// Order `cores` such that the first core of each physical core, as given by `physical`, comes
+/// first, then the second ones, and so on. The order is otherwise kept.
+fn siblings_last<K: Eq>(cores: Vec<Core>, physical: impl Fn(Core) -> K) -> Vec<Core> {
+	let mut groups: Vec<(K, Vec<Core>)> = vec![];
+	for core in cores {
+		let key = physical(core);
+		match groups.iter_mut().find(|(k, _)| *k == key) {
+			Some((_, group)) => group.push(core),
+			None => groups.push((key, vec![core])),
+		}
+	}
+	let depth = groups
+		.iter()
+		.map(|(_, group)| group.len())
+		.max()
+		.unwrap_or(0);
+	(0..depth)
+		.flat_map(|i| groups.iter().filter_map(move |(_, group)| group.get(i)))
+		.copied()
+		.collect()
+}
+
+/// Parse a list of cores in the sysfs format, e.g. `0-3,8,10-11`.
+fn parse_cpu_list(list: &str) -> Option<Vec<Core>> {
+	let mut cores = vec![];
+	for range in list.trim().split(',').filter(|range| !range.is_empty()) {
+		match range.split_once('-') {
+			Some((from, to)) => cores.extend(from.parse::<Core>().ok()?..=to.parse().ok()?),
+			None => cores.push(range.parse().ok()?),
+		}
+	}
+	Some(cores)
+}
+
+/// The cores of each NUMA node, as reported by sysfs.
+fn numa_nodes() -> Vec<Vec<Core>> {
+	let mut nodes = fs::read_dir("/sys/devices/system/node")
+		.into_iter()
+		.flatten()
+		.filter_map(|entry| {
+			let entry = entry.ok()?;
+			let id = entry
+				.file_name()
+				.to_str()?
+				.strip_prefix("node")?
+				.parse::<usize>()
+				.ok()?;
+			let list = fs::read_to_string(entry.path().join("cpulist")).ok()?;
+			Some((id, parse_cpu_list(&list)?))
+		})
+		.collect::<Vec<_>>();
+	nodes.sort_by_key(|(id, _)| *id);
+	nodes.into_iter().map(|(_, cores)| cores).collect()
+}
+
+/// The package and the id of the physical core of the given core, as reported by sysfs.
+fn physical_core(core: Core) -> Option<(usize, usize)> {
+	let read = |name: &str| {
+		fs::read_to_string(format!(
+			"/sys/devices/system/cpu/cpu{}/topology/{}",
+			core, name
+		))
+		.ok()?
+		.trim()
+		.parse::<usize>()
+		.ok()
+	};
+	Some((read("physical_package_id")?, read("core_id")?))
+}
+
+/// The cores that the current thread is allowed to run on.
+///
+/// Falls back to all of the cores if they cannot be queried.
+pub fn allowed_cores() -> Vec<Core> {
+	current_affinity().unwrap_or_else(|_| (0..num_cpus::get()).collect())
+}
+
+/// Pin the thread of `handle` to the given cores.
+pub fn pin_thread<T>(handle: &JoinHandle<T>, cores: &[Core]) -> io::Result<()> {
+	sys::set_thread_affinity(handle, cores)
+}
+
+/// The cores that the current thread is allowed to run on.
+pub fn current_affinity() -> io::Result<Vec<Core>> {
+	sys::current_affinity()
+}
+
+/// Pins the current thread to a core, and restores its previous affinity once dropped.
+#[derive(Debug)]
+pub struct PinCurrent {
+	previous: Vec<Core>,
+}
+
+impl PinCurrent {
+	/// Pin the current thread to `core`.
+	pub fn new(core: Core) -> io::Result<Self> {
+		let previous = current_affinity()?;
+		sys::set_current_affinity(&[core])?;
+		Ok(Self { previous })
+	}
+}
+
+impl Drop for PinCurrent {
+	fn drop(&mut self) {
+		let _ = sys::set_current_affinity(&self.previous);
+	}
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+	use super::Core;
+	use std::{io, mem, os::unix::thread::JoinHandleExt, thread::JoinHandle};
+
+	fn cpu_set(cores: &[Core]) -> io::Result<libc::cpu_set_t> {
+		// safety: `cpu_set_t` is a plain bit mask, for which all zeroes is the empty set.
+		let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+		for core in cores {
+			if *core >= libc::CPU_SETSIZE as usize {
+				return Err(io::Error::from(io::ErrorKind::InvalidInput));
+			}
+			unsafe { libc::CPU_SET(*core, &mut set) };
+		}
+		Ok(set)
+	}
+
+	pub fn set_thread_affinity<T>(handle: &JoinHandle<T>, cores: &[Core]) -> io::Result<()> {
+		let set = cpu_set(cores)?;
+		let thread = handle.as_pthread_t();
+		match unsafe {
+			libc::pthread_setaffinity_np(thread, mem::size_of::<libc::cpu_set_t>(), &set)
+		} {
+			0 => Ok(()),
+			errno => Err(io::Error::from_raw_os_error(errno)),
+		}
+	}
+
+	pub fn set_current_affinity(cores: &[Core]) -> io::Result<()> {
+		let set = cpu_set(cores)?;
+		match unsafe { libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) } {
+			0 => Ok(()),
+			_ => Err(io::Error::last_os_error()),
+		}
+	}
+
+	pub fn current_affinity() -> io::Result<Vec<Core>> {
+		let mut set = cpu_set(&[])?;
+		match unsafe { libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut set) } {
+			0 => Ok((0..libc::CPU_SETSIZE as usize)
+				.filter(|core| unsafe { libc::CPU_ISSET(*core, &set) })
+				.collect()),
+			_ => Err(io::Error::last_os_error()),
+		}
+	}
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+	use super::Core;
+	use std::{io, thread::JoinHandle};
+
+	fn unsupported() -> io::Error {
+		io::Error::new(
+			io::ErrorKind::Unsupported,
+			"Pinning is only supported on Linux.",
+		)
+	}
+
+	pub fn set_thread_affinity<T>(_: &JoinHandle<T>, _: &[Core]) -> io::Result<()> {
+		Err(unsupported())
+	}
+
+	pub fn set_current_affinity(_: &[Core]) -> io::Result<()> {
+		Err(unsupported())
+	}
+
+	pub fn current_affinity() -> io::Result<Vec<Core>> {
+		Err(unsupported())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_cpu_list_works() {
+		assert_eq!(
+			parse_cpu_list("0-3,8,10-11\n"),
+			Some(vec![0, 1, 2, 3, 8, 10, 11])
+		);
+		assert_eq!(parse_cpu_list("5"), Some(vec![5]));
+		assert_eq!(parse_cpu_list(""), Some(vec![]));
+		assert_eq!(parse_cpu_list("0-x"), None);
+	}
+
+	#[test]
+	fn siblings_come_last() {
+		// 4 physical cores with 2 hyper-threads each, numbered as Linux usually does.
+		let cores = (0..8).collect::<Vec<_>>();
+		assert_eq!(
+			siblings_last(cores, |core| core % 4),
+			vec![0, 1, 2, 3, 4, 5, 6, 7]
+		);
+
+		// siblings numbered next to one another.
+		let cores = (0..8).collect::<Vec<_>>();
+		assert_eq!(
+			siblings_last(cores, |core| core / 2),
+			vec![0, 2, 4, 6, 1, 3, 5, 7]
+		);
+	}
+
+	#[test]
+	fn placement_prefers_the_largest_node() {
+		let topology = Topology {
+			nodes: vec![vec![0, 1], vec![2, 3, 4, 5]],
+		};
+		assert_eq!(topology.placement(3), vec![2, 3, 4]);
+		assert_eq!(topology.placement(5), vec![2, 3, 4, 5, 0]);
+		assert_eq!(topology.placement(8), vec![2, 3, 4, 5, 0, 1, 2, 3]);
+	}
+
+	#[test]
+	fn explicit_cores_are_reused() {
+		assert_eq!(Pinning::Cores(vec![1, 3]).cores(3), vec![1, 3, 1]);
+		assert!(Pinning::Cores(vec![]).cores(3).is_empty());
+		assert!(Pinning::Unpinned.cores(3).is_empty());
+		assert_eq!(Pinning::Auto.cores(3).len(), 3);
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn pin_current_is_restored() {
+		let allowed = allowed_cores();
+		std::thread::spawn(move || {
+			let core = *allowed.last().unwrap();
+			{
+				let _pinned = PinCurrent::new(core).unwrap();
+				assert_eq!(current_affinity().unwrap(), vec![core]);
+			}
+			assert_eq!(current_affinity().unwrap(), allowed);
+		})
+		.join()
+		.unwrap();
+	}
+}
@@ -1,23 +1,32 @@
 use crate::{
+	affinity::{self, Core},
 	concurrent::tx_distribution::Distributer,
 	pool::*,
-	types::{ExecutionTag, Message, MessagePayload, TaskType},
+	types::{
+		is_expired, AuthoringStats, ExecutionTag, ExecutorError, InvalidBlock, Message,
+		MessagePayload, TaskType, WorkerStats,
+	},
 	Block, State, Transaction,
 };
 use logging::log;
 use primitives::*;
-use runtime::StateMap;
+use runtime::{RuntimeDispatchError, RuntimeDispatchSuccess, StateMap};
 use std::{
-	collections::BTreeMap,
+	collections::{BTreeMap, HashMap, HashSet},
 	sync::{
-		mpsc::{Receiver, SendError, Sender},
+		mpsc::{Receiver, Sender},
 		Arc,
 	},
-	thread::{self, JoinHandle},
+	thread::JoinHandle,
+	time::{Duration, Instant},
 };
 
 const LOG_TARGET: &'static str = "master";
 
+/// The time that the master waits for a message from the workers before checking if they are all
+/// still alive.
+const LIVENESS_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 /// A handle created for each worker thread.
 #[derive(Debug)]
 pub struct WorkerHandle {
@@ -25,18 +34,77 @@ pub struct WorkerHandle {
 	pub send: Sender<Message>,
 	/// The thread handle for this thread. Can be used to join all the threads.
 	pub handle: JoinHandle<()>,
+	/// The core that this thread is pinned to, if any.
+	pub core: Option<Core>,
 }
 
 impl WorkerHandle {
 	/// Create a new [`WorkerHandle`].
 	pub fn new(send: Sender<Message>, handle: JoinHandle<()>) -> Self {
-		Self { send, handle }
+		Self {
+			send,
+			handle,
+			core: None,
+		}
+	}
+}
+
+/// The access hints of the transactions that the workers might still execute.
+///
+/// This is used to prove that an orphan can be executed while the workers are still running: if
+/// none of its keys is accessed by any of these transactions, no worker will ever touch them again
+/// in this block. The proof is only as good as the access hints.
+#[derive(Debug, Default)]
+struct PendingAccess {
+	/// Number of pending transactions that access each key.
+	keys: HashMap<StateKey, usize>,
+	/// Number of pending transactions without any access hints. These could access anything.
+	unhinted: usize,
+	/// Orphans whose access hints are not to be trusted, because the distributer said so.
+	untrusted: HashSet<TransactionId>,
+}
+
+impl PendingAccess {
+	fn add(&mut self, mut keys: Vec<StateKey>) {
+		keys.sort_unstable();
+		keys.dedup();
+		if keys.is_empty() {
+			self.unhinted += 1;
+		}
+		keys.into_iter()
+			.for_each(|key| *self.keys.entry(key).or_default() += 1);
+	}
+
+	fn remove(&mut self, mut keys: Vec<StateKey>) {
+		keys.sort_unstable();
+		keys.dedup();
+		if keys.is_empty() {
+			self.unhinted -= 1;
+		}
+		keys.into_iter().for_each(|key| {
+			let count = self
+				.keys
+				.get_mut(&key)
+				.expect("Only pending keys are removed; qed.");
+			*count -= 1;
+			if *count == 0 {
+				self.keys.remove(&key);
+			}
+		});
+	}
+
+	/// True if the orphan `tx` with the given access `keys` accesses nothing that is pending.
+	fn is_free(&self, tx: TransactionId, keys: &[StateKey]) -> bool {
+		self.unhinted == 0
+			&& !keys.is_empty()
+			&& !self.untrusted.contains(&tx)
+			&& keys.iter().all(|key| !self.keys.contains_key(key))
 	}
 }
 
 /// The master thread.
 #[derive(Debug)]
-pub struct Master<P: TransactionPool<Transaction>, D: Distributer> {
+pub struct Master<P: TransactionPool<Transaction>> {
 	/// The id of the thread.
 	pub id: ThreadId,
 	/// A map to all the workers and a [`WorkerHandle`] per each of them.
@@ -49,18 +117,49 @@ pub struct Master<P: TransactionPool<Transaction>, D: Distributer> {
 	pub tx_pool: P,
 	/// The orphan pool.
 	pub orphan_pool: Vec<Transaction>,
+	/// Number of transactions at the front of `orphan_pool` that are already executed.
+	///
+	/// In authoring, orphans are executed as soon as they are proven not to conflict with any of
+	/// the transactions that the workers might still execute, and moved to the front.
+	pub executed_orphans: usize,
+	/// The transactions of the last authored block that were not executed, either because the
+	/// deadline passed or because their nonce was bad.
+	///
+	/// These are not part of the block, and should go back to the pool.
+	pub unexecuted: Vec<Transaction>,
 	/// A master runtime used for orphan phase and validation.
 	pub runtime: runtime::SequentialRuntime,
-	// Marker.
-	_marker: std::marker::PhantomData<D>,
+	/// The distributer of the transactions among the workers. Kept across blocks.
+	pub distributer: Box<dyn Distributer<P>>,
+	/// If set, any transaction that accesses a key which is accessed by more than this fraction
+	/// of the transactions of the block is sent to the orphan pool right away.
+	///
+	/// A key that is accessed by a single transaction is never hot, no matter how small the block.
+	///
+	/// Such transactions would most likely be forwarded between the workers and end up as
+	/// orphans anyhow.
+	pub hot_key_threshold: Option<f64>,
+	/// The statistics of the last authored block.
+	pub stats: AuthoringStats,
+	/// The core that the master is pinned to while authoring or validating, if any.
+	pub core: Option<Core>,
+	/// The access hints of the transactions that the workers might still execute.
+	pending_access: PendingAccess,
+	/// The deadline of the block that is being authored, if any.
+	deadline: Option<Instant>,
 }
 
-impl<P: TransactionPool<Transaction>, D: Distributer> Master<P, D> {
+impl<P: TransactionPool<Transaction>> Master<P> {
 	/// Create a new instance of the master queue.
+	///
+	/// The transaction pool holds at most `pool_capacity` transactions, if set. See
+	/// [`TransactionPool::bounded`].
 	pub fn new(
 		id: ThreadId,
 		from_workers: Receiver<Message>,
 		initial_state: Option<State>,
+		distributer: Box<dyn Distributer<P>>,
+		pool_capacity: Option<usize>,
 	) -> Self {
 		let state: Arc<State> = initial_state.unwrap_or_default().into();
 		let runtime = runtime::SequentialRuntime::new(Arc::clone(&state), id);
@@ -69,34 +168,115 @@ impl<P: TransactionPool<Transaction>, D: Distributer> Master<P, D> {
 			from_workers,
 			workers: Default::default(),
 			state,
-			tx_pool: P::new(),
+			tx_pool: pool_capacity.map_or_else(P::new, P::bounded),
 			orphan_pool: Default::default(),
+			executed_orphans: 0,
+			unexecuted: Default::default(),
 			runtime,
-			_marker: std::marker::PhantomData::<D>,
+			distributer,
+			hot_key_threshold: None,
+			stats: Default::default(),
+			core: None,
+			pending_access: Default::default(),
+			deadline: None,
 		}
 	}
 
-	/// Call [`Self::new`] with the current thread id.
-	pub fn new_from_thread(from_workers: Receiver<Message>, initial_state: Option<State>) -> Self {
-		let id = thread::current().id().as_u64().into();
-		Self::new(id, from_workers, initial_state)
-	}
-
 	/// Get the number of workers.
 	pub fn num_workers(&self) -> usize {
 		self.workers.len()
 	}
 
 	/// Send a particular message to all workers.
-	pub fn broadcast(&self, message: Message) -> Result<(), SendError<Message>> {
+	///
+	/// Sending to a worker only fails if its thread is gone, hence the error reports it as
+	/// panicked.
+	pub fn broadcast(&self, payload: MessagePayload) -> Result<(), ExecutorError> {
+		let message = Message::new(payload, self.id);
 		log!(info, "Broadcasting {:?}", message);
+		self.workers.iter().try_for_each(|(id, h)| {
+			h.send
+				.send(message.clone())
+				.map_err(|_| ExecutorError::WorkerPanicked(*id))
+		})
+	}
+
+	/// Find the first worker whose thread has finished.
+	///
+	/// Workers only finish upon receiving `Terminate`, so while a task is being carried out, any
+	/// finished worker must have panicked.
+	pub(crate) fn dead_worker(&self) -> Option<ThreadId> {
 		self.workers
 			.iter()
-			.map(|(_, h)| h.send.send(message.clone()))
-			.collect::<Result<_, _>>()
+			.find(|(_, h)| h.handle.is_finished())
+			.map(|(id, _)| *id)
+	}
+
+	/// Pin the current thread to `self.core`, until the returned guard is dropped.
+	///
+	/// Failing to pin is not fatal, the master merely runs wherever it is.
+	fn pin_current(&self) -> Option<affinity::PinCurrent> {
+		let core = self.core?;
+		affinity::PinCurrent::new(core)
+			.map_err(|why| log!(warn, "Failed to pin master to core {}: {}", core, why))
+			.ok()
+	}
+
+	/// Forget about the transactions of the last block, while keeping the state that they led to.
+	///
+	/// This must be done before each block, so that none of the transactions of the previous one
+	/// is carried over.
+	pub fn reset_block(&mut self) {
+		self.tx_pool.clear();
+		self.orphan_pool.clear();
+		self.executed_orphans = 0;
+		self.unexecuted.clear();
+	}
+
+	/// Wait until all of the workers are idle, and make sure that no message of a previous task is
+	/// left in any of the channels.
+	///
+	/// This takes two rounds of `Sync`. Once all of the workers have acknowledged the first one,
+	/// none of them is carrying out a task anymore. Yet a message that one worker sent to another
+	/// one right before acknowledging might be queued after the `Sync` of the receiver. It is
+	/// certainly queued before the second one, hence it is received while the worker waits for a
+	/// task, at which point it is reported back to master as an error.
+	///
+	/// Returns an error if any such stray message is found, either by the master or by a worker.
+	pub fn sync(&self) -> Result<(), ExecutorError> {
+		for _ in 0..2 {
+			self.broadcast(MessagePayload::Task(TaskType::Sync))?;
+			let mut synced = 0;
+			while synced != self.workers.len() {
+				match self.from_workers.recv_timeout(LIVENESS_CHECK_INTERVAL) {
+					Ok(Message {
+						payload: MessagePayload::Synced,
+						..
+					}) => synced += 1,
+					Ok(Message {
+						payload: MessagePayload::WorkerError,
+						from,
+					}) => return Err(ExecutorError::WorkerError(from)),
+					Ok(Message { payload, from }) => {
+						log!(error, "Stray message from {}: {:?}", from, payload);
+						return Err(ExecutorError::UnexpectedMessage(from));
+					}
+					Err(_) => {
+						if let Some(dead) = self.dead_worker() {
+							log!(error, "Worker {} panicked while syncing.", dead);
+							return Err(ExecutorError::WorkerPanicked(dead));
+						}
+					}
+				}
+			}
+		}
+		Ok(())
 	}
 
 	/// unpark all workers.
+	///
+	/// The workers block on their inbox rather than parking, so this is only needed for worker
+	/// threads that park themselves, such as the mock ones in tests.
 	pub fn unpark_all(&self) {
 		self.workers
 			.iter()
@@ -104,113 +284,198 @@ impl<P: TransactionPool<Transaction>, D: Distributer> Master<P, D> {
 	}
 
 	/// Run the logic needed to terminate all workers.
-	pub fn run_terminate(&self) {
+	pub fn run_terminate(&self) -> Result<(), ExecutorError> {
 		self.unpark_all();
-		self.broadcast(MessagePayload::Terminate.into()).unwrap();
-	}
-
-	/// The logic of the master thread for validating a block.
-	pub fn run_validate(&mut self, block: Block) {
-		self.unpark_all();
-
-		self.broadcast(MessagePayload::Task(TaskType::Validating).into())
-			.expect("Broadcast should work");
-
-		let mut buckets: BTreeMap<ThreadId, Vec<Transaction>> = Default::default();
-		let mut orphans: Vec<Transaction> = Default::default();
-		for tx in block.transactions {
-			match tx.tag {
-				ExecutionTag::Done(whom) => buckets.entry(whom).or_default().push(tx.clone()),
-				ExecutionTag::Orphan => orphans.push(tx.clone()),
-				_ => panic!("Unexpected tx status."),
-			}
-		}
+		self.broadcast(MessagePayload::Terminate)
 	}
 
 	/// The logic of the master thread for authoring a block.
-	pub fn run_author(&mut self) {
-		// unpark all workers.
-		self.unpark_all();
+	///
+	/// If any of the workers panics in the meantime, an error is returned instead of waiting for
+	/// it forever. The remaining workers are left in the middle of the task, hence the master
+	/// should be dropped afterwards.
+	///
+	/// Once the `deadline` passes, neither the workers nor the master execute any new
+	/// transaction. The ones that are left are moved to `self.unexecuted`, along with any
+	/// transaction that is not tagged as executed, e.g. due to a bad nonce.
+	pub fn run_author(&mut self, deadline: Option<Instant>) -> Result<(), ExecutorError> {
+		let _pinned = self.pin_current();
+		self.stats = AuthoringStats {
+			master_core: self.core,
+			..Default::default()
+		};
+		self.pending_access = Default::default();
+		self.unexecuted.clear();
+		self.deadline = deadline;
+		let result = self.author();
+		self.deadline = None;
+		result
+	}
 
+	fn author(&mut self) -> Result<(), ExecutorError> {
 		// send task.
-		self.broadcast(MessagePayload::Task(TaskType::Authoring).into())
-			.expect("Broadcast should work");
+		self.broadcast(MessagePayload::Task(TaskType::Authoring(self.deadline)))?;
 
 		// distribute transactions, mark all transactions by their _designated_ executor.
-		self.concurrent_phase();
+		let start = Instant::now();
+		self.concurrent_phase()?;
+		self.stats.concurrent_phase = start.elapsed();
 
 		// collect any `Orphan` or `Executed` events. This will update some of the transactions'
 		// `ExecutionStatus` to `Orphan` or `Done(_)` of some other thread than the designated one.
-		self.collection_phase();
+		let start = Instant::now();
+		self.collection_phase()?;
+		self.stats.collection = start.elapsed();
+		self.distributer.finish_block();
 
 		// Send terminate to all workers.
-		self.broadcast(MessagePayload::TaskDone.into())
-			.expect("broadcast should work; qed.");
+		self.broadcast(MessagePayload::TaskDone)?;
 
-		// Execute all the collected orphans.
-		self.execute_orphan_pool();
+		// Execute all the collected orphans, those of each origin in the order of their nonces.
+		let start = Instant::now();
+		let executed_orphans = self.executed_orphans;
+		self.orphan_pool[executed_orphans..].sort_by_key(|tx| tx.nonce);
+		self.stats.logic_errors += self.execute_orphan_pool();
+		self.stats.orphan_phase = start.elapsed();
+		self.stats.orphaned = self.orphan_pool.len();
+
+		let untagged = self
+			.tx_pool
+			.extract(|tx| !matches!(tx.tag, ExecutionTag::Done(_)));
+		self.unexecuted.extend(untagged.into_iter().map(|mut tx| {
+			tx.reset();
+			tx
+		}));
+		self.stats.unexecuted = self.unexecuted.len();
+		Ok(())
 	}
 
-	/// The logic of the master thread for validating a block.AccountId
+	/// Convert the `Done(_)` tags and the hops of the given transactions from the ids of the worker
+	/// threads to the index of their bucket.
+	///
+	/// The index of the bucket of each worker is its index in the ordered list of workers. This
+	/// makes the block independent of the thread ids of this particular executor.
+	pub fn tag_buckets(&self, transactions: &mut [Transaction]) {
+		let buckets = self
+			.workers
+			.keys()
+			.enumerate()
+			.map(|(index, worker)| (*worker, index as ThreadId))
+			.collect::<BTreeMap<_, _>>();
+		transactions.iter_mut().for_each(|tx| {
+			if let ExecutionTag::Done(worker) = tx.tag {
+				tx.tag = ExecutionTag::Done(
+					*buckets
+						.get(&worker)
+						.expect("Transaction must be executed by a known worker; qed."),
+				);
+			}
+			tx.hops.iter_mut().for_each(|worker| {
+				*worker = *buckets
+					.get(worker)
+					.expect("Transaction must be forwarded by a known worker; qed.")
+			});
+		});
+	}
+
+	/// The logic of the master thread for validating a block.
 	///
 	/// It assumes that the state is already clean. It applies the block on top of the state. The
 	/// transactions are assumed to be in the correct order already: All the orphan ones will be
-	/// executed in the same order, same as all the transactions belonging to a particular thread.
-	pub fn validate_block(&mut self, block: Block) -> StateMap {
-		self.unpark_all();
+	/// executed in the same order, same as all the transactions belonging to a particular bucket.
+	///
+	/// The `Done(_)` tags of the block are bucket indices (see [`Self::tag_buckets`]). The number
+	/// of buckets need not be the same as the number of workers of this master; buckets are
+	/// mapped to workers in a round robin fashion.
+	///
+	/// The workers execute their transactions with tainting enabled, where each key is tainted by
+	/// the bucket, not the worker. If any of them reports a conflict, then the transactions
+	/// assigned to different buckets were not independent and the block is rejected. The orphans
+	/// are not executed in this case.
+	///
+	/// The block is also rejected if any of its transactions, be it an orphan or not, has a bad
+	/// nonce.
+	///
+	/// A malformed block, i.e. one with a transaction that is neither executed nor an orphan, is
+	/// rejected before any of it is executed.
+	pub fn validate_block(&mut self, block: Block) -> Result<StateMap, ExecutorError> {
+		crate::pool::verify_block(&block.transactions)?;
+		let _pinned = self.pin_current();
+		if let Some(tx) = block
+			.transactions
+			.iter()
+			.find(|tx| !matches!(tx.tag, ExecutionTag::Done(_) | ExecutionTag::Orphan))
+		{
+			log!(warn, "Rejecting block due to unexpected tag of {:?}.", tx);
+			return Err(InvalidBlock::UnexpectedTag(tx.id).into());
+		}
 
-		self.broadcast(MessagePayload::Task(TaskType::Validating).into())
-			.expect("Broadcast should work");
+		self.broadcast(MessagePayload::Task(TaskType::Validating))?;
 
+		let workers = self.workers.iter().collect::<Vec<_>>();
 		for tx in block.transactions {
 			match tx.tag {
-				ExecutionTag::Done(owner) => {
-					self.workers
-						.get(&owner)
-						.expect("Worker should exist")
+				ExecutionTag::Done(bucket) => {
+					let (owner, handle) = workers[bucket as usize % workers.len()];
+					handle
 						.send
-						.send(MessagePayload::Transaction(tx).into())
-						.expect("Send should work");
+						.send(Message::new(MessagePayload::Transaction(tx), self.id))
+						.map_err(|_| ExecutorError::WorkerPanicked(*owner))?;
 				}
-				ExecutionTag::Orphan => {
-					self.orphan_pool.push(tx);
-				}
-				_ => panic!("Transaction for validation need to be executed or orphan"),
+				_ => self.orphan_pool.push(tx),
 			}
 		}
 
 		// tell all workers that there will be no more incoming transactions.
-		self.broadcast(MessagePayload::TransactionDistributionDone.into())
-			.expect("Broadcast should work; qed.");
+		self.broadcast(MessagePayload::TransactionDistributionDone)?;
 
 		let mut workers_done = 0;
-		loop {
-			if let Ok(Message {
-				payload,
-				from: worker,
-			}) = self.from_workers.try_recv()
-			{
-				log!(
-					trace,
-					"message in collection phase of validating form {:?} => {:?}",
-					worker,
-					payload
-				);
-				match payload {
-					MessagePayload::ValidationReport => {
-						workers_done += 1;
+		let mut invalid = None;
+		while workers_done != self.workers.len() {
+			match self.from_workers.recv_timeout(LIVENESS_CHECK_INTERVAL) {
+				Ok(Message {
+					payload,
+					from: worker,
+				}) => {
+					log!(
+						trace,
+						"message in collection phase of validating form {:?} => {:?}",
+						worker,
+						payload
+					);
+					match payload {
+						MessagePayload::ValidationReport(maybe_invalid) => {
+							workers_done += 1;
+							invalid = invalid.or(maybe_invalid);
+						}
+						MessagePayload::WorkerError => {
+							return Err(ExecutorError::WorkerError(worker))
+						}
+						_ => return Err(ExecutorError::UnexpectedMessage(worker)),
+					}
+				}
+				Err(_) => {
+					if let Some(dead) = self.dead_worker() {
+						log!(error, "Worker {} panicked during validation.", dead);
+						return Err(ExecutorError::WorkerPanicked(dead));
 					}
-					_ => panic!("Unexpected message type"),
 				}
-			}
-			if workers_done == self.workers.len() {
-				break;
 			}
 		}
 
+		if let Some(why) = invalid {
+			log!(warn, "Rejecting block due to {:?}.", why);
+			return Err(why.into());
+		}
+
+		self.unexecuted.clear();
 		self.execute_orphan_pool();
+		if let Some(tx) = self.unexecuted.first() {
+			log!(warn, "Rejecting block due to bad nonce of orphan {:?}.", tx);
+			return Err(InvalidBlock::BadNonce(tx.id).into());
+		}
 
-		self.state.dump()
+		Ok(self.state.dump())
 	}
 
 	/// Logic of the collection phase of the execution.
@@ -222,19 +487,34 @@ impl<P: TransactionPool<Transaction>, D: Distributer> Master<P, D> {
 	///
 	/// This process ends when we have received all `AuthoringReport`. Then, we know exactly how
 	/// many `Executed` events we must wait for. Only then, we can terminate.
-	fn collection_phase(&mut self) {
+	///
+	/// Both `Orphan` and `Executed` events are reported to the distributer as conflicts. The
+	/// counts of each worker are recorded in `self.stats`.
+	///
+	/// Meanwhile, any orphan that is proven not to conflict with the remaining transactions of the
+	/// workers is executed right away. The rest are left for the orphan phase.
+	///
+	/// Fails if any of the workers panics before all of the reports are received.
+	fn collection_phase(&mut self) -> Result<(), ExecutorError> {
 		let mut executed_workers = 0;
 		let mut executed_local = 0;
 		let mut forwarded = 0;
 		let mut reported = 0;
+		let mut skipped = 0;
+		let mut skipped_forwarded = 0;
+		// counted as they arrive, because orphans with a bad nonce leave the orphan pool.
+		let mut orphaned = 0;
 		let total = self.tx_pool.len();
+		// transactions that were never sent to the workers.
+		let pre_orphaned = self.orphan_pool.len();
 		let workers_len = self.workers.len();
 
+		self.execute_free_orphans();
 		loop {
 			if let Ok(Message {
 				payload,
 				from: worker,
-			}) = self.from_workers.try_recv()
+			}) = self.from_workers.recv_timeout(LIVENESS_CHECK_INTERVAL)
 			{
 				log!(
 					trace,
@@ -243,68 +523,141 @@ impl<P: TransactionPool<Transaction>, D: Distributer> Master<P, D> {
 					payload
 				);
 				match payload {
-					MessagePayload::AuthoringReport(e, f) => {
+					MessagePayload::AuthoringReport(e, f, l) => {
 						executed_workers += e;
 						forwarded += f;
 						reported += 1;
+						let stats = self.stats.workers.entry(worker).or_default();
+						stats.executed += e;
+						stats.forwarded += f;
+						self.stats.logic_errors += l;
+
+						// if nothing was forwarded, all of the transactions of this worker are
+						// executed, and it will only execute the ones of the other workers.
+						if f == 0 {
+							let done = self
+								.tx_pool
+								.iter()
+								.filter(|t| t.tag == ExecutionTag::Done(worker))
+								.map(|t| self.runtime.validate(&t.function, t.signature.0))
+								.collect::<Vec<_>>();
+							done.into_iter()
+								.for_each(|keys| self.pending_access.remove(keys));
+							self.execute_free_orphans();
+						}
 					}
-					MessagePayload::WorkerOrphan(tid) => {
+					MessagePayload::WorkerOrphan(tid, hops) => {
 						let mut orphan = self
 							.tx_pool
-							.remove(|t| t.id == tid)
-							.expect("Transaction must exist in the pool");
+							.remove_by_id(tid)
+							.ok_or(ExecutorError::UnexpectedMessage(worker))?;
 						orphan.tag = ExecutionTag::Orphan;
+						orphaned += 1;
+						self.record_hops(&mut orphan, hops);
+						self.distributer.report_conflict(&self.runtime, &orphan);
+						self.pending_access
+							.remove(self.runtime.validate(&orphan.function, orphan.signature.0));
 						self.orphan_pool.push(orphan);
+						self.execute_free_orphans();
 					}
-					MessagePayload::WorkerExecuted(tid) => {
-						let idx = self
+					MessagePayload::WorkerSkipped(tid, hops) => {
+						let mut tx = self
 							.tx_pool
-							.iter()
-							.position(|t| t.id == tid)
-							.expect("Transaction must exist in the pool");
+							.remove_by_id(tid)
+							.ok_or(ExecutorError::UnexpectedMessage(worker))?;
+						skipped += 1;
+						// the ones with no hops are the designated ones of the worker, and are not
+						// accounted in any report.
+						if !hops.is_empty() {
+							skipped_forwarded += 1;
+						}
+						self.pending_access
+							.remove(self.runtime.validate(&tx.function, tx.signature.0));
+						tx.reset();
+						self.unexecuted.push(tx);
+						self.execute_free_orphans();
+					}
+					MessagePayload::WorkerExecuted(tid, outcome, hops, conflict) => {
+						self.stats.workers.entry(worker).or_default().executed += 1;
+						if let RuntimeDispatchSuccess::LogicError(_) = outcome {
+							self.stats.logic_errors += 1;
+						}
 
 						// Remove this transaction. Later on we add it to the end.
-						let mut t = self.tx_pool.remove_at(idx);
+						let mut t = self
+							.tx_pool
+							.remove_by_id(tid)
+							.ok_or(ExecutorError::UnexpectedMessage(worker))?;
+						self.record_hops(&mut t, hops);
+						// a transaction handed over to an idle worker is not a conflict.
+						if conflict {
+							self.distributer.report_conflict(&self.runtime, &t);
+						}
+						self.pending_access
+							.remove(self.runtime.validate(&t.function, t.signature.0));
+						self.execute_free_orphans();
 
 						log!(trace, "Updating owner of {:?} to {:?}", t, worker);
 						// initially, the transaction must have been marked with Done(_) of some
 						// other thread, and now we update it.
 						match t.tag {
-							ExecutionTag::Done(initial_worker) => {
-								assert_ne!(worker, initial_worker);
+							ExecutionTag::Done(initial_worker) if initial_worker != worker => {
 								t.tag = ExecutionTag::Done(worker);
 								executed_local += 1;
 							}
-							_ => panic!("Unexpected initial worker."),
+							_ => return Err(ExecutorError::UnexpectedMessage(worker)),
 						};
 
 						// Add it to the end. This ensures partial order, which is important.
-						self.tx_pool.push_back(t);
+						self.tx_pool.push_back(t)?;
 					}
-					_ => panic!("Unexpected message type at master."),
+					MessagePayload::WorkerError => return Err(ExecutorError::WorkerError(worker)),
+					_ => return Err(ExecutorError::UnexpectedMessage(worker)),
 				}
+			} else if let Some(dead) = self.dead_worker() {
+				log!(
+					error,
+					"Worker {} panicked during the collection phase.",
+					dead
+				);
+				return Err(ExecutorError::WorkerPanicked(dead));
 			}
 
 			// we all workers have said that we're done, and we've received enough `Executed`
 			// messages. At this point all transactions must be either reported as orphan, or
 			// executed.
-			if reported == workers_len && forwarded == (executed_local + self.orphan_pool.len()) {
+			if reported == workers_len
+				&& forwarded == (executed_local + orphaned + skipped_forwarded)
+			{
 				debug_assert_eq!(
 					total,
-					executed_local + executed_workers + self.orphan_pool.len()
+					executed_local + executed_workers + orphaned + skipped
 				);
 				log!(
 					info,
-					"Finishing Collection phase with [{} executed][{} forwarded][{} orphaned]",
+					"Finishing Collection phase with [{} executed][{} forwarded][{} orphaned][{} pre-orphaned][{} skipped]",
 					executed_workers,
 					executed_local,
-					self.orphan_pool.len()
+					orphaned,
+					pre_orphaned,
+					skipped,
 				);
-				break;
+				break Ok(());
 			}
 		}
 	}
 
+	/// Set the hops of a forwarded transaction, as reported by the last worker that received it.
+	///
+	/// The first hop is already accounted for in the `AuthoringReport` of the designated worker,
+	/// but the rest of them are only known here.
+	fn record_hops(&mut self, tx: &mut Transaction, hops: Vec<ThreadId>) {
+		hops.iter()
+			.skip(1)
+			.for_each(|worker| self.stats.workers.entry(*worker).or_default().forwarded += 1);
+		tx.hops = hops;
+	}
+
 	/// Logic of the initial phase of the execution.
 	///
 	/// First, we distribute all the transactions to the worker threads with some arbitrary
@@ -312,56 +665,157 @@ impl<P: TransactionPool<Transaction>, D: Distributer> Master<P, D> {
 	/// threads send a message indicating that.
 	///
 	/// At the end of this phase, all transactions in the `tx_pool` must have been marked by
-	/// `Executed(id)` where the id is their _designated worker_.
-	pub(crate) fn concurrent_phase(&mut self) {
+	/// `Executed(id)` where the id is their _designated worker_. The ones that the distributer
+	/// marked as `Orphan` are moved to the orphan pool right away.
+	///
+	/// Fails if a transaction is left without a designated worker. The workers are already
+	/// authoring at that point, hence the executor should be dropped afterwards.
+	pub(crate) fn concurrent_phase(&mut self) -> Result<(), ExecutorError> {
+		let start = Instant::now();
+		self.pre_orphan_hot_transactions();
 		self.distribute_transactions();
+		self.stats.distribution = start.elapsed();
+
+		let orphans = self.tx_pool.extract(|tx| tx.tag == ExecutionTag::Orphan);
+		if !orphans.is_empty() {
+			self.stats.pre_orphaned += orphans.len();
+			self.pending_access.untrusted = orphans.iter().map(|tx| tx.id).collect();
+			self.orphan_pool.extend(orphans);
+		}
+
+		let runtime = &self.runtime;
+		let pending_access = &mut self.pending_access;
+		self.tx_pool
+			.iter()
+			.for_each(|tx| pending_access.add(runtime.validate(&tx.function, tx.signature.0)));
 
 		let threads_and_txs = self
 			.tx_pool
-			.all()
 			.iter()
 			.map(|tx| match tx.tag {
-				ExecutionTag::Done(w) => (w, tx.clone()),
-				_ => panic!(
-					"A transaction has not been assigned. This is a bug in the distribution code"
-				),
+				ExecutionTag::Done(w) if self.workers.contains_key(&w) => Ok((w, tx.clone())),
+				_ => {
+					log!(
+						error,
+						"{:?} is not assigned to a worker. This is a bug in the distribution code.",
+						tx
+					);
+					Err(ExecutorError::UnassignedTransaction(tx.id))
+				}
 			})
-			.collect::<Vec<_>>();
+			.collect::<Result<Vec<_>, _>>()?;
 
 		// distribute transactions to all workers.
-		threads_and_txs.into_iter().for_each(|(tid, tx)| {
+		self.stats.workers = self
+			.workers
+			.iter()
+			.map(|(id, h)| {
+				let stats = WorkerStats {
+					core: h.core,
+					..Default::default()
+				};
+				(*id, stats)
+			})
+			.collect();
+		threads_and_txs.into_iter().try_for_each(|(tid, tx)| {
+			self.stats
+				.workers
+				.get_mut(&tid)
+				.expect("Worker thread must exist; qed.")
+				.received += 1;
 			self.workers
 				.get(&tid)
 				.expect("Worker thread must exist; qed.")
 				.send
-				.send(MessagePayload::Transaction(tx).into())
-				.expect("Sending should not fail; qed.")
-		});
+				.send(Message::new(MessagePayload::Transaction(tx), self.id))
+				.map_err(|_| ExecutorError::WorkerPanicked(tid))
+		})?;
 
 		// tell all workers that there will be no more incoming transactions.
-		self.broadcast(MessagePayload::TransactionDistributionDone.into())
-			.expect("Broadcast should work; qed.");
+		self.broadcast(MessagePayload::TransactionDistributionDone)
+	}
+
+	/// Execute the orphans that are proven not to conflict with the workers, and move them to the
+	/// executed front of the orphan pool.
+	///
+	/// The workers might still be running at this point. Nothing is executed past the deadline.
+	fn execute_free_orphans(&mut self) {
+		if is_expired(self.deadline) {
+			return;
+		}
+		let mut index = self.executed_orphans;
+		while index < self.orphan_pool.len() {
+			let tx = &self.orphan_pool[index];
+			let keys = self.runtime.validate(&tx.function, tx.signature.0);
+			if !self.pending_access.is_free(tx.id, &keys) {
+				index += 1;
+				continue;
+			}
+
+			let mut tx = self.orphan_pool.remove(index);
+			let outcome = match self.execute_in_master(&tx) {
+				Ok(outcome) => outcome,
+				// another transaction of the same origin must come first.
+				Err(expected) if tx.nonce > expected => {
+					self.orphan_pool.insert(index, tx);
+					index += 1;
+					continue;
+				}
+				Err(_) => {
+					tx.reset();
+					self.unexecuted.push(tx);
+					continue;
+				}
+			};
+			if let RuntimeDispatchSuccess::LogicError(_) = outcome {
+				self.stats.logic_errors += 1;
+			}
+			log!(
+				trace,
+				"Executed orphan {} while the workers are running.",
+				tx.id
+			);
+			self.orphan_pool.insert(self.executed_orphans, tx);
+			self.executed_orphans += 1;
+			self.stats.pipelined_orphans += 1;
+			index += 1;
+		}
 	}
 
-	/// Execute all the transactions in the orphan queue on top of the previous state.
+	/// Execute all the transactions in the orphan queue that are not executed yet on top of the
+	/// previous state.
+	///
+	/// At this point, we are sure that no other thread is alive. Returns the number of
+	/// transactions that were executed with a logical error.
 	///
-	/// At this point, we are sure that no other thread is alive.
-	pub(crate) fn execute_orphan_pool(&mut self) {
-		use runtime::{RuntimeDispatchSuccess, RuntimeDispatchSuccessCount};
+	/// Once the deadline passes, the rest of the orphans are moved to `self.unexecuted`, as well as
+	/// any orphan with a bad nonce.
+	pub(crate) fn execute_orphan_pool(&mut self) -> usize {
+		use runtime::RuntimeDispatchSuccessCount;
+		let pending = self.orphan_pool.split_off(self.executed_orphans);
 		log!(
 			info,
 			"Starting orphan phase with {} transactions.",
-			self.orphan_pool.len()
+			pending.len()
 		);
-		let mut outcomes: Vec<RuntimeDispatchSuccess> = Vec::with_capacity(self.orphan_pool.len());
-		for tx in self.orphan_pool.iter_mut() {
+		let mut outcomes: Vec<RuntimeDispatchSuccess> = Vec::with_capacity(pending.len());
+		for mut tx in pending {
 			debug_assert_eq!(tx.tag, ExecutionTag::Orphan);
-			let origin = tx.signature.0;
-			let _outcome = self
-				.runtime
-				.dispatch(tx.function.clone(), origin)
-				.expect("Executing transaction in the master runtime should never fail; qed");
-			outcomes.push(_outcome);
+			let outcome = if is_expired(self.deadline) {
+				None
+			} else {
+				self.execute_in_master(&tx).ok()
+			};
+			match outcome {
+				Some(outcome) => {
+					outcomes.push(outcome);
+					self.orphan_pool.push(tx);
+				}
+				None => {
+					tx.reset();
+					self.unexecuted.push(tx);
+				}
+			}
 		}
 
 		log!(
@@ -370,9 +824,88 @@ impl<P: TransactionPool<Transaction>, D: Distributer> Master<P, D> {
 			outcomes.ok_count(),
 			outcomes.logic_error_count()
 		);
+		self.executed_orphans = self.orphan_pool.len();
+		outcomes.logic_error_count()
+	}
+
+	/// Execute `tx` in the master's runtime.
+	///
+	/// Returns the expected nonce if the nonce of the transaction is bad, in which case nothing is
+	/// written.
+	fn execute_in_master(&self, tx: &Transaction) -> Result<RuntimeDispatchSuccess, Nonce> {
+		match self
+			.runtime
+			.dispatch_transaction(tx.function.clone(), tx.signature.0, tx.nonce)
+		{
+			Ok(outcome) => Ok(outcome),
+			Err(RuntimeDispatchError::BadNonce(expected)) => {
+				log!(
+					debug,
+					"Orphan {} has nonce {}, expected {}.",
+					tx.id,
+					tx.nonce,
+					expected
+				);
+				Err(expected)
+			}
+			Err(RuntimeDispatchError::Tainted(..)) => {
+				unreachable!("Executing transaction in the master runtime should never fail; qed")
+			}
+		}
+	}
+
+	/// Move all the transactions that access a hot key to the orphan pool, as per
+	/// `self.hot_key_threshold`.
+	///
+	/// The frequency of each key is counted from the access hints of the transactions in the pool.
+	/// A key must be accessed by at least two transactions to be hot.
+	pub(crate) fn pre_orphan_hot_transactions(&mut self) {
+		let threshold = match self.hot_key_threshold {
+			Some(threshold) => threshold,
+			None => return,
+		};
+
+		let tx_keys = self
+			.tx_pool
+			.iter()
+			.map(|tx| {
+				let mut keys = self.runtime.validate(&tx.function, tx.signature.0);
+				keys.sort_unstable();
+				keys.dedup();
+				keys
+			})
+			.collect::<Vec<_>>();
+		let mut frequency: HashMap<&StateKey, usize> = HashMap::new();
+		tx_keys
+			.iter()
+			.flatten()
+			.for_each(|key| *frequency.entry(key).or_default() += 1);
+
+		// in small blocks, the fraction alone might be less than one transaction.
+		let limit = (threshold * self.tx_pool.len() as f64).max(1.0);
+		let is_hot = tx_keys
+			.iter()
+			.map(|keys| keys.iter().any(|key| frequency[key] as f64 > limit))
+			.collect::<Vec<_>>();
+		if !is_hot.contains(&true) {
+			return;
+		}
+
+		let mut is_hot = is_hot.into_iter();
+		let hot = self.tx_pool.extract(|_| is_hot.next().unwrap_or_default());
+		self.stats.pre_orphaned = hot.len();
+		self.orphan_pool.extend(hot.into_iter().map(|mut tx| {
+			tx.set_orphan();
+			tx
+		}));
+		log!(
+			debug,
+			"Pre-orphaned {} transactions that access a hot key.",
+			self.stats.pre_orphaned
+		);
 	}
 
-	/// For now, round robin distribution.
+	/// Distribute the transactions with `self.distributer`.
 	///
 	/// This marks each transaction with the Done(_) of the assigned thread id.`
 	pub(crate) fn distribute_transactions(&mut self) {
@@ -383,25 +916,34 @@ impl<P: TransactionPool<Transaction>, D: Distributer> Master<P, D> {
 			.cloned()
 			.collect::<Vec<ThreadId>>();
 
-		D::distribute(&self.runtime, worker_ids.as_ref(), &mut self.tx_pool);
+		self.distributer
+			.distribute(&self.runtime, worker_ids.as_ref(), &mut self.tx_pool);
 	}
 
 	/// Join on all the workers.
 	///
 	/// The master terminates upon calling this.
-	pub fn join_all(self) -> std::thread::Result<()> {
+	pub fn join_all(mut self) -> std::thread::Result<()> {
+		self.join_workers()
+	}
+
+	/// Join on all the workers, and remove them from `self.workers`.
+	///
+	/// All of the workers are joined, even if some of them have panicked. The first panic is
+	/// returned.
+	fn join_workers(&mut self) -> std::thread::Result<()> {
 		log!(warn, "Joining all threads.");
-		self.workers
+		std::mem::take(&mut self.workers)
+			.into_values()
+			.map(|handle| handle.handle.join())
+			.collect::<Vec<_>>()
 			.into_iter()
-			.map(|(_, handle)| handle.handle.join())
-			.collect::<Result<_, _>>()
+			.collect()
 	}
 
 	/// A run method only for testing.
 	#[cfg(test)]
 	pub fn run_test(&self) {
-		self.unpark_all();
-
 		// receive from all workers.
 		let mut num_received = 0;
 		let num_workers = self.num_workers();
@@ -412,12 +954,31 @@ impl<P: TransactionPool<Transaction>, D: Distributer> Master<P, D> {
 		}
 
 		// send to all workers.
-		self.broadcast(Message::new_from_thread(MessagePayload::Test(
-			b"FromMaster".to_vec(),
-		)))
-		.unwrap();
+		self.broadcast(MessagePayload::Test(b"FromMaster".to_vec()))
+			.unwrap();
+	}
+}
+impl<P: TransactionPool<Transaction>> Drop for Master<P> {
+	/// Terminate and join all the workers that have not been joined yet.
+	///
+	/// Workers that are already gone, or panicked, are ignored.
+	fn drop(&mut self) {
+		if self.workers.is_empty() {
+			return;
+		}
+
+		let message = Message::new(MessagePayload::Terminate, self.id);
+		self.workers.values().for_each(|h| {
+			let _ = h.send.send(message.clone());
+		});
+		self.unpark_all();
+
+		if self.join_workers().is_err() {
+			log!(warn, "Some workers had panicked before master was dropped.");
+		}
 	}
 }
+
 #[cfg(test)]
 mod master_tests_single_worker {
 	use super::*;
@@ -431,13 +992,14 @@ mod master_tests_single_worker {
 
 	type Pool = VecPool<Transaction>;
 
-	fn test_master() -> (Master<Pool, RoundRobin>, Receiver<Message>, Sender<Message>) {
+	fn test_master() -> (Master<Pool>, Receiver<Message>, Sender<Message>) {
 		let (from_workers_tx, from_workers_rx) = channel();
-		let mut master = Master::<Pool, RoundRobin>::new(MASTER_ID, from_workers_rx, None);
+		let mut master =
+			Master::<Pool>::new(MASTER_ID, from_workers_rx, None, Box::new(RoundRobin), None);
 
 		let (to_worker_tx, to_worker_rx) = channel();
 		let handle = std::thread::spawn(move || {
-			thread::park();
+			std::thread::park();
 		});
 		master
 			.workers
@@ -449,6 +1011,7 @@ mod master_tests_single_worker {
 			master
 				.tx_pool
 				.push_back(Transaction::new_transfer(o, i as u32))
+				.unwrap();
 		}
 
 		(master, to_worker_rx, from_workers_tx)
@@ -458,7 +1021,7 @@ mod master_tests_single_worker {
 	fn initial_phase_works() {
 		let (mut master, worker_rx, _) = test_master();
 
-		master.concurrent_phase();
+		master.concurrent_phase().unwrap();
 
 		// 4 tx must arrive.
 		for _ in 0..NUM_TX {
@@ -482,11 +1045,14 @@ mod master_tests_single_worker {
 		// in a single worker setup it makes not much sense to have any sort of forwarding or
 		// orphans.
 		from_worker_tx
-			.send(MessagePayload::AuthoringReport(NUM_TX, 0).into())
+			.send(Message::new(
+				MessagePayload::AuthoringReport(NUM_TX, 0, 0),
+				WORKER_ID,
+			))
 			.unwrap();
 
 		// this must terminate eventually with the messages sent above.
-		master.collection_phase();
+		master.collection_phase().unwrap();
 	}
 }
 
@@ -503,20 +1069,17 @@ mod master_tests_multi_worker {
 
 	type Pool = VecPool<Transaction>;
 
-	fn test_master() -> (
-		Master<Pool, RoundRobin>,
-		Vec<Receiver<Message>>,
-		Sender<Message>,
-	) {
+	fn test_master() -> (Master<Pool>, Vec<Receiver<Message>>, Sender<Message>) {
 		let (from_workers_tx, from_workers_rx) = channel();
-		let mut master = Master::<Pool, RoundRobin>::new(MASTER_ID, from_workers_rx, None);
+		let mut master =
+			Master::<Pool>::new(MASTER_ID, from_workers_rx, None, Box::new(RoundRobin), None);
 
 		let mut worker_receivers = vec![];
 
 		(0..WORKER_IDS.len()).for_each(|i| {
 			let (to_worker_tx, to_worker_rx) = channel();
 			let handle = std::thread::spawn(move || {
-				thread::park();
+				std::thread::park();
 			});
 			master
 				.workers
@@ -526,7 +1089,7 @@ mod master_tests_multi_worker {
 
 		for i in 0..NUM_TX {
 			let tx = Transaction::new_transfer(random(), i as u32);
-			master.tx_pool.push_back(tx);
+			master.tx_pool.push_back(tx).unwrap();
 		}
 
 		// needed for everything to work well.
@@ -539,7 +1102,7 @@ mod master_tests_multi_worker {
 	fn initial_phase_works() {
 		let (mut master, worker_receivers, _) = test_master();
 
-		master.concurrent_phase();
+		master.concurrent_phase().unwrap();
 
 		// each thread must receive NUM_TX / WORKER_IDS.len() txs and one `TransactionDistributionDone`.
 		for rx in worker_receivers {
@@ -565,17 +1128,20 @@ mod master_tests_multi_worker {
 		let (mut master, _receivers, from_worker_tx) = test_master();
 
 		// in this case this makes not difference thou'.
-		master.concurrent_phase();
+		master.concurrent_phase().unwrap();
 
 		// each worker reports back that they've done NUM_TX/Len.
-		for _ in 0..WORKER_IDS.len() {
+		for worker in WORKER_IDS.iter() {
 			from_worker_tx
-				.send(MessagePayload::AuthoringReport(NUM_TX / WORKER_IDS.len(), 0).into())
+				.send(Message::new(
+					MessagePayload::AuthoringReport(NUM_TX / WORKER_IDS.len(), 0, 0),
+					*worker,
+				))
 				.unwrap();
 		}
 
 		// this must terminate eventually with the messages sent above.
-		master.collection_phase();
+		master.collection_phase().unwrap();
 	}
 
 	#[test]
@@ -597,23 +1163,134 @@ mod master_tests_multi_worker {
 	fn collection_phase_works_with_forwarded() {
 		let (mut master, _receivers, from_worker_tx) = test_master();
 
-		master.concurrent_phase();
+		master.concurrent_phase().unwrap();
 
 		// each worker reports back that they've done all except one.
-		for _ in 0..WORKER_IDS.len() {
+		for worker in WORKER_IDS.iter() {
 			from_worker_tx
-				.send(MessagePayload::AuthoringReport(NUM_TX / WORKER_IDS.len() - 1, 1).into())
+				.send(Message::new(
+					MessagePayload::AuthoringReport(NUM_TX / WORKER_IDS.len() - 1, 1, 0),
+					*worker,
+				))
 				.unwrap();
 		}
 
-		// each thread will report one `Executed(_)`. The ID is kinda arbitrary at this stage.
+		// each thread will report one `Executed(_)`. The ID is kinda arbitrary at this stage, but
+		// it must be reported by a thread other than the designated one.
 		for i in 0..WORKER_IDS.len() {
 			from_worker_tx
-				.send(MessagePayload::WorkerExecuted(i as TransactionId).into())
+				.send(Message::new(
+					MessagePayload::WorkerExecuted(
+						i as TransactionId,
+						RuntimeDispatchSuccess::Ok,
+						vec![WORKER_IDS[i]],
+						true,
+					),
+					WORKER_IDS[(i + 1) % WORKER_IDS.len()],
+				))
 				.unwrap();
 		}
 
 		// this must terminate eventually with the messages sent above.
-		master.collection_phase();
+		master.collection_phase().unwrap();
+	}
+
+	#[test]
+	fn orphans_are_pipelined_only_once_proven_free() {
+		let (mut master, _receivers, from_worker_tx) = test_master();
+		master.tx_pool = Pool::new();
+		master.hot_key_threshold = Some(0.25);
+
+		// all of the senders pay the hub, which is hot, hence they are all pre-orphaned.
+		let senders = (0..6).map(|_| random()).collect::<Vec<_>>();
+		let accounts = senders.iter().map(|s| s.public()).collect::<Vec<_>>();
+		for (id, sender) in senders.into_iter().enumerate() {
+			transaction_generator::endow_account(sender.public(), &master.runtime, 100);
+			master
+				.tx_pool
+				.push_back(transaction_generator::build_transfer(
+					id as TransactionId,
+					sender,
+					bob().public(),
+				))
+				.unwrap();
+		}
+
+		// the workers pay the first sender twice, and the next two once. Nothing that the workers
+		// execute touches the last three senders.
+		let paid = [0, 0, 1, 2];
+		for (i, j) in paid.iter().enumerate() {
+			master
+				.tx_pool
+				.push_back(transaction_generator::build_transfer(
+					(6 + i) as TransactionId,
+					random(),
+					accounts[*j],
+				))
+				.unwrap();
+		}
+		for id in 10..16 {
+			master
+				.tx_pool
+				.push_back(transaction_generator::build_transfer(
+					id,
+					random(),
+					random().public(),
+				))
+				.unwrap();
+		}
+
+		master.concurrent_phase().unwrap();
+		assert_eq!(master.stats.pre_orphaned, 6);
+		let executed_ids = |m: &Master<Pool>| {
+			m.orphan_pool[..m.executed_orphans]
+				.iter()
+				.map(|tx| tx.id)
+				.collect::<Vec<_>>()
+		};
+
+		// while all the workers are running, only the untouched senders are free.
+		master.execute_free_orphans();
+		assert_eq!(executed_ids(&master), vec![3, 4, 5]);
+
+		// the workers report in order, and an orphan is free once the last of the workers that pay
+		// its sender has reported.
+		let worker_of = |id: TransactionId| {
+			let tx = master.tx_pool.iter().find(|tx| tx.id == id).unwrap();
+			WORKER_IDS
+				.iter()
+				.position(|w| tx.tag == ExecutionTag::Done(*w))
+				.unwrap()
+		};
+		// the first sender is paid by two different workers.
+		assert_ne!(worker_of(6), worker_of(7));
+		let freed_after = |j: usize| {
+			paid.iter()
+				.enumerate()
+				.filter(|(_, p)| **p == j)
+				.map(|(i, _)| worker_of((6 + i) as TransactionId))
+				.max()
+				.unwrap()
+		};
+		let mut expected = vec![3, 4, 5, 0, 1, 2];
+		expected[3..].sort_by_key(|j| freed_after(*j as usize));
+
+		for worker in WORKER_IDS.iter() {
+			let executed = master
+				.tx_pool
+				.iter()
+				.filter(|tx| tx.tag == ExecutionTag::Done(*worker))
+				.count();
+			from_worker_tx
+				.send(Message::new(
+					MessagePayload::AuthoringReport(executed, 0, 0),
+					*worker,
+				))
+				.unwrap();
+		}
+		master.collection_phase().unwrap();
+
+		assert_eq!(master.stats.pipelined_orphans, 6);
+		assert_eq!(executed_ids(&master), expected);
 	}
 }
@@ -11,7 +11,12 @@ use std::{
 	time::{Duration, Instant},
 };
 
-use crate::{pool::*, types::*, Executor, State, StateMap};
+use crate::{
+	affinity::{self, Pinning},
+	pool::*,
+	types::*,
+	Executor, State, StateMap,
+};
 use master::*;
 use primitives::*;
 use std::thread;
@@ -20,13 +25,35 @@ use worker::*;
 
 const LOG_TARGET: &'static str = "concurrent-exec";
 
+/// The settings of a [`ConcurrentExecutor`] that are fixed once it is set up.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Settings {
+	/// The number of times that a worker may forward a transaction before it is orphaned. See
+	/// [`Worker::forward_budget`].
+	pub forward_budget: usize,
+	/// The maximum number of transactions in the pool of each block, if any.
+	///
+	/// The transactions that do not fit are left out of the block. Which ones those are is up to
+	/// the pool, e.g. [`IndexedPool`] keeps the ones with the highest tips.
+	pub pool_capacity: Option<usize>,
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Self {
+			forward_budget: DEFAULT_FORWARD_BUDGET,
+			pool_capacity: None,
+		}
+	}
+}
+
 /// A concurrent executor.
 #[derive(Debug)]
-pub struct ConcurrentExecutor<P: TransactionPool<Transaction>, D: Distributer> {
-	pub master: Master<P, D>,
+pub struct ConcurrentExecutor<P: TransactionPool<Transaction>> {
+	pub master: Master<P>,
 }
 
-impl<P: TransactionPool<Transaction>, D: Distributer> ConcurrentExecutor<P, D> {
+impl<P: TransactionPool<Transaction>> ConcurrentExecutor<P> {
 	/// Sets up a new concurrent executor.
 	///
 	/// This is basically a wrapper around a master struct with some utility function.
@@ -34,42 +61,99 @@ impl<P: TransactionPool<Transaction>, D: Distributer> ConcurrentExecutor<P, D> {
 	/// OIt is worth noting that this function defines the initial code of each worker thread. The
 	/// initial code is as such: The worker thread will be created, it will wait for a mandatory
 	/// initial message from the master, and then call either `run` or `test_run`. The main `run`
-	/// method of the worker will just sleep. See `Worker` for more info.
-	pub fn new(threads: usize, test_run: bool, initial_state: Option<State>) -> Self {
+	/// method of the worker will just wait for tasks. See `Worker` for more info.
+	///
+	/// The workers are given the logical ids `0..threads`, and the master is given `threads`.
+	/// These ids are used for tainting, tagging the transactions and routing the messages, hence
+	/// they are the same across different runs.
+	///
+	/// The transactions of each block are distributed among the workers by `distributer`, which is
+	/// kept across blocks.
+	pub fn new(
+		threads: usize,
+		test_run: bool,
+		initial_state: Option<State>,
+		distributer: Box<dyn Distributer<P>>,
+	) -> Self {
+		Self::with_settings(
+			threads,
+			test_run,
+			initial_state,
+			distributer,
+			Default::default(),
+		)
+	}
+
+	/// Same as [`Self::new`], but the workers may forward a transaction up to `forward_budget`
+	/// times before it is orphaned. See [`Worker::forward_budget`].
+	pub fn with_forward_budget(
+		threads: usize,
+		test_run: bool,
+		initial_state: Option<State>,
+		distributer: Box<dyn Distributer<P>>,
+		forward_budget: usize,
+	) -> Self {
+		let settings = Settings {
+			forward_budget,
+			..Default::default()
+		};
+		Self::with_settings(threads, test_run, initial_state, distributer, settings)
+	}
+
+	/// Same as [`Self::new`], with the given [`Settings`].
+	pub fn with_settings(
+		threads: usize,
+		test_run: bool,
+		initial_state: Option<State>,
+		distributer: Box<dyn Distributer<P>>,
+		settings: Settings,
+	) -> Self {
+		let Settings {
+			forward_budget,
+			pool_capacity,
+		} = settings;
+
 		// One queue for all workers to send to master.
 		let (workers_to_master_tx, workers_to_master_rx) = channel();
 
-		let mut master = Master::new_from_thread(workers_to_master_rx, initial_state);
+		let master_id = threads as ThreadId;
+		let mut master = Master::new(
+			master_id,
+			workers_to_master_rx,
+			initial_state,
+			distributer,
+			pool_capacity,
+		);
 
 		let mut to_workers: BTreeMap<ThreadId, Sender<Message>> = Default::default();
 
 		for i in 0..threads {
-			// clone the state and master id.
+			// clone the state.
 			let state_ptr = Arc::clone(&master.state);
-			let master_id = master.id;
+			let worker_id = i as ThreadId;
 
-			// one channel for the master to send to this worker.
-			let (master_to_worker_tx, master_to_worker_rx) = channel();
+			// one channel for the master and all other workers to send to this worker.
+			let (to_worker_tx, inbox_rx) = channel();
 			let worker_to_master_tx = Clone::clone(&workers_to_master_tx);
 
-			// one channel for other workers to send to this worker.
-			let (from_others_tx, from_others_rx) = channel();
-
 			let worker_handle = thread::Builder::new()
 				.name(format!("Worker#{}", i))
 				.spawn(move || {
 					// note that we are creating this inside a new thread.
-					let mut worker = Worker::new_from_thread(
+					let mut worker = Worker::new(
+						worker_id,
 						master_id,
 						state_ptr,
 						worker_to_master_tx,
-						master_to_worker_rx,
-						from_others_rx,
+						inbox_rx,
 					);
+					worker.forward_budget = forward_budget;
 
 					// wait for the master to send you the btree-map of the send queue to all other
 					// threads.
-					worker.wait_finalize_setup();
+					if worker.wait_finalize_setup().is_err() {
+						return;
+					}
 
 					// run
 					if test_run {
@@ -80,9 +164,8 @@ impl<P: TransactionPool<Transaction>, D: Distributer> ConcurrentExecutor<P, D> {
 				})
 				.expect("Failed to spawn a new worker thread.");
 
-			let worker_id = worker_handle.thread().id().as_u64().into();
-			to_workers.insert(worker_id, from_others_tx);
-			let handle = WorkerHandle::new(master_to_worker_tx, worker_handle);
+			to_workers.insert(worker_id, Clone::clone(&to_worker_tx));
+			let handle = WorkerHandle::new(to_worker_tx, worker_handle);
 			master.workers.insert(worker_id, handle);
 		}
 
@@ -90,32 +173,146 @@ impl<P: TransactionPool<Transaction>, D: Distributer> ConcurrentExecutor<P, D> {
 		logging::log!(info, "created {} worker threads.", threads);
 
 		master
-			.broadcast(MessagePayload::FinalizeSetup(to_workers.clone()).into())
+			.broadcast(MessagePayload::FinalizeSetup(to_workers.clone()))
 			.expect("Broadcast must works");
 
 		Self { master }
 	}
+
+	/// Pin the master and the workers to cpu cores, as per `pinning`.
+	///
+	/// The first core goes to the master, and the rest to the workers in the order of their ids.
+	/// The workers are pinned right away. The master is only pinned while it authors or validates
+	/// a block, so that any other thread spawned by the caller, e.g. the ones that verify the
+	/// signatures of the blocks, is not confined to its core.
+	///
+	/// The cores are recorded in the [`AuthoringStats`] of each block.
+	///
+	/// Threads that are not to be pinned are left alone, unless they were pinned before, hence
+	/// [`Pinning::Unpinned`] works on any platform.
+	pub fn pin(&mut self, pinning: &Pinning) -> std::io::Result<()> {
+		let mut cores = pinning.cores(self.master.num_workers() + 1).into_iter();
+		self.master.core = cores.next();
+		for handle in self.master.workers.values_mut() {
+			let previous = std::mem::replace(&mut handle.core, cores.next());
+			match (handle.core, previous) {
+				(Some(core), _) => affinity::pin_thread(&handle.handle, &[core])?,
+				(None, Some(_)) => {
+					affinity::pin_thread(&handle.handle, &affinity::allowed_cores())?
+				}
+				(None, None) => (),
+			}
+		}
+		logging::log!(info, "Pinned the threads as per {:?}.", pinning);
+		Ok(())
+	}
+
+	/// Author consecutive blocks, each on top of the state of the previous one.
+	///
+	/// All of the blocks are carried out by the same workers, hence they are only set up once. The
+	/// blocks are authored lazily, as the returned iterator is consumed. After each block, the
+	/// executor is synced (see [`Master::sync`]), so that a stray message fails the block that it
+	/// belongs to rather than a later one. The iterator ends after the first error, since the
+	/// executor is not usable anymore afterwards.
+	pub fn author_blocks<'a, I>(
+		&'a mut self,
+		blocks: I,
+	) -> impl Iterator<Item = Result<(StateMap, Block, AuthoringStats), ExecutorError>> + 'a
+	where
+		I: IntoIterator<Item = Vec<Transaction>>,
+		I::IntoIter: 'a,
+	{
+		let mut failed = false;
+		blocks.into_iter().map_while(move |transactions| {
+			if failed {
+				return None;
+			}
+			let outcome = self
+				.author_block(transactions)
+				.and_then(|outcome| self.master.sync().map(|_| outcome));
+			failed = outcome.is_err();
+			Some(outcome)
+		})
+	}
+
+	/// Author a block of the ready transactions of `pool`, highest tip first, within `limits`.
+	///
+	/// Afterwards, the pool is pruned against the new state, hence the included transactions are
+	/// dropped from it, and the future transactions that they unblock become ready. The ones that
+	/// are left out of the block stay in the pool.
+	pub fn author_ready(
+		&mut self,
+		pool: &mut ReadyPool<Transaction>,
+		limits: BlockLimits,
+	) -> Result<(StateMap, Block, AuthoringStats), ExecutorError> {
+		let transactions = pool.ready().into_iter().cloned().collect::<Vec<_>>();
+		let (state, block, stats, _) = self.build_block(transactions, limits)?;
+		let pruned = pool.prune(&self.master.runtime);
+		logging::log!(
+			debug,
+			"Pruned {} transactions from the ready pool, {} left.",
+			pruned,
+			pool.len()
+		);
+		Ok((state, block, stats))
+	}
 }
 
-impl<P: TransactionPool<Transaction>, D: Distributer> Executor for ConcurrentExecutor<P, D> {
+impl<P: TransactionPool<Transaction>> Executor for ConcurrentExecutor<P> {
 	fn author_block(
 		&mut self,
 		initial_transactions: Vec<Transaction>,
-	) -> (StateMap, Block, Duration) {
+	) -> Result<(StateMap, Block, AuthoringStats), ExecutorError> {
+		self.build_block(initial_transactions, Default::default())
+			.map(|(state, block, stats, _)| (state, block, stats))
+	}
+
+	fn build_block(
+		&mut self,
+		transactions: Vec<Transaction>,
+		limits: BlockLimits,
+	) -> Result<(StateMap, Block, AuthoringStats, Vec<Transaction>), ExecutorError> {
 		logging::log!(
 			info,
 			"📕 Authoring block with {} transactions.",
-			initial_transactions.len(),
+			transactions.len(),
 		);
-		// Validate and all of the transactions to the pool.
-		self.master
-			.tx_pool
-			.push_batch(initial_transactions.as_ref());
+		let start = Instant::now();
+		self.master.reset_block();
+		let (mut transactions, rest) = limits.split(transactions);
+		// anything known about a previous execution of the transactions is stale.
+		transactions.iter_mut().for_each(Transaction::reset);
+
+		// Validate and add all of the transactions to the pool. The invalid ones, and the ones
+		// whose id is already taken, are dropped. The ones that do not fit in a bounded pool,
+		// including the ones evicted by others, are left for a later block.
+		let outcomes = self.master.tx_pool.push_batch(transactions.as_ref());
+		let bounded = self.master.tx_pool.limit().is_some();
+		let mut left_out = vec![];
+		let mut invalid = 0;
+		for (tx, outcome) in transactions.into_iter().zip(outcomes) {
+			match outcome {
+				Ok(()) | Err(PoolError::PoolFull)
+					if bounded && self.master.tx_pool.get_by_id(tx.id).is_none() =>
+				{
+					left_out.push(tx)
+				}
+				Ok(()) => (),
+				Err(_) => invalid += 1,
+			}
+		}
+		if invalid > 0 {
+			logging::log!(
+				warn,
+				"Dropped {} transactions with an invalid signature or a duplicate id.",
+				invalid
+			);
+		}
 
 		// run.
+		let deadline = limits.deadline_from(start);
 		let start = Instant::now();
-		self.master.unpark_all();
-		self.master.run_author();
+		self.master.run_author(deadline)?;
 
 		// dump the state
 		let state = self.master.state.dump();
@@ -123,24 +320,34 @@ impl<P: TransactionPool<Transaction>, D: Distributer> Executor for ConcurrentExe
 		block
 			.transactions
 			.extend(self.master.orphan_pool.iter().cloned());
-
-		(state, block, start.elapsed())
+		self.master.tag_buckets(&mut block.transactions);
+
+		let stats = AuthoringStats {
+			total: start.elapsed(),
+			..self.master.stats.clone()
+		};
+		let mut unexecuted = std::mem::take(&mut self.master.unexecuted);
+		unexecuted.extend(left_out);
+		unexecuted.extend(rest);
+		Ok((state, block, stats, unexecuted))
 	}
 
 	fn clean(&mut self) {
-		self.master.tx_pool.clear();
+		self.master.reset_block();
 		self.master.state.unsafe_clean();
-		self.master.orphan_pool.clear();
 	}
 
-	fn validate_block(&mut self, block: Block) -> (StateMap, Duration) {
+	fn validate_block(&mut self, block: Block) -> Result<(StateMap, Duration), ExecutorError> {
 		logging::log!(
 			info,
 			"✅ Validating block with {} transactions. ",
 			block.transactions.len(),
 		);
 		let start = Instant::now();
-		(self.master.validate_block(block), start.elapsed())
+		self.master.reset_block();
+		self.master
+			.validate_block(block)
+			.map(|state| (state, start.elapsed()))
 	}
 
 	fn apply_state(&mut self, state: StateMap) {
@@ -156,14 +363,15 @@ mod concurrent_executor {
 	use crate::{pool::TransactionPool, *};
 	use logging::init_logger;
 	use primitives::testing::*;
-	use runtime::{balances::BalanceOf, ConcurrentRuntime, InitialStateGenerate};
+	use runtime::{
+		balances::{self, BalanceOf},
+		ConcurrentRuntime, InitialStateGenerate, OuterCall,
+	};
 	use types::transaction_generator;
 
 	#[test]
 	fn concurrent_executor_new_works() {
-		// TODO: this test is sometimes flaky:
-		// thread 'main' panicked at 'assertion failed: master.join_all().is_ok()', executor/src/concurrent/mod.rs:148:9
-		let executor = ConcurrentExecutor::<Pool, RoundRobin>::new(4, true, None);
+		let executor = ConcurrentExecutor::<Pool>::new(4, true, None, Box::new(RoundRobin));
 		let master = executor.master;
 		std::thread::sleep(std::time::Duration::from_millis(500));
 		assert_eq!(master.workers.len(), 4);
@@ -177,12 +385,12 @@ mod concurrent_executor {
 	fn empty_setup_works_authoring() {
 		init_logger();
 
-		let mut executor = ConcurrentExecutor::<Pool, RoundRobin>::new(3, false, None);
+		let mut executor = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
 		std::thread::sleep(std::time::Duration::from_millis(200));
 		assert_eq!(executor.master.workers.len(), 3);
 
-		executor.author_block(vec![]);
-		executor.master.run_terminate();
+		executor.author_block(vec![]).unwrap();
+		executor.master.run_terminate().unwrap();
 		assert!(executor.master.join_all().is_ok());
 	}
 
@@ -190,20 +398,17 @@ mod concurrent_executor {
 	fn empty_setup_validation_authoring() {
 		init_logger();
 
-		let mut executor = ConcurrentExecutor::<Pool, RoundRobin>::new(3, false, None);
+		let mut executor = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
 		std::thread::sleep(std::time::Duration::from_millis(200));
 		assert_eq!(executor.master.workers.len(), 3);
 
 		assert!(executor.author_and_validate(vec![], None).0);
-
-		executor.master.run_terminate();
-		assert!(executor.master.join_all().is_ok());
 	}
 
 	#[test]
 	fn validation_authoring_works_bank() {
 		logging::init_logger();
-		let mut executor = ConcurrentExecutor::<Pool, RoundRobin>::new(3, false, None);
+		let mut executor = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
 		let (transactions, accounts) = transaction_generator::bank(50, 100, 100);
 
 		let initial_state = InitialStateGenerate::new()
@@ -225,7 +430,7 @@ mod concurrent_executor {
 	fn multiple_tasks_works() {
 		init_logger();
 
-		let mut executor = ConcurrentExecutor::<Pool, RoundRobin>::new(3, false, None);
+		let mut executor = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
 
 		let (txs, accounts) = transaction_generator::bank(5, 20, 100);
 		accounts.iter().for_each(|acc| {
@@ -234,7 +439,7 @@ mod concurrent_executor {
 		std::thread::sleep(std::time::Duration::from_millis(200));
 		assert_eq!(executor.master.workers.len(), 3);
 
-		let (state1, block1, _) = executor.author_block(txs.clone());
+		let (state1, block1, _) = executor.author_block(txs.clone()).unwrap();
 		executor.clean();
 
 		// master queue must be empty.
@@ -250,7 +455,7 @@ mod concurrent_executor {
 				1000_000_000_000,
 			)
 		});
-		let (state2, block2, _) = executor.author_block(txs);
+		let (state2, block2, _) = executor.author_block(txs).unwrap();
 		executor.clean();
 
 		assert_eq!(executor.master.tx_pool.len(), 0);
@@ -273,7 +478,7 @@ mod concurrent_executor {
 		// bob -> dave, will be assigned to third worker.
 		let tx3 = transaction_generator::build_transfer(102, bob(), dave().public());
 
-		let executor = ConcurrentExecutor::<Pool, RoundRobin>::new(3, false, None);
+		let executor = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
 		let mut master = executor.master;
 		std::thread::sleep(std::time::Duration::from_millis(200));
 		assert_eq!(master.workers.len(), 3);
@@ -284,12 +489,11 @@ mod concurrent_executor {
 		transaction_generator::endow_account(dave().public(), &master.runtime, 1000);
 		transaction_generator::endow_account(eve().public(), &master.runtime, 1000);
 
-		master.tx_pool.push_back(tx1);
-		master.tx_pool.push_back(tx2);
-		master.tx_pool.push_back(tx3);
+		master.tx_pool.push_back(tx1).unwrap();
+		master.tx_pool.push_back(tx2).unwrap();
+		master.tx_pool.push_back(tx3).unwrap();
 
-		master.run_author();
-		master.run_terminate();
+		master.run_author(None).unwrap();
 
 		let alice_balance = BalanceOf::read(&master.runtime, alice().public())
 			.unwrap()
@@ -308,14 +512,12 @@ mod concurrent_executor {
 		assert_eq!(eve_balance, 990);
 		assert_eq!(bob_balance, 1000);
 		assert_eq!(dave_balance, 1020);
-
-		assert!(master.join_all().is_ok());
 	}
 
 	#[test]
 	fn pool_must_reorder_when_forward() {
 		init_logger();
-		let mut executor = ConcurrentExecutor::<Pool, RoundRobin>::new(2, false, None);
+		let mut executor = ConcurrentExecutor::<Pool>::new(2, false, None, Box::new(RoundRobin));
 
 		let txs_and_accounts = transaction_generator::random_transfers(4);
 
@@ -338,7 +540,7 @@ mod concurrent_executor {
 			.cloned()
 			.map(|(tx, _, _)| tx)
 			.collect();
-		let (_, block, _) = executor.author_block(txs);
+		let (_, block, _) = executor.author_block(txs).unwrap();
 
 		assert_eq!(
 			block
@@ -369,7 +571,7 @@ mod concurrent_executor {
 			.cloned()
 			.map(|(tx, _, _)| tx)
 			.collect();
-		let (_, block, _) = executor.author_block(txs);
+		let (_, block, _) = executor.author_block(txs).unwrap();
 
 		assert_eq!(
 			block
@@ -382,8 +584,1031 @@ mod concurrent_executor {
 		);
 	}
 
+	#[test]
+	fn validation_rejects_conflicting_buckets() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(2, false, None, Box::new(RoundRobin));
+		let workers = executor.master.workers.keys().cloned().collect::<Vec<_>>();
+
+		// both transactions write to the balance of bob, yet they are put in different buckets.
+		let mut tx1 = transaction_generator::build_transfer(1, alice(), bob().public());
+		let mut tx2 = transaction_generator::build_transfer(2, eve(), bob().public());
+		tx1.set_done(workers[0]);
+		tx2.set_done(workers[1]);
+
+		transaction_generator::endow_account(alice().public(), &executor.master.runtime, 1000);
+		transaction_generator::endow_account(eve().public(), &executor.master.runtime, 1000);
+
+		assert!(matches!(
+			executor.validate_block(vec![tx1, tx2].into()),
+			Err(ExecutorError::InvalidBlock(InvalidBlock::Conflict(tid))) if tid == 1 || tid == 2
+		));
+		executor.clean();
+	}
+
+	#[test]
+	fn validation_rejects_tampered_transactions() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(2, false, None, Box::new(RoundRobin));
+		let worker = *executor.master.workers.keys().next().unwrap();
+
+		let mut tx1 = transaction_generator::build_transfer(1, alice(), bob().public());
+		let mut tx2 = transaction_generator::build_transfer(2, eve(), dave().public());
+		tx1.set_done(worker);
+		tx2.set_done(worker);
+		transaction_generator::endow_account(alice().public(), &executor.master.runtime, 1000);
+		transaction_generator::endow_account(eve().public(), &executor.master.runtime, 1000);
+
+		// the tip is signed, so raising it after the fact invalidates the transaction.
+		let mut block: Block = vec![tx1, tx2].into();
+		block.transactions[1].tip += 1;
+		assert_eq!(
+			executor.validate_block(block.clone()).map(|_| ()),
+			Err(ExecutorError::InvalidBlock(InvalidBlock::BadSignature(2)))
+		);
+
+		// the block is rejected before anything is executed, so the untampered one still passes.
+		block.transactions[1].tip -= 1;
+		assert!(executor.validate_block(block).is_ok());
+		executor.clean();
+	}
+
+	#[test]
+	fn bounded_pool_leaves_out_lowest_tips() {
+		init_logger();
+		let settings = Settings {
+			pool_capacity: Some(3),
+			..Default::default()
+		};
+		let mut executor = ConcurrentExecutor::<Pool>::with_settings(
+			2,
+			false,
+			None,
+			Box::new(RoundRobin),
+			settings,
+		);
+
+		// each sender pays a different tip, to a recipient of its own.
+		let tips = [3, 0, 4, 1, 2];
+		let transactions = tips
+			.iter()
+			.enumerate()
+			.map(|(id, tip)| {
+				let sender = random();
+				transaction_generator::endow_account(
+					sender.public(),
+					&executor.master.runtime,
+					100,
+				);
+				let call = OuterCall::Balances(balances::Call::Transfer(random().public(), 10));
+				transaction_generator::sign_with_tip(id as TransactionId, call, 0, *tip, &sender)
+			})
+			.collect::<Vec<_>>();
+
+		let ids = |txs: &[Transaction]| {
+			let mut ids = txs.iter().map(|tx| tx.id).collect::<Vec<_>>();
+			ids.sort();
+			ids
+		};
+		let (_, block, _, unexecuted) = executor
+			.build_block(transactions, Default::default())
+			.unwrap();
+		assert_eq!(ids(&block.transactions), vec![0, 2, 4]);
+		assert_eq!(ids(&unexecuted), vec![1, 3]);
+
+		// the ones left out are not lost, and make it into the next block.
+		let (_, next, _, unexecuted) = executor
+			.build_block(unexecuted, Default::default())
+			.unwrap();
+		assert_eq!(ids(&next.transactions), vec![1, 3]);
+		assert!(unexecuted.is_empty());
+	}
+
+	#[test]
+	fn validation_rejects_unexecuted_transactions() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(2, false, None, Box::new(RoundRobin));
+		let (transactions, accounts) = transaction_generator::bank(10, 20, 100);
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				accounts
+					.into_iter()
+					.for_each(|acc| transaction_generator::endow_account(acc, rt, 100_000))
+			})
+			.build();
+
+		// a block that was never authored carries no execution tags.
+		let block: Block = transactions.clone().into();
+		assert!(matches!(
+			executor.validate_block(block),
+			Err(ExecutorError::InvalidBlock(InvalidBlock::UnexpectedTag(_)))
+		));
+
+		// the validator is still usable afterwards.
+		executor.clean();
+		assert!(
+			executor
+				.author_and_validate(transactions, Some(initial_state))
+				.0
+		);
+	}
+
+	#[test]
+	fn build_block_respects_size_and_weight_limits() {
+		init_logger();
+		let (transactions, accounts) = transaction_generator::bank(50, 100, 100);
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				accounts
+					.into_iter()
+					.for_each(|acc| transaction_generator::endow_account(acc, rt, 100_000))
+			})
+			.build();
+
+		// each transfer weighs 6.
+		for limits in [
+			BlockLimits {
+				max_transactions: Some(20),
+				..Default::default()
+			},
+			BlockLimits {
+				max_weight: Some(20 * 6 + 5),
+				..Default::default()
+			},
+		] {
+			let mut author = ConcurrentExecutor::<Pool>::new(4, false, None, Box::new(RoundRobin));
+			author.apply_state(initial_state.clone());
+			let (authoring_state, block, _, unexecuted) =
+				author.build_block(transactions.clone(), limits).unwrap();
+			assert_eq!(block.transactions.len(), 20);
+			assert_eq!(unexecuted, transactions[20..].to_vec());
+
+			let mut validator =
+				ConcurrentExecutor::<Pool>::new(2, false, None, Box::new(RoundRobin));
+			validator.apply_state(initial_state.clone());
+			let (validation_state, _) = validator.validate_block(block).unwrap();
+			assert!(validation_state.state_eq(authoring_state));
+		}
+	}
+
+	#[test]
+	fn build_block_past_deadline_executes_nothing() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(4, false, None, Box::new(RoundRobin));
+		let (transactions, accounts) = transaction_generator::bank(50, 100, 100);
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				accounts
+					.into_iter()
+					.for_each(|acc| transaction_generator::endow_account(acc, rt, 100_000))
+			})
+			.build();
+		executor.apply_state(initial_state.clone());
+
+		let limits = BlockLimits {
+			deadline: Some(Duration::ZERO),
+			..Default::default()
+		};
+		let (state, block, stats, mut unexecuted) =
+			executor.build_block(transactions.clone(), limits).unwrap();
+		assert!(block.transactions.is_empty());
+		assert_eq!(stats.unexecuted, transactions.len());
+		assert!(state.state_eq(initial_state));
+
+		// they all go back to the pool as they were.
+		unexecuted.sort_by_key(|tx| tx.id);
+		assert_eq!(unexecuted, transactions);
+
+		// and can be included in the next block.
+		let (_, block, _, unexecuted) = executor
+			.build_block(unexecuted, Default::default())
+			.unwrap();
+		assert_eq!(block.transactions.len(), transactions.len());
+		assert!(unexecuted.is_empty());
+	}
+
+	#[test]
+	fn build_block_within_deadline_is_valid() {
+		init_logger();
+		let (transactions, accounts) = transaction_generator::bank(50, 100, 100);
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				accounts
+					.into_iter()
+					.for_each(|acc| transaction_generator::endow_account(acc, rt, 100_000))
+			})
+			.build();
+
+		let author = |threads| {
+			let mut author =
+				ConcurrentExecutor::<Pool>::new(threads, false, None, Box::new(RoundRobin));
+			author.apply_state(initial_state.clone());
+			author
+		};
+
+		for threads in [1, 4] {
+			// the deadlines are fractions of the time it takes to build the whole block, so that
+			// the block is cut at different points.
+			let start = Instant::now();
+			author(threads)
+				.build_block(transactions.clone(), Default::default())
+				.unwrap();
+			let full = start.elapsed();
+
+			for fraction in [0.3, 0.6, 0.9] {
+				let limits = BlockLimits {
+					deadline: Some(full.mul_f64(fraction)),
+					..Default::default()
+				};
+				let (authoring_state, block, stats, unexecuted) = author(threads)
+					.build_block(transactions.clone(), limits)
+					.unwrap();
+				assert_eq!(
+					block.transactions.len() + unexecuted.len(),
+					transactions.len()
+				);
+				assert_eq!(stats.unexecuted, unexecuted.len());
+				assert!(unexecuted
+					.iter()
+					.all(|tx| tx.tag == ExecutionTag::NotExecuted && tx.hops.is_empty()));
+
+				// whatever made it into the block is valid on its own.
+				let mut validator =
+					ConcurrentExecutor::<Pool>::new(2, false, None, Box::new(RoundRobin));
+				validator.apply_state(initial_state.clone());
+				let (validation_state, _) = validator.validate_block(block).unwrap();
+				assert!(validation_state.state_eq(authoring_state));
+			}
+		}
+	}
+
+	#[test]
+	fn validation_works_with_different_number_of_threads() {
+		init_logger();
+		let mut author = ConcurrentExecutor::<Pool>::new(4, false, None, Box::new(RoundRobin));
+		let (transactions, accounts) = transaction_generator::bank(50, 100, 100);
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				accounts
+					.into_iter()
+					.for_each(|acc| transaction_generator::endow_account(acc, rt, 100_000))
+			})
+			.build();
+
+		author.apply_state(initial_state.clone());
+		let (authoring_state, block, _) = author.author_block(transactions).unwrap();
+		assert!(block.transactions.iter().all(|tx| match tx.tag {
+			ExecutionTag::Done(bucket) => bucket < 4,
+			_ => tx.tag == ExecutionTag::Orphan,
+		}));
+
+		for threads in [1, 3, 4, 6] {
+			let mut validator =
+				ConcurrentExecutor::<Pool>::new(threads, false, None, Box::new(RoundRobin));
+			validator.apply_state(initial_state.clone());
+			let (validation_state, _) = validator.validate_block(block.clone()).unwrap();
+			assert!(validation_state.state_eq(authoring_state.clone()));
+		}
+	}
+
+	#[test]
+	fn authoring_is_deterministic() {
+		use parity_scale_codec::Encode;
+		init_logger();
+		let txs_and_accounts = transaction_generator::random_transfers(20);
+
+		let blocks = (0..2)
+			.map(|_| {
+				let mut executor =
+					ConcurrentExecutor::<Pool>::new(4, false, None, Box::new(ConnectedComponents));
+				txs_and_accounts.iter().for_each(|(_, sender, _)| {
+					transaction_generator::endow_account(*sender, &executor.master.runtime, 1000)
+				});
+				let txs = txs_and_accounts
+					.iter()
+					.map(|(tx, _, _)| tx.clone())
+					.collect();
+				let (_, block, _) = executor.author_block(txs).unwrap();
+				block.encode()
+			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(blocks[0], blocks[1]);
+	}
+
+	#[test]
+	fn dropping_executor_terminates_workers() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
+		executor.author_block(vec![]).unwrap();
+
+		let state = Arc::clone(&executor.master.state);
+		drop(executor);
+
+		// all workers have been joined, thus their references to the state are gone as well.
+		assert_eq!(Arc::strong_count(&state), 1);
+	}
+
+	#[test]
+	fn consecutive_blocks_leak_no_messages() {
+		init_logger();
+		let accounts = (0..50).map(|_| random()).collect::<Vec<_>>();
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				accounts
+					.iter()
+					.for_each(|acc| transaction_generator::endow_account(acc.public(), rt, 100_000))
+			})
+			.build();
+
+		// each block has distinct senders, with the nonces following up on the previous blocks.
+		let mut nonces = transaction_generator::Nonces::default();
+		let mut next_block = |b: usize| {
+			(0..16)
+				.map(|i| {
+					let from = &accounts[(b + i) % accounts.len()];
+					let to = accounts[(b + i + 1) % accounts.len()].public();
+					let call =
+						runtime::OuterCall::Balances(runtime::balances::Call::Transfer(to, 10));
+					let id = (b * 16 + i) as TransactionId;
+					transaction_generator::sign(id, call, nonces.next(from.public()), from)
+				})
+				.collect::<Vec<_>>()
+		};
+
+		let mut author = ConcurrentExecutor::<Pool>::new(4, false, None, Box::new(RoundRobin));
+		let mut validator = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
+		author.apply_state(initial_state.clone());
+		validator.apply_state(initial_state);
+
+		// every block is synced by `author_blocks` itself, which fails upon any stray message.
+		let blocks = author
+			.author_blocks((0..100).map(&mut next_block))
+			.collect::<Result<Vec<_>, _>>()
+			.unwrap();
+		assert_eq!(blocks.len(), 100);
+		assert!(author.master.from_workers.try_recv().is_err());
+
+		for (authoring_state, block, _) in blocks {
+			// none of the transactions of the previous block is carried over.
+			assert_eq!(block.transactions.len(), 16);
+			let (validation_state, _) = validator.validate_block(block).unwrap();
+			assert!(validation_state.state_eq(authoring_state));
+			validator.master.sync().unwrap();
+		}
+		assert!(validator.master.from_workers.try_recv().is_err());
+
+		// each transaction has bumped the nonce of its sender, and none of them was replayed.
+		let nonces = accounts
+			.iter()
+			.map(|acc| {
+				runtime::system::NonceOf::read(&validator.master.runtime, acc.public()).unwrap()
+			})
+			.sum::<Nonce>();
+		assert_eq!(nonces, 16 * 100);
+	}
+
+	#[test]
+	fn stray_messages_fail_sync() {
+		init_logger();
+		let executor = ConcurrentExecutor::<Pool>::new(2, false, None, Box::new(RoundRobin));
+		executor.master.sync().unwrap();
+
+		// a worker that receives anything but a task while waiting for one reports it.
+		executor
+			.master
+			.workers
+			.get(&1)
+			.unwrap()
+			.send
+			.send(Message::new(
+				MessagePayload::TransactionDistributionDone,
+				executor.master.id,
+			))
+			.unwrap();
+		assert_eq!(
+			executor.master.sync().unwrap_err(),
+			ExecutorError::WorkerError(1)
+		);
+		assert_eq!(executor.master.dead_worker(), None);
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn pinned_executor_works() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
+		let (transactions, accounts) = transaction_generator::bank(50, 100, 100);
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				accounts
+					.into_iter()
+					.for_each(|acc| transaction_generator::endow_account(acc, rt, 100_000))
+			})
+			.build();
+
+		let allowed = affinity::allowed_cores();
+		let core = allowed[0];
+		executor.pin(&Pinning::Cores(vec![core])).unwrap();
+		executor.apply_state(initial_state.clone());
+		let (_, _, stats) = executor.author_block(transactions.clone()).unwrap();
+		assert_eq!(stats.master_core, Some(core));
+		assert!(stats.workers.values().all(|w| w.core == Some(core)));
+
+		// the master is only pinned while it works.
+		assert_eq!(affinity::current_affinity().unwrap(), allowed);
+
+		executor.clean();
+		executor.pin(&Pinning::Auto).unwrap();
+		assert!(
+			executor
+				.author_and_validate(transactions.clone(), Some(initial_state.clone()))
+				.0
+		);
+
+		executor.clean();
+		executor.pin(&Pinning::Unpinned).unwrap();
+		executor.apply_state(initial_state);
+		let (_, _, stats) = executor.author_block(transactions).unwrap();
+		assert_eq!(stats.master_core, None);
+		assert!(stats.workers.values().all(|w| w.core.is_none()));
+	}
+
+	#[test]
+	fn unexpected_message_fails_authoring() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
+
+		// the first worker expects a task, this will make it report an error right away.
+		executor
+			.master
+			.workers
+			.get(&0)
+			.unwrap()
+			.send
+			.send(Message::new(MessagePayload::TaskDone, executor.master.id))
+			.unwrap();
+
+		// non-conflicting transactions, so that no other worker tries to forward to the dead one.
+		let txs = transaction_generator::random_transfers(9)
+			.into_iter()
+			.map(|(tx, _, _)| tx)
+			.collect();
+
+		assert_eq!(
+			executor.author_block(txs).unwrap_err(),
+			ExecutorError::WorkerError(0)
+		);
+		assert_eq!(executor.master.dead_worker(), None);
+
+		// and dropping the executor must not hang.
+	}
+
+	/// A distribution that puts all of the transactions in the bucket of the first worker.
+	#[derive(Debug)]
+	struct AllToFirst;
+
+	impl<P: TransactionPool<Transaction>> Distributer<P> for AllToFirst {
+		fn distribute(
+			&mut self,
+			_: &runtime::SequentialRuntime,
+			worker_ids: &[ThreadId],
+			pool: &mut P,
+		) {
+			pool.iter_mut()
+				.for_each(|tx| tx.tag = ExecutionTag::Done(worker_ids[0]));
+		}
+	}
+
+	#[test]
+	fn idle_workers_steal_transactions() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(2, false, None, Box::new(AllToFirst));
+		let txs_and_accounts = transaction_generator::random_transfers(20);
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				txs_and_accounts.iter().for_each(|(_, sender, _)| {
+					transaction_generator::endow_account(*sender, rt, 1000)
+				})
+			})
+			.build();
+		let txs = txs_and_accounts
+			.into_iter()
+			.map(|(tx, _, _)| tx)
+			.collect::<Vec<_>>();
+
+		executor.apply_state(initial_state.clone());
+		let (authoring_state, block, _) = executor.author_block(txs).unwrap();
+		executor.clean();
+
+		// the second worker has received nothing from the master, yet it must have executed some.
+		let stolen = block
+			.transactions
+			.iter()
+			.filter(|tx| tx.tag == ExecutionTag::Done(1))
+			.count();
+		assert!(stolen > 0);
+		assert!(block
+			.transactions
+			.iter()
+			.all(|tx| tx.tag != ExecutionTag::Orphan));
+
+		executor.apply_state(initial_state);
+		let (validation_state, _) = executor.validate_block(block).unwrap();
+		assert!(validation_state.state_eq(authoring_state));
+	}
+
+	#[test]
+	fn stolen_transactions_are_not_conflicts() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(
+			2,
+			false,
+			None,
+			Box::new(Adaptive::new(Box::new(AllToFirst))),
+		);
+		let txs_and_accounts = transaction_generator::random_transfers(20);
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				txs_and_accounts.iter().for_each(|(_, sender, _)| {
+					transaction_generator::endow_account(*sender, rt, 1000)
+				})
+			})
+			.build();
+		let txs = txs_and_accounts
+			.into_iter()
+			.map(|(tx, _, _)| tx)
+			.collect::<Vec<_>>();
+
+		executor.apply_state(initial_state);
+		let (_, block, _) = executor.author_block(txs).unwrap();
+		assert!(block
+			.transactions
+			.iter()
+			.any(|tx| tx.tag == ExecutionTag::Done(1)));
+
+		// the transactions are independent, so nothing is learned from handing them over.
+		assert!(executor.master.distributer.history().unwrap().is_empty());
+	}
+
+	#[test]
+	fn adaptive_distribution_learns_hot_keys() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(
+			4,
+			false,
+			None,
+			Box::new(Adaptive::new(Box::new(RoundRobin))),
+		);
+		let hub = testing::bob().public();
+		let txs = (0..20)
+			.map(|i| transaction_generator::build_transfer(i, testing::random(), hub))
+			.collect::<Vec<_>>();
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				txs.iter()
+					.for_each(|tx| transaction_generator::endow_account(tx.signature.0, rt, 1000))
+			})
+			.build();
+
+		// the same block, over and over again. All of the transactions pay the hub.
+		let orphans = (0..3)
+			.map(|_| {
+				executor.apply_state(initial_state.clone());
+				let (authoring_state, block, _) = executor.author_block(txs.clone()).unwrap();
+				executor.clean();
+
+				executor.apply_state(initial_state.clone());
+				let (validation_state, _) = executor.validate_block(block.clone()).unwrap();
+				executor.clean();
+				assert!(validation_state.state_eq(authoring_state));
+
+				block
+					.transactions
+					.iter()
+					.filter(|tx| tx.tag == ExecutionTag::Orphan)
+					.count()
+			})
+			.collect::<Vec<_>>();
+
+		// round robin spreads the hub among all workers at first, but then it is learned to be hot
+		// and all of the transactions go to the same worker.
+		assert!(orphans[0] > 0);
+		assert_eq!(orphans[1..], [0, 0]);
+		assert!(executor
+			.master
+			.distributer
+			.history()
+			.unwrap()
+			.is_hot(&<BalanceOf<runtime::SequentialRuntime>>::key_for(hub)));
+	}
+
+	#[test]
+	fn invalid_transactions_are_dropped() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
+		let mut txs = (0..10)
+			.map(|i| transaction_generator::build_transfer(i, testing::random(), dave().public()))
+			.collect::<Vec<_>>();
+		txs[4].signature.1 = txs[5].signature.1;
+
+		let (_, block, _) = executor.author_block(txs).unwrap();
+		let mut included = block
+			.transactions
+			.iter()
+			.map(|tx| tx.id)
+			.collect::<Vec<_>>();
+		included.sort_unstable();
+		assert_eq!(included, vec![0, 1, 2, 3, 5, 6, 7, 8, 9]);
+	}
+
+	#[test]
+	fn included_transactions_bump_nonces() {
+		use runtime::system::NonceOf;
+
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
+		let alice = testing::alice();
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				transaction_generator::endow_account(alice.public(), rt, 1000);
+				transaction_generator::endow_account(bob().public(), rt, 1000);
+			})
+			.build();
+		executor.apply_state(initial_state.clone());
+
+		let alice_tx = |id, nonce| {
+			transaction_generator::build_transfer_with_nonce(
+				id,
+				testing::alice(),
+				dave().public(),
+				nonce,
+			)
+		};
+		let mut pool = ReadyPool::new();
+		for (id, nonce) in [(0, 2), (1, 0), (2, 1), (4, 4)] {
+			pool.submit(&executor.master.runtime, alice_tx(id, nonce))
+				.unwrap();
+		}
+		pool.submit(
+			&executor.master.runtime,
+			transaction_generator::build_transfer(3, bob(), dave().public()),
+		)
+		.unwrap();
+		assert_eq!((pool.ready_len(), pool.future_len()), (4, 1));
+
+		// all of the ready ones of alice fit, and bob's is left in the pool.
+		let limits = BlockLimits {
+			max_transactions: Some(3),
+			..Default::default()
+		};
+		let (_, first, _) = executor.author_ready(&mut pool, limits).unwrap();
+		assert_eq!(
+			first
+				.transactions
+				.iter()
+				.map(|tx| tx.nonce)
+				.collect::<Vec<_>>(),
+			vec![0, 1, 2]
+		);
+		assert_eq!(
+			NonceOf::read(&executor.master.runtime, alice.public()),
+			Ok(3)
+		);
+		assert_eq!((pool.ready_len(), pool.future_len()), (1, 1));
+
+		// the missing nonce of alice makes her last one ready as well.
+		pool.submit(&executor.master.runtime, alice_tx(5, 3))
+			.unwrap();
+		let (authoring_state, second, _) = executor
+			.author_ready(&mut pool, Default::default())
+			.unwrap();
+		assert_eq!(second.transactions.len(), 3);
+		assert_eq!(
+			NonceOf::read(&executor.master.runtime, alice.public()),
+			Ok(5)
+		);
+		assert_eq!(
+			NonceOf::read(&executor.master.runtime, bob().public()),
+			Ok(1)
+		);
+		assert!(pool.is_empty());
+
+		executor.clean();
+		executor.apply_state(initial_state);
+		executor.validate_block(first).unwrap();
+		let (validation_state, _) = executor.validate_block(second).unwrap();
+		assert!(validation_state.state_eq(authoring_state));
+	}
+
+	#[test]
+	fn replayed_transactions_are_rejected() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(3, false, None, Box::new(RoundRobin));
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				transaction_generator::endow_account(alice().public(), rt, 1000);
+				transaction_generator::endow_account(bob().public(), rt, 1000);
+			})
+			.build();
+		executor.apply_state(initial_state);
+
+		let txs = vec![
+			transaction_generator::build_transfer(0, alice(), dave().public()),
+			transaction_generator::build_transfer(1, bob(), dave().public()),
+		];
+		let (state, block, _, unexecuted) = executor
+			.build_block(txs.clone(), Default::default())
+			.unwrap();
+		assert_eq!(block.transactions.len(), 2);
+		assert!(unexecuted.is_empty());
+
+		// the same transactions again are left out of the next block.
+		let (replay_state, replay, stats, mut unexecuted) = executor
+			.build_block(txs.clone(), Default::default())
+			.unwrap();
+		assert!(replay.transactions.is_empty());
+		assert_eq!(stats.unexecuted, 2);
+		unexecuted.sort_by_key(|tx| tx.id);
+		assert_eq!(unexecuted, txs);
+		assert!(replay_state.state_eq(state.clone()));
+
+		// and a block that includes them again is rejected, be it by the workers or the master.
+		let mut validator = ConcurrentExecutor::<Pool>::new(2, false, None, Box::new(RoundRobin));
+		validator.apply_state(state.clone());
+		assert!(matches!(
+			validator.validate_block(block.clone()),
+			Err(ExecutorError::InvalidBlock(InvalidBlock::BadNonce(_)))
+		));
+
+		let mut orphans = block;
+		orphans
+			.transactions
+			.iter_mut()
+			.for_each(|tx| tx.tag = ExecutionTag::Orphan);
+		validator.clean();
+		validator.apply_state(state);
+		assert!(matches!(
+			validator.validate_block(orphans),
+			Err(ExecutorError::InvalidBlock(InvalidBlock::BadNonce(_)))
+		));
+	}
+
+	#[test]
+	fn authoring_stats_are_reported() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(4, false, None, Box::new(RoundRobin));
+
+		// half of the transactions pay the hub, and only half of the senders have any funds.
+		let hub = testing::bob().public();
+		let txs = (0..40)
+			.map(|i| {
+				let dest = if i % 2 == 0 {
+					hub
+				} else {
+					testing::random().public()
+				};
+				transaction_generator::build_transfer(i, testing::random(), dest)
+			})
+			.collect::<Vec<_>>();
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				txs.iter()
+					.filter(|tx| tx.id < 20)
+					.for_each(|tx| transaction_generator::endow_account(tx.signature.0, rt, 1000))
+			})
+			.build();
+
+		executor.apply_state(initial_state);
+		let (_, block, stats) = executor.author_block(txs).unwrap();
+
+		let orphans = block
+			.transactions
+			.iter()
+			.filter(|tx| tx.tag == ExecutionTag::Orphan)
+			.count();
+		assert_eq!(stats.orphaned, orphans);
+		assert_eq!(stats.pre_orphaned, 0);
+		assert_eq!(stats.logic_errors, 20);
+		assert_eq!(stats.workers.len(), 4);
+		assert_eq!(
+			stats.workers.values().map(|w| w.received).sum::<usize>(),
+			40
+		);
+		assert_eq!(
+			stats.workers.values().map(|w| w.executed).sum::<usize>() + orphans,
+			40
+		);
+		assert!(stats.total >= stats.concurrent_phase + stats.collection + stats.orphan_phase);
+		assert!(stats.concurrent_phase >= stats.distribution);
+	}
+
+	#[test]
+	fn pipelined_orphans_validate() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(4, false, None, Box::new(RoundRobin));
+		executor.master.hot_key_threshold = Some(0.25);
+
+		// half of the transactions pay the hub, and are pre-orphaned. A few of the rest pay the
+		// senders of the first ones.
+		let hub = testing::bob().public();
+		let senders = (0..20).map(|_| testing::random()).collect::<Vec<_>>();
+		let sender_keys = senders.iter().map(|s| s.public()).collect::<Vec<_>>();
+		let mut txs = senders
+			.into_iter()
+			.enumerate()
+			.map(|(i, s)| transaction_generator::build_transfer(i as TransactionId, s, hub))
+			.collect::<Vec<_>>();
+		txs.extend((20..40).map(|i| {
+			let dest = if i < 25 {
+				sender_keys[i - 20]
+			} else {
+				testing::random().public()
+			};
+			transaction_generator::build_transfer(i as TransactionId, testing::random(), dest)
+		}));
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				txs.iter()
+					.for_each(|tx| transaction_generator::endow_account(tx.signature.0, rt, 1000))
+			})
+			.build();
+
+		executor.apply_state(initial_state.clone());
+		let (authoring_state, block, stats) = executor.author_block(txs).unwrap();
+		executor.clean();
+		// how many of them are pipelined depends on the timing of the workers, see
+		// `master::master_tests_multi_worker::orphans_are_pipelined_only_once_proven_free`.
+		assert_eq!(stats.pre_orphaned, 20);
+
+		executor.apply_state(initial_state);
+		let (validation_state, _) = executor.validate_block(block).unwrap();
+		assert!(validation_state.state_eq(authoring_state));
+	}
+
+	#[test]
+	fn hot_transactions_are_pre_orphaned() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(4, false, None, Box::new(RoundRobin));
+		executor.master.hot_key_threshold = Some(0.25);
+
+		// half of the transactions pay the hub, the rest are independent.
+		let hub = testing::bob().public();
+		let txs = (0..40)
+			.map(|i| {
+				let dest = if i % 2 == 0 {
+					hub
+				} else {
+					testing::random().public()
+				};
+				transaction_generator::build_transfer(i, testing::random(), dest)
+			})
+			.collect::<Vec<_>>();
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				txs.iter()
+					.for_each(|tx| transaction_generator::endow_account(tx.signature.0, rt, 1000))
+			})
+			.build();
+
+		executor.apply_state(initial_state.clone());
+		let (authoring_state, block, stats) = executor.author_block(txs).unwrap();
+		executor.clean();
+		assert_eq!(stats.pre_orphaned, 20);
+		assert_eq!(stats.orphaned, 20);
+		// none of the workers access the hub or the senders of the orphans.
+		assert_eq!(stats.pipelined_orphans, 20);
+		assert!(block
+			.transactions
+			.iter()
+			.all(|tx| (tx.tag == ExecutionTag::Orphan) == (tx.id % 2 == 0)));
+
+		executor.apply_state(initial_state);
+		let (validation_state, _) = executor.validate_block(block).unwrap();
+		assert!(validation_state.state_eq(authoring_state));
+	}
+
+	#[test]
+	fn small_blocks_are_not_entirely_hot() {
+		init_logger();
+		let mut executor = ConcurrentExecutor::<Pool>::new(2, false, None, Box::new(RoundRobin));
+		executor.master.hot_key_threshold = Some(0.25);
+
+		// each key is accessed by one of three transactions, above the threshold, yet no key is
+		// shared.
+		let txs = transaction_generator::random_transfers(3)
+			.into_iter()
+			.map(|(tx, _, _)| tx)
+			.collect::<Vec<_>>();
+		let (_, block, stats) = executor.author_block(txs).unwrap();
+		assert_eq!(stats.pre_orphaned, 0);
+		assert!(block
+			.transactions
+			.iter()
+			.all(|tx| tx.tag != ExecutionTag::Orphan));
+	}
+
+	/// A distribution that puts each transaction in the bucket of the worker at the given index.
+	#[derive(Debug)]
+	struct ByIndex(fn(&Transaction) -> usize);
+
+	impl<P: TransactionPool<Transaction>> Distributer<P> for ByIndex {
+		fn distribute(
+			&mut self,
+			_: &runtime::SequentialRuntime,
+			worker_ids: &[ThreadId],
+			pool: &mut P,
+		) {
+			pool.iter_mut()
+				.for_each(|tx| tx.tag = ExecutionTag::Done(worker_ids[(self.0)(tx)]));
+		}
+	}
+
+	#[test]
+	fn multi_hop_forwarding_works() {
+		init_logger();
+		const BUDGET: usize = 3;
+		const SENDERS: usize = 20;
+		const FILLERS: usize = 200;
+
+		// the senders spend first in the third worker, and then in the first one, at the back of
+		// its queue. The second and the fourth worker have nothing to do, and take over from the
+		// first one, only to find out that the sender is already tainted by the third one.
+		let mut executor = ConcurrentExecutor::<Pool>::with_forward_budget(
+			4,
+			false,
+			None,
+			Box::new(ByIndex(|tx| if (tx.id as usize) < SENDERS { 2 } else { 0 })),
+			BUDGET,
+		);
+		let (mut txs, last): (Vec<_>, Vec<_>) = (0..SENDERS)
+			.map(|i| {
+				let sender = testing::random();
+				let call = || {
+					runtime::OuterCall::Balances(runtime::balances::Call::Transfer(
+						testing::random().public(),
+						10,
+					))
+				};
+				(
+					transaction_generator::sign(i as TransactionId, call(), 0, &sender),
+					transaction_generator::sign(
+						(SENDERS + FILLERS + i) as TransactionId,
+						call(),
+						1,
+						&sender,
+					),
+				)
+			})
+			.unzip();
+		txs.extend((0..FILLERS).map(|i| {
+			transaction_generator::build_transfer(
+				(SENDERS + i) as TransactionId,
+				testing::random(),
+				testing::random().public(),
+			)
+		}));
+		txs.extend(last);
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				txs.iter()
+					.for_each(|tx| transaction_generator::endow_account(tx.signature.0, rt, 1000))
+			})
+			.build();
+
+		executor.apply_state(initial_state.clone());
+		let (authoring_state, block, _) = executor.author_block(txs).unwrap();
+		executor.clean();
+
+		// some of the transactions are handed over, and then forwarded.
+		assert!(block
+			.transactions
+			.iter()
+			.any(|tx| matches!(tx.tag, ExecutionTag::Done(_)) && tx.hops.len() > 1));
+
+		// no transaction is forwarded more than the budget, or back to where it came from.
+		for tx in block.transactions.iter() {
+			let mut hops = tx.hops.clone();
+			hops.sort_unstable();
+			hops.dedup();
+			assert_eq!(hops.len(), tx.hops.len());
+			match tx.tag {
+				ExecutionTag::Done(bucket) => {
+					assert!(tx.hops.len() <= BUDGET);
+					assert!(!tx.hops.contains(&bucket));
+				}
+				ExecutionTag::Orphan => assert!(tx.hops.len() <= BUDGET + 1),
+				_ => panic!("Transaction must be executed"),
+			}
+		}
+
+		executor.apply_state(initial_state);
+		let (validation_state, _) = executor.validate_block(block).unwrap();
+		assert!(validation_state.state_eq(authoring_state));
+	}
+
 	macro_rules! bank_test_with_distribution {
-		($( $distribution:ty, $name:ident ,)*) => {
+		($( $distribution:expr, $name:ident ,)*) => {
 			$(
 				#[test]
 				fn $name() {
@@ -396,7 +1621,12 @@ mod concurrent_executor {
 					const NUM_ACCOUNTS: usize = 10;
 					const NUM_TXS: usize = 250;
 
-					let mut executor = ConcurrentExecutor::<Pool, $distribution>::new(4, false, None);
+					let mut executor = ConcurrentExecutor::<Pool>::new(
+						4,
+						false,
+						None,
+						by_name($distribution).unwrap(),
+					);
 					std::thread::sleep(std::time::Duration::from_millis(500));
 					assert_eq!(executor.master.workers.len(), 4);
 
@@ -413,14 +1643,14 @@ mod concurrent_executor {
 
 
 					executor.author_and_validate(transfers, Some(initial_state));
-
-					executor.master.run_terminate();
-					assert!(executor.master.join_all().is_ok());
 				}
 			)*
 		}
 	}
 
-	bank_test_with_distribution!(RoundRobin, bank_round_robin,);
-	bank_test_with_distribution!(ConnectedComponents, bank_connected_components,);
+	bank_test_with_distribution!("RoundRobin", bank_round_robin,);
+	bank_test_with_distribution!("ConnectedComponents", bank_connected_components,);
+	bank_test_with_distribution!("HashPartition", bank_hash_partition,);
+	bank_test_with_distribution!("MinCut", bank_min_cut,);
+	bank_test_with_distribution!("Adaptive", bank_adaptive,);
 }
@@ -3,361 +3,690 @@ use crate::{
 	types::{ExecutionTag, Transaction},
 };
 use primitives::*;
-use runtime::SequentialRuntime;
-use std::matches;
-
-pub use node::ConnectedComponents;
+use runtime::{SequentialRuntime, Weight};
+use std::collections::{HashMap, HashSet};
 
 const LOG_TARGET: &'static str = "tx-dist";
 
 /// Something that can distribute transactions into buckets, each assigned to a thread.
-pub trait Distributer {
+///
+/// A distributer is kept by the master across blocks, hence it can carry configuration and learn
+/// from the previous blocks.
+pub trait Distributer<P: TransactionPool<Transaction>>: std::fmt::Debug {
 	/// Distribute the given transactions among the given thread ids in some arbitrary way.
 	///
 	/// This will receive a mutable reference to the pool and will also update the `status` of each
-	/// transaction to `Done(_)`.
-	fn distribute<P: TransactionPool<Transaction>>(
-		runtime: &SequentialRuntime,
-		worker_ids: &[ThreadId],
-		txs: &mut P,
-	);
+	/// transaction to `Done(_)`, or to `Orphan` if it should be left for the master to execute
+	/// after all of the workers are done.
+	fn distribute(&mut self, runtime: &SequentialRuntime, worker_ids: &[ThreadId], txs: &mut P);
+
+	/// Report a transaction of the block being authored that a worker reported as orphan, or as
+	/// executed on behalf of another worker.
+	fn report_conflict(&mut self, _runtime: &SequentialRuntime, _tx: &Transaction) {}
+
+	/// Report that all of the transactions of the block being authored are collected.
+	fn finish_block(&mut self) {}
+
+	/// What has been learned from the previous blocks, if this distributer learns at all.
+	fn history(&self) -> Option<&ExecutionHistory> {
+		None
+	}
 }
 
-pub mod node {
-	use super::*;
-	use std::{
-		cell::RefCell,
-		rc::{Rc, Weak},
-	};
+/// The names of all of the distributers that can be created with [`by_name`].
+pub const DISTRIBUTERS: [&str; 5] = [
+	"RoundRobin",
+	"HashPartition",
+	"ConnectedComponents",
+	"MinCut",
+	"Adaptive",
+];
 
-	type NodeCell = RefCell<Node>;
-
-	#[derive(Eq, PartialEq, Clone, Debug)]
-	enum NodeType {
-		Tx(TransactionId),
-		Key(StateKey),
-	}
-
-	#[derive(Clone, Debug)]
-	struct Node {
-		node_type: NodeType,
-		/// Points to key if node type is tx.
-		///
-		/// This is an Rc.
-		keys: Option<Vec<Rc<NodeCell>>>,
-		/// Points to tx if node type is key.
-		///
-		/// This is a Weak.
-		txs: Option<Vec<Weak<NodeCell>>>,
-		/// Id of the component to which this node belongs to.
-		component: Option<u32>,
-	}
-
-	impl Node {
-		fn new_tx(tx: TransactionId, keys: Vec<Rc<NodeCell>>) -> Self {
-			Self {
-				node_type: NodeType::Tx(tx),
-				keys: Some(keys),
-				txs: None,
-				component: None,
-			}
-		}
+/// Create a distributer with its default configuration, by its name.
+///
+/// The base of `Adaptive` is `ConnectedComponents`, unless it is given after a colon, e.g.
+/// `Adaptive:RoundRobin`.
+pub fn by_name<P: TransactionPool<Transaction> + 'static>(
+	name: &str,
+) -> Option<Box<dyn Distributer<P>>> {
+	match name {
+		"RoundRobin" => Some(Box::new(RoundRobin)),
+		"HashPartition" => Some(Box::new(HashPartition)),
+		"ConnectedComponents" => Some(Box::new(ConnectedComponents)),
+		"MinCut" => Some(Box::new(MinCut::default())),
+		"Adaptive" => Some(Box::new(Adaptive::new(Box::new(ConnectedComponents)))),
+		_ => name
+			.strip_prefix("Adaptive:")
+			.and_then(by_name)
+			.map(|base| Box::new(Adaptive::new(base)) as Box<dyn Distributer<P>>),
+	}
+}
 
-		fn new_key(key: StateKey, txs: Vec<Weak<NodeCell>>) -> Self {
-			Self {
-				node_type: NodeType::Key(key),
-				txs: Some(txs),
-				keys: None,
-				component: None,
-			}
-		}
+/// What the master has learned from the execution of the previous blocks.
+///
+/// Each transaction that a worker reports as orphan, or as executed on behalf of another worker,
+/// is counted as a conflict and blamed on all of the keys in its access hints. Transactions that
+/// are handed over to idle workers are reported in the same way, hence the scores are only an
+/// estimate.
+///
+/// A key is hot once it has conflicted enough. A conflict of a transaction whose keys were all hot
+/// already, and hence was executed on a dedicated thread (see [`Adaptive`]), is a miss: it must be
+/// due to a key that is not in the hints. Keys with enough misses are badly hinted.
+///
+/// Misses decay with every block, such that badly hinted keys are retried once in a while.
+/// Conflicts only decay in blocks that do not access the key, such that a hot key remains hot even
+/// if isolating it has stopped its conflicts.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionHistory {
+	/// Decayed number of conflicts of each key.
+	conflicts: HashMap<StateKey, f64>,
+	/// Decayed number of misses of each key.
+	misses: HashMap<StateKey, f64>,
+	/// The keys accessed in the current block.
+	active: HashSet<StateKey>,
+	/// The transactions of the current block that are sent to a dedicated thread.
+	dedicated: HashSet<TransactionId>,
+}
 
-		fn is_key_node(&self) -> bool {
-			matches!(self.node_type, NodeType::Key(_))
-		}
+impl ExecutionHistory {
+	/// The factor by which the scores decay per block.
+	pub const DECAY: f64 = 0.9;
+	/// Decayed number of conflicts from which on a key is hot.
+	pub const HOT_THRESHOLD: f64 = 1.0;
+	/// Decayed number of misses from which on a key is badly hinted.
+	///
+	/// A single miss keeps a key badly hinted for a few blocks.
+	pub const MISS_THRESHOLD: f64 = 0.5;
+	/// Scores below this are forgotten, to keep the history bounded.
+	pub const FORGET_THRESHOLD: f64 = 0.05;
 
-		fn is_tx_node(&self) -> bool {
-			matches!(self.node_type, NodeType::Tx(_))
-		}
+	/// If nothing has been learned, i.e. no key has ever conflicted.
+	pub fn is_empty(&self) -> bool {
+		self.conflicts.is_empty() && self.misses.is_empty()
+	}
 
-		fn add_key(&mut self, key: Rc<NodeCell>) {
-			debug_assert!(self.is_tx_node());
-			self.keys.as_mut().unwrap().push(key);
-		}
+	/// If transactions that access `key` should all be executed by the same thread.
+	pub fn is_hot(&self, key: &StateKey) -> bool {
+		self.conflicts
+			.get(key)
+			.is_some_and(|c| *c >= Self::HOT_THRESHOLD)
+	}
 
-		fn add_tx(&mut self, tx: Weak<NodeCell>) {
-			debug_assert!(self.is_key_node());
-			self.txs.as_mut().unwrap().push(tx)
-		}
+	/// If transactions that access `key` are likely to conflict regardless of the distribution.
+	pub fn is_badly_hinted(&self, key: &StateKey) -> bool {
+		self.misses
+			.get(key)
+			.is_some_and(|m| *m >= Self::MISS_THRESHOLD)
+	}
 
-		fn as_rc(self) -> Rc<NodeCell> {
-			Rc::from(RefCell::new(self))
-		}
+	/// Note that the given keys are accessed in the current block.
+	pub fn access(&mut self, keys: &[StateKey]) {
+		self.active.extend(keys.iter().cloned());
+	}
 
-		fn visit(&mut self, id: u32) -> bool {
-			match self.component {
-				None => {
-					self.component = Some(id);
-					true
-				}
-				_ => false,
+	/// Note that transaction `tx` of the current block is sent to a dedicated thread.
+	pub fn dedicate(&mut self, tx: TransactionId) {
+		self.dedicated.insert(tx);
+	}
+
+	/// Record a conflict of transaction `tx` of the current block, which accesses `keys`.
+	pub fn record_conflict(&mut self, tx: TransactionId, keys: &[StateKey]) {
+		let scores = if self.dedicated.contains(&tx) && keys.iter().all(|k| self.is_hot(k)) {
+			&mut self.misses
+		} else {
+			&mut self.conflicts
+		};
+		keys.iter()
+			.for_each(|k| *scores.entry(k.clone()).or_default() += 1f64);
+	}
+
+	/// Finish the current block, and decay the scores.
+	pub fn finish_block(&mut self) {
+		let active = std::mem::take(&mut self.active);
+		self.conflicts.retain(|k, c| {
+			if !active.contains(k) {
+				*c *= Self::DECAY;
 			}
-		}
+			*c >= Self::FORGET_THRESHOLD
+		});
+		self.misses.retain(|_, m| {
+			*m *= Self::DECAY;
+			*m >= Self::FORGET_THRESHOLD
+		});
+		self.dedicated.clear();
+	}
+}
 
-		fn traverse(start: Rc<NodeCell>, component_id: u32) -> Vec<Rc<NodeCell>> {
-			let mut queue = vec![start];
-			let mut component = vec![];
+/// The access graph of a set of transactions: the keys that each transaction accesses, and the
+/// transactions that access each key.
+///
+/// Transactions are identified by their index in the pool, keys by the order in which they first
+/// appear.
+struct AccessGraph {
+	/// The keys of each transaction, sorted and deduplicated.
+	tx_keys: Vec<Vec<usize>>,
+	/// The transactions of each key, in the order of the pool.
+	key_txs: Vec<Vec<usize>>,
+}
 
-			while !queue.is_empty() {
-				let current = queue.remove(0);
+impl AccessGraph {
+	fn new<P: TransactionPool<Transaction>>(runtime: &SequentialRuntime, pool: &P) -> Self {
+		let mut key_indices: HashMap<StateKey, usize> = HashMap::new();
+		let tx_keys = pool
+			.iter()
+			.map(|tx| {
+				let mut keys = runtime
+					.validate(&tx.function, tx.signature.0)
+					.into_iter()
+					.map(|key| {
+						let next = key_indices.len();
+						*key_indices.entry(key).or_insert(next)
+					})
+					.collect::<Vec<_>>();
+				keys.sort_unstable();
+				keys.dedup();
+				keys
+			})
+			.collect::<Vec<_>>();
+		let mut key_txs = vec![vec![]; key_indices.len()];
+		tx_keys.iter().enumerate().for_each(|(tx, keys)| {
+			keys.iter().for_each(|k| key_txs[*k].push(tx));
+		});
 
-				// mark visit the current node.
-				{
-					let mut current_borrow = current.borrow_mut();
-					if current_borrow.visit(component_id) && current_borrow.is_tx_node() {
-						component.push(Rc::clone(&current));
-					}
-				}
+		Self { tx_keys, key_txs }
+	}
 
-				let current = current.borrow();
-				match current.node_type {
-					NodeType::Key(_) => {
-						debug_assert!(current.keys.is_none());
-						current.txs.as_ref().unwrap().iter().for_each(|tx_weak| {
-							let tx_rc = Rc::clone(&tx_weak.upgrade().unwrap());
-							if tx_rc.borrow().component.is_none() {
-								queue.push(tx_rc);
-							}
-						})
-					}
-					NodeType::Tx(_) => {
-						debug_assert!(current.txs.is_none());
-						current.keys.as_ref().unwrap().iter().for_each(|key_rc| {
-							if key_rc.borrow().component.is_none() {
-								queue.push(Rc::clone(key_rc))
-							}
-						})
-					}
-				}
+	/// The connected components of the graph, ordered by their first transaction.
+	///
+	/// Found by merging all transactions of each key into one set.
+	fn components(&self) -> Vec<Vec<usize>> {
+		let mut parent = (0..self.tx_keys.len()).collect::<Vec<_>>();
+		fn root(parent: &mut [usize], mut x: usize) -> usize {
+			while parent[x] != x {
+				parent[x] = parent[parent[x]];
+				x = parent[x];
 			}
-
-			component
+			x
 		}
+		self.key_txs.iter().for_each(|txs| {
+			txs.windows(2).for_each(|pair| {
+				let (x, y) = (root(&mut parent, pair[0]), root(&mut parent, pair[1]));
+				parent[x.max(y)] = x.min(y);
+			})
+		});
+
+		// the root of each set is its smallest transaction, hence it is seen first.
+		let mut component_of_root = vec![usize::MAX; self.tx_keys.len()];
+		let mut components: Vec<Vec<usize>> = vec![];
+		(0..self.tx_keys.len()).for_each(|tx| {
+			let r = root(&mut parent, tx);
+			if component_of_root[r] == usize::MAX {
+				component_of_root[r] = components.len();
+				components.push(vec![]);
+			}
+			components[component_of_root[r]].push(tx)
+		});
+		components
 	}
+}
 
-	#[cfg(test)]
-	mod test_node {
-		use super::*;
+/// Distributes the connected components of the access graph, such that no two transactions that
+/// access the same key end up in different threads.
+///
+/// Components are assigned to the least loaded thread, heaviest first, where the load of a thread
+/// is the sum of the weights of its transactions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectedComponents;
 
-		#[test]
-		fn traverse_works() {
-			//
-			//  t1         t2
-			//  |           |
-			// 	|--- k1 ----|
-			//  |--- k2 ----|
-			let k1 = Node::new_key(vec![1, 1, 1].into(), vec![]).as_rc();
-			let k2 = Node::new_key(vec![2, 2, 2].into(), vec![]).as_rc();
-			let mut t1 = Node::new_tx(1, vec![]);
-			let mut t2 = Node::new_tx(2, vec![]);
+impl<P: TransactionPool<Transaction>> Distributer<P> for ConnectedComponents {
+	fn distribute(&mut self, runtime: &SequentialRuntime, worker_ids: &[ThreadId], txs: &mut P) {
+		let graph = AccessGraph::new(runtime, txs);
+		let weights = txs
+			.iter()
+			.map(|tx| runtime.weight(&tx.function))
+			.collect::<Vec<_>>();
 
-			t1.add_key(Rc::clone(&k1));
-			t1.add_key(Rc::clone(&k2));
+		let mut components = graph
+			.components()
+			.into_iter()
+			.map(|c| (c.iter().map(|tx| weights[*tx]).sum::<Weight>(), c))
+			.collect::<Vec<_>>();
 
-			t2.add_key(Rc::clone(&k1));
-			t2.add_key(Rc::clone(&k2));
+		debug_assert_eq!(
+			components.iter().map(|(_, x)| x.len()).sum::<usize>(),
+			txs.len(),
+			"All transactions must be assigned to a component.",
+		);
 
-			let t1 = t1.as_rc();
-			let t2 = t2.as_rc();
+		// longest processing time first: the heaviest component goes to the least loaded worker.
+		components.sort_by_key(|(weight, c)| std::cmp::Reverse((*weight, c.len())));
 
-			k1.borrow_mut().add_tx(Rc::downgrade(&t1));
-			k1.borrow_mut().add_tx(Rc::downgrade(&t2));
+		let mut loads = worker_ids
+			.iter()
+			.map(|w| (*w, 0 as Weight, 0usize))
+			.collect::<Vec<_>>();
+		let mut assignment = vec![0 as ThreadId; txs.len()];
+		for (weight, component) in components {
+			let (worker, load, count) = loads
+				.iter_mut()
+				.min_by_key(|(_, load, count)| (*load, *count))
+				.expect("There is at least one worker; qed.");
 
-			// dbg!(k1.borrow().txs.as_ref().unwrap().first().unwrap().upgrade());
+			logging::log!(
+				trace,
+				"assigning component with {} transactions and weight {} to thread {}",
+				component.len(),
+				weight,
+				worker,
+			);
+			*load += weight;
+			*count += component.len();
+			component.iter().for_each(|tx| assignment[*tx] = *worker);
+		}
 
-			k2.borrow_mut().add_tx(Rc::downgrade(&t1));
-			k2.borrow_mut().add_tx(Rc::downgrade(&t2));
+		debug_assert_eq!(
+			loads.iter().map(|(_, _, count)| count).sum::<usize>(),
+			txs.len(),
+			"all transactions within the components must be assigned to a worker."
+		);
 
-			let members = Node::traverse(k1.clone(), 1);
+		logging::log!(info, "Assigning outcome = {:?}", loads);
 
-			assert_eq!(k1.borrow().component, Some(1));
-			assert_eq!(k2.borrow().component, Some(1));
+		txs.iter_mut()
+			.zip(assignment)
+			.for_each(|(tx, worker)| tx.tag = ExecutionTag::Done(worker));
+	}
+}
 
-			assert_eq!(t2.borrow().component, Some(1));
-			assert_eq!(t2.borrow().component, Some(1));
+/// A dumb, naive, round robin transaction distributer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoundRobin;
 
-			assert_eq!(members.len(), 2);
-			assert_eq!(
-				members
-					.iter()
-					.map(|c| c.borrow().node_type.clone())
-					.collect::<Vec<_>>(),
-				vec![NodeType::Tx(1), NodeType::Tx(2)],
-			)
-		}
+impl<P: TransactionPool<Transaction>> Distributer<P> for RoundRobin {
+	fn distribute(&mut self, _: &SequentialRuntime, worker_ids: &[ThreadId], pool: &mut P) {
+		let num_workers = worker_ids.len();
+
+		pool.iter_mut().enumerate().for_each(|(idx, tx)| {
+			let assigned_worker = worker_ids[idx % num_workers];
+			tx.tag = ExecutionTag::Done(assigned_worker);
+		});
 	}
+}
 
-	pub struct ConnectedComponents;
+/// A transaction distributer that partitions the transactions by the hash of their first access
+/// key.
+///
+/// The first access key is usually the balance of the origin, hence all transactions of the same
+/// sender end up in the same worker. Unlike [`ConnectedComponents`], no graph is built, and
+/// transactions that share any other key might end up in different workers. Transactions that
+/// have no access keys are assigned in a round robin fashion.
+///
+/// The key is hashed with [`stable_hash`], hence the same block is always partitioned the same way,
+/// on any machine.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HashPartition;
 
-	impl Distributer for ConnectedComponents {
-		fn distribute<P: TransactionPool<Transaction>>(
-			runtime: &SequentialRuntime,
-			worker_ids: &[ThreadId],
-			txs: &mut P,
-		) {
-			use std::collections::BTreeMap;
-			let mut keys_map: BTreeMap<StateKey, Rc<NodeCell>> = BTreeMap::new();
+/// The 64-bit FNV-1a hash of the bytes of `key`.
+///
+/// Unlike the hashers of the standard library, this is fixed across platforms and compiler
+/// versions.
+fn stable_hash(key: &StateKey) -> u64 {
+	const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+	const PRIME: u64 = 0x0000_0100_0000_01b3;
+	key.as_ref().iter().fold(OFFSET_BASIS, |hash, byte| {
+		(hash ^ *byte as u64).wrapping_mul(PRIME)
+	})
+}
 
-			let transaction_nodes = txs
-				.iter()
-				.map(|tx| {
-					let keys = runtime.validate(&tx.function, tx.signature.0);
-					let tx_node = Node::new_tx(tx.id, Default::default()).as_rc();
-
-					let keys = keys
-						.into_iter()
-						.map(|key| {
-							let key_node = keys_map
-								.entry(key.clone())
-								.or_insert(Node::new_key(key, Default::default()).as_rc());
-							// add this transaction to the key.
-							key_node.borrow_mut().add_tx(Rc::downgrade(&tx_node));
-
-							Rc::clone(key_node)
-						})
-						.collect::<Vec<_>>();
-
-					for key in keys {
-						tx_node.borrow_mut().add_key(key);
-					}
+impl<P: TransactionPool<Transaction>> Distributer<P> for HashPartition {
+	fn distribute(&mut self, runtime: &SequentialRuntime, worker_ids: &[ThreadId], pool: &mut P) {
+		let num_workers = worker_ids.len();
 
-					tx_node
-				})
-				.collect::<Vec<_>>();
+		pool.iter_mut().enumerate().for_each(|(idx, tx)| {
+			let bucket = runtime
+				.validate(&tx.function, tx.signature.0)
+				.first()
+				.map(|key| (stable_hash(key) % num_workers as u64) as usize)
+				.unwrap_or(idx % num_workers);
+			tx.tag = ExecutionTag::Done(worker_ids[bucket]);
+		});
+	}
+}
 
-			let mut components = transaction_nodes
-				.iter()
-				.enumerate()
-				.map(|(id, tx_node)| Node::traverse(Rc::clone(tx_node), id as u32))
-				.filter(|c| !c.is_empty())
-				.collect::<Vec<_>>();
+/// A transaction distributer that splits large connected components across threads.
+///
+/// Like [`ConnectedComponents`], transactions that share any access key are grouped into the same
+/// component. Components that are larger than the fair share of a thread are partitioned, such
+/// that the number of access keys shared between the partitions is minimized. Each transaction
+/// that accesses a key that is also accessed from another partition is a potential conflict, which
+/// will end up being forwarded or orphaned. A component is only split if the number of such
+/// transactions is bounded by [`Self::max_conflict_ratio`]. Otherwise, it is kept whole.
+///
+/// The partitioning is a greedy assignment in breadth-first order, followed by a few passes of
+/// Kernighan-Lin style refinement that move single transactions between partitions, as long as
+/// this reduces the number of shared keys. Finally, all components and partitions are assigned to
+/// the least loaded thread, largest first.
+#[derive(Debug, Clone)]
+pub struct MinCut {
+	/// The maximum ratio of the transactions of a component that may conflict after it is split.
+	///
+	/// Higher values trade more orphans and forwards for a better balance.
+	pub max_conflict_ratio: f64,
+	/// The ratio by which a partition may exceed its fair share.
+	pub imbalance: f64,
+	/// Maximum number of refinement passes over a component.
+	pub refinement_passes: usize,
+}
 
-			debug_assert_eq!(
-				components.iter().map(|x| x.len()).sum::<usize>(),
-				txs.len(),
-				"All transactions must be assigned to a component.",
-			);
+impl Default for MinCut {
+	fn default() -> Self {
+		Self {
+			max_conflict_ratio: 0.2,
+			imbalance: 0.1,
+			refinement_passes: 8,
+		}
+	}
+}
 
-			let mut component_workers: BTreeMap<ThreadId, Vec<Rc<NodeCell>>> = BTreeMap::new();
-			worker_ids.iter().for_each(|i| {
-				component_workers.insert(*i, vec![]);
-			});
+impl MinCut {
+	/// Split `component` into at most `parts` partitions.
+	///
+	/// Returns the partitions and the number of transactions that access a shared key.
+	fn partition(
+		&self,
+		component: &[usize],
+		parts: usize,
+		tx_keys: &[Vec<usize>],
+		key_txs: &[Vec<usize>],
+	) -> (Vec<Vec<usize>>, usize) {
+		use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+		// the greedy assignment fills each partition up to its fair share, the refinement may
+		// exceed it up to `capacity`.
+		let fair_share = component.len().div_ceil(parts);
+		let capacity = (fair_share as f64 * (1f64 + self.imbalance)).ceil() as usize;
+		let mut part_of: BTreeMap<usize, usize> = BTreeMap::new();
+		let mut sizes = vec![0usize; parts];
+		// number of transactions in each partition that access each key.
+		let mut key_usage: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
 
-			loop {
-				// Get the largest component.
-				let (index, largest_component) = components
+		// greedy assignment, in breadth-first order so that neighbours are placed close to one
+		// another.
+		let mut visited = BTreeSet::new();
+		let mut queue = VecDeque::new();
+		for start in component {
+			if !visited.insert(*start) {
+				continue;
+			}
+			queue.push_back(*start);
+			while let Some(tx) = queue.pop_front() {
+				let part = (0..parts)
+					.filter(|p| sizes[*p] < fair_share)
+					.max_by(|x, y| {
+						let score = |p: usize| {
+							let neighbours = tx_keys[tx]
+								.iter()
+								.filter_map(|k| key_usage.get(k).map(|u| u[p]))
+								.sum::<usize>();
+							(neighbours as f64) * (1f64 - sizes[p] as f64 / fair_share as f64)
+						};
+						score(*x)
+							.partial_cmp(&score(*y))
+							.unwrap()
+							.then(sizes[*y].cmp(&sizes[*x]))
+							.then(y.cmp(x))
+					})
+					.expect("Total capacity is at least the size of the component; qed.");
+				part_of.insert(tx, part);
+				sizes[part] += 1;
+				tx_keys[tx]
 					.iter()
-					.enumerate()
-					.max_by_key(|(_, x)| x.len())
-					.map(|(i, x)| (i, x.clone()))
-					.unwrap();
-				// smallest thread group.
-				let (k, v) = component_workers
-					.iter_mut()
-					.min_by_key(|(_, v)| v.len())
-					.unwrap();
+					.for_each(|k| key_usage.entry(*k).or_insert_with(|| vec![0; parts])[part] += 1);
 
-				logging::log!(
-					trace,
-					"assigning component with {} transactions to thread {}",
-					largest_component.len(),
-					k,
-				);
-				v.extend(largest_component);
+				tx_keys[tx]
+					.iter()
+					.flat_map(|k| key_txs[*k].iter())
+					.for_each(|next| {
+						if visited.insert(*next) {
+							queue.push_back(*next);
+						}
+					});
+			}
+		}
+
+		// refinement: move single transactions to where they share the most keys.
+		for _ in 0..self.refinement_passes {
+			let mut moved = false;
+			for tx in component {
+				let current = part_of[tx];
+				let best = (0..parts)
+					.filter(|p| *p != current && sizes[*p] < capacity)
+					.map(|p| {
+						let gain = tx_keys[*tx]
+							.iter()
+							.map(|k| {
+								let usage = &key_usage[k];
+								(usage[current] == 1) as isize - (usage[p] == 0) as isize
+							})
+							.sum::<isize>();
+						(gain, p)
+					})
+					.max_by(|(g1, p1), (g2, p2)| g1.cmp(g2).then(p2.cmp(p1)));
 
-				components.remove(index);
-				if components.len() == 0 {
-					break;
+				if let Some((gain, target)) = best {
+					if gain > 0 {
+						tx_keys[*tx].iter().for_each(|k| {
+							let usage = key_usage.get_mut(k).unwrap();
+							usage[current] -= 1;
+							usage[target] += 1;
+						});
+						sizes[current] -= 1;
+						sizes[target] += 1;
+						part_of.insert(*tx, target);
+						moved = true;
+					}
 				}
 			}
+			if !moved {
+				break;
+			}
+		}
 
-			debug_assert!(components.is_empty(), "All components must be drained.");
-			debug_assert_eq!(
-				component_workers
+		let conflicts = component
+			.iter()
+			.filter(|tx| {
+				tx_keys[**tx]
 					.iter()
-					.map(|(_, v)| v.len())
-					.sum::<usize>(),
-				txs.len(),
-				"all transactions within the components must be assigned to a worker."
-			);
+					.any(|k| key_usage[k].iter().filter(|u| **u > 0).count() > 1)
+			})
+			.count();
+		let mut partitions = vec![vec![]; parts];
+		component
+			.iter()
+			.for_each(|tx| partitions[part_of[tx]].push(*tx));
+		partitions.retain(|p| !p.is_empty());
+		(partitions, conflicts)
+	}
+}
 
-			logging::log!(
-				info,
-				"Assigning outcome = {:?}",
-				component_workers
-					.iter()
-					.map(|(k, v)| (k, v.len()))
-					.collect::<Vec<_>>()
-			);
+impl<P: TransactionPool<Transaction>> Distributer<P> for MinCut {
+	fn distribute(&mut self, runtime: &SequentialRuntime, worker_ids: &[ThreadId], pool: &mut P) {
+		let graph = AccessGraph::new(runtime, pool);
+		let components = graph.components();
 
-			component_workers
-				.into_iter()
-				.for_each(|(worker, tx_nodes)| {
-					tx_nodes
-						.into_iter()
-						.map(|tx| match tx.borrow().node_type {
-							NodeType::Tx(id) => id,
-							NodeType::Key(_) => panic!("Unexpected node type"),
-						})
-						.for_each(|tid| {
-							let (_, tx) = txs.get_mut(|t| t.id == tid).unwrap();
-							tx.tag = ExecutionTag::Done(worker);
-						})
-				});
-
-			debug_assert!(
-				txs.iter().all(|tx| matches!(tx.tag, ExecutionTag::Done(_))),
-				"All transactions inside the pool must have a Done(_) status.",
-			)
+		// split the ones that are larger than the fair share of a thread.
+		let num_workers = worker_ids.len();
+		let fair_share = graph.tx_keys.len().div_ceil(num_workers.max(1));
+		let mut units = vec![];
+		for component in components {
+			if num_workers < 2 || component.len() <= fair_share {
+				units.push(component);
+				continue;
+			}
+
+			let budget = (component.len() as f64 * self.max_conflict_ratio) as usize;
+			let accepted = (2..=num_workers).rev().find_map(|parts| {
+				let (partitions, conflicts) =
+					self.partition(&component, parts, &graph.tx_keys, &graph.key_txs);
+				logging::log!(
+					debug,
+					"splitting component of {} into {} partitions with {} conflicts (budget {})",
+					component.len(),
+					partitions.len(),
+					conflicts,
+					budget,
+				);
+				if conflicts <= budget {
+					Some(partitions)
+				} else {
+					None
+				}
+			});
+
+			match accepted {
+				Some(partitions) => units.extend(partitions),
+				None => units.push(component),
+			}
 		}
+
+		// largest first, to the least loaded worker.
+		units.sort_by_key(|unit| std::cmp::Reverse(unit.len()));
+		let mut loads = worker_ids.iter().map(|w| (*w, 0usize)).collect::<Vec<_>>();
+		let mut assignment = vec![0 as ThreadId; graph.tx_keys.len()];
+		for unit in units {
+			let (worker, load) = loads
+				.iter_mut()
+				.min_by_key(|(_, load)| *load)
+				.expect("There is at least one worker; qed.");
+			*load += unit.len();
+			unit.iter().for_each(|tx| assignment[*tx] = *worker);
+		}
+
+		logging::log!(info, "Assigning outcome = {:?}", loads);
+		pool.iter_mut()
+			.zip(assignment)
+			.for_each(|(tx, worker)| tx.tag = ExecutionTag::Done(worker));
 	}
+}
 
-	#[cfg(test)]
-	mod test_distributer {
-		use super::*;
-		use crate::{types, Pool, State};
+/// A distributer that learns from the conflicts of the previous blocks, as recorded in its
+/// [`ExecutionHistory`].
+///
+/// Transactions that access a badly hinted key are sent straight to the orphan pool, since they
+/// would most likely conflict anyhow. Transactions that access a hot key are all sent to a
+/// dedicated thread, the last one, such that they cannot conflict with one another. The rest are
+/// distributed by the base distributer among the remaining threads.
+pub struct Adaptive<P: TransactionPool<Transaction>> {
+	/// The distributer of the transactions that access no hot or badly hinted key.
+	base: Box<dyn Distributer<P>>,
+	/// What has been learned so far.
+	history: ExecutionHistory,
+}
 
-		#[test]
-		fn it_works() {
-			logging::init_logger();
-			let (load, _) = types::transaction_generator::bank(50, 10, 100);
-			let mut pool = Pool::from(load);
-			let state = State::default().as_arc();
-			let rt = SequentialRuntime::new(state, 1);
-			let workers = vec![1, 2, 3];
+impl<P: TransactionPool<Transaction>> std::fmt::Debug for Adaptive<P> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Adaptive")
+			.field("base", &self.base)
+			.field("history", &self.history)
+			.finish()
+	}
+}
 
-			// all the assertions are in the code.
-			ConnectedComponents::distribute(&rt, &workers, &mut pool);
+impl<P: TransactionPool<Transaction>> Adaptive<P> {
+	/// Create a new adaptive distributer on top of `base`, with no history.
+	pub fn new(base: Box<dyn Distributer<P>>) -> Self {
+		Self {
+			base,
+			history: Default::default(),
 		}
 	}
 }
 
-/// A dumb, naive, round robin transaction distributer.
-pub struct RoundRobin;
+/// Where [`Adaptive`] sends a transaction.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Route {
+	Orphan,
+	Dedicated,
+	Free,
+}
 
-impl Distributer for RoundRobin {
-	fn distribute<P: TransactionPool<Transaction>>(
-		_: &SequentialRuntime,
-		worker_ids: &[ThreadId],
-		pool: &mut P,
-	) {
-		let num_workers = worker_ids.len();
+impl<P: TransactionPool<Transaction>> Distributer<P> for Adaptive<P> {
+	fn distribute(&mut self, runtime: &SequentialRuntime, worker_ids: &[ThreadId], pool: &mut P) {
+		let history = &mut self.history;
+		let routes = pool
+			.iter()
+			.map(|tx| {
+				let keys = runtime.validate(&tx.function, tx.signature.0);
+				history.access(&keys);
+				if keys.iter().any(|k| history.is_badly_hinted(k)) {
+					Route::Orphan
+				} else if worker_ids.len() > 1 && keys.iter().any(|k| history.is_hot(k)) {
+					Route::Dedicated
+				} else {
+					Route::Free
+				}
+			})
+			.collect::<Vec<_>>();
 
-		pool.iter_mut().enumerate().for_each(|(idx, tx)| {
-			let assigned_worker = worker_ids[idx % num_workers];
-			tx.tag = ExecutionTag::Done(assigned_worker);
+		let (base_workers, dedicated_worker) = if routes.contains(&Route::Dedicated) {
+			let (last, rest) = worker_ids
+				.split_last()
+				.expect("There is at least one worker; qed.");
+			(rest, Some(*last))
+		} else {
+			(worker_ids, None)
+		};
+
+		let mut free = P::from(
+			pool.iter()
+				.zip(routes.iter())
+				.filter(|(_, route)| **route == Route::Free)
+				.map(|(tx, _)| tx.clone())
+				.collect::<Vec<_>>(),
+		);
+		self.base.distribute(runtime, base_workers, &mut free);
+		let mut free_tags = free
+			.iter()
+			.map(|tx| (tx.id, tx.tag))
+			.collect::<HashMap<_, _>>();
+
+		logging::log!(
+			info,
+			"Adaptive distribution: {} orphan, {} dedicated to {:?}, {} free.",
+			routes.iter().filter(|r| **r == Route::Orphan).count(),
+			routes.iter().filter(|r| **r == Route::Dedicated).count(),
+			dedicated_worker,
+			free_tags.len(),
+		);
+
+		pool.iter_mut().zip(routes).for_each(|(tx, route)| {
+			tx.tag = match route {
+				Route::Orphan => ExecutionTag::Orphan,
+				Route::Dedicated => {
+					history.dedicate(tx.id);
+					ExecutionTag::Done(dedicated_worker.expect("Dedicated thread exists; qed."))
+				}
+				Route::Free => free_tags
+					.remove(&tx.id)
+					.expect("All free transactions are distributed by the base; qed."),
+			}
 		});
 	}
+
+	fn report_conflict(&mut self, runtime: &SequentialRuntime, tx: &Transaction) {
+		self.history
+			.record_conflict(tx.id, &runtime.validate(&tx.function, tx.signature.0));
+		self.base.report_conflict(runtime, tx);
+	}
+
+	fn finish_block(&mut self) {
+		self.history.finish_block();
+		self.base.finish_block();
+	}
+
+	fn history(&self) -> Option<&ExecutionHistory> {
+		Some(&self.history)
+	}
 }
 
 #[cfg(test)]
@@ -365,7 +694,7 @@ mod tests {
 	use super::*;
 	use crate::{
 		pool::VecPool,
-		types::{ExecutionTag, Transaction},
+		types::{transaction_generator, ExecutionTag, Transaction},
 	};
 	use primitives::testing;
 
@@ -373,13 +702,16 @@ mod tests {
 	fn round_robin_works() {
 		let mut pool = <VecPool<Transaction>>::new();
 
-		pool.push_back(Transaction::new_transfer(testing::alice(), 1));
-		pool.push_back(Transaction::new_transfer(testing::bob(), 2));
-		pool.push_back(Transaction::new_transfer(testing::dave(), 3));
+		pool.push_back(Transaction::new_transfer(testing::alice(), 1))
+			.unwrap();
+		pool.push_back(Transaction::new_transfer(testing::bob(), 2))
+			.unwrap();
+		pool.push_back(Transaction::new_transfer(testing::dave(), 3))
+			.unwrap();
 
 		let ids = vec![1, 2];
 
-		RoundRobin::distribute(&Default::default(), ids.as_ref(), &mut pool);
+		RoundRobin.distribute(&Default::default(), ids.as_ref(), &mut pool);
 
 		assert_eq!(
 			pool.get(|t| t.signature.0 == testing::alice().public())
@@ -405,4 +737,337 @@ mod tests {
 			ExecutionTag::Done(1)
 		);
 	}
+
+	#[test]
+	fn hash_partition_keeps_senders_together() {
+		let mut pool = <VecPool<Transaction>>::new();
+		let senders = [testing::alice, testing::bob, testing::dave, testing::eve];
+		for i in 0..20 {
+			pool.push_back(transaction_generator::build_transfer(
+				i as TransactionId,
+				senders[i % senders.len()](),
+				testing::random().public(),
+			))
+			.unwrap();
+		}
+
+		let ids = vec![1, 2, 3];
+		HashPartition.distribute(&Default::default(), ids.as_ref(), &mut pool);
+
+		for sender in senders {
+			let tags = pool
+				.iter()
+				.filter(|t| t.signature.0 == sender().public())
+				.map(|t| t.tag)
+				.collect::<Vec<_>>();
+			assert_eq!(tags.len(), 5);
+			assert!(tags.iter().all(|tag| *tag == tags[0]));
+			assert!(matches!(tags[0], ExecutionTag::Done(id) if ids.contains(&id)));
+		}
+	}
+
+	#[test]
+	fn stable_hash_is_fnv1a() {
+		// the reference values of the FNV-1a 64-bit hash.
+		assert_eq!(stable_hash(&Vec::new().into()), 0xcbf2_9ce4_8422_2325);
+		assert_eq!(stable_hash(&b"a".to_vec().into()), 0xaf63_dc4c_8601_ec8c);
+		assert_eq!(
+			stable_hash(&b"foobar".to_vec().into()),
+			0x8594_4171_f739_67e8
+		);
+	}
+
+	#[test]
+	fn hash_partition_is_stable() {
+		let (load, _) = transaction_generator::bank(20, 100, 10);
+		let mut pool1 = <VecPool<Transaction>>::from(load.clone());
+		let mut pool2 = <VecPool<Transaction>>::from(load);
+
+		let ids = vec![1, 2, 3, 4];
+		HashPartition.distribute(&Default::default(), ids.as_ref(), &mut pool1);
+		HashPartition.distribute(&Default::default(), ids.as_ref(), &mut pool2);
+
+		assert_eq!(
+			pool1.iter().map(|t| t.tag).collect::<Vec<_>>(),
+			pool2.iter().map(|t| t.tag).collect::<Vec<_>>(),
+		);
+		assert!(pool1
+			.iter()
+			.all(|t| matches!(t.tag, ExecutionTag::Done(id) if ids.contains(&id))));
+	}
+
+	/// Two stars around the keys of `hubs`, each sending to random accounts, bridged by both hubs
+	/// sending to alice.
+	fn bridged_stars(hubs: [fn() -> Pair; 2], size: usize) -> Vec<Transaction> {
+		let mut txs = vec![];
+		for hub in hubs.iter() {
+			for nonce in 0..size {
+				let id = txs.len() as TransactionId;
+				txs.push(transaction_generator::build_transfer_with_nonce(
+					id,
+					hub(),
+					testing::random().public(),
+					nonce as Nonce,
+				));
+			}
+			let id = txs.len() as TransactionId;
+			txs.push(transaction_generator::build_transfer_with_nonce(
+				id,
+				hub(),
+				testing::alice().public(),
+				size as Nonce,
+			));
+		}
+		txs
+	}
+
+	#[test]
+	fn min_cut_splits_giant_component() {
+		let hubs = [testing::bob().public(), testing::dave().public()];
+		let mut pool =
+			<VecPool<Transaction>>::from(bridged_stars([testing::bob, testing::dave], 10));
+
+		// all of them are in one component.
+		let ids = vec![1, 2];
+		ConnectedComponents.distribute(&Default::default(), ids.as_ref(), &mut pool);
+		assert!(pool
+			.iter()
+			.all(|t| t.tag == pool.iter().next().unwrap().tag));
+
+		MinCut::default().distribute(&Default::default(), ids.as_ref(), &mut pool);
+		let tags_of = |hub: AccountId| {
+			pool.iter()
+				.filter(|t| t.signature.0 == hub)
+				.map(|t| t.tag)
+				.collect::<Vec<_>>()
+		};
+		let bob_tags = tags_of(hubs[0]);
+		let dave_tags = tags_of(hubs[1]);
+		assert_eq!(bob_tags.len(), 11);
+		assert_eq!(dave_tags.len(), 11);
+		assert!(bob_tags.iter().all(|t| *t == bob_tags[0]));
+		assert!(dave_tags.iter().all(|t| *t == dave_tags[0]));
+		assert_ne!(bob_tags[0], dave_tags[0]);
+	}
+
+	#[test]
+	fn min_cut_keeps_component_if_too_many_conflicts() {
+		// a single hot key, any split would conflict with all of the transactions.
+		let hub = testing::bob().public();
+		let mut pool = <VecPool<Transaction>>::from(
+			(0..20)
+				.map(|id| transaction_generator::build_transfer(id, testing::random(), hub))
+				.collect::<Vec<_>>(),
+		);
+
+		let ids = vec![1, 2, 3];
+		MinCut::default().distribute(&Default::default(), ids.as_ref(), &mut pool);
+		let first = pool.iter().next().unwrap().tag;
+		assert!(matches!(first, ExecutionTag::Done(id) if ids.contains(&id)));
+		assert!(pool.iter().all(|t| t.tag == first));
+	}
+
+	#[test]
+	fn min_cut_balances_independent_transactions() {
+		let (load, _) = transaction_generator::bank(1000, 40, 10);
+		let mut pool = <VecPool<Transaction>>::from(load);
+
+		let ids = vec![1, 2, 3, 4];
+		MinCut::default().distribute(&Default::default(), ids.as_ref(), &mut pool);
+
+		for id in ids {
+			let count = pool
+				.iter()
+				.filter(|t| t.tag == ExecutionTag::Done(id))
+				.count();
+			assert!((5..=15).contains(&count), "unbalanced: {}", count);
+		}
+	}
+
+	#[test]
+	fn access_graph_components_work() {
+		//  t0         t1         t2
+		//  |           |         |
+		//  |--- k1 ----|         |--- k4
+		//  |--- k2 ----|         |--- k5
+		//  |--- k3 ----|
+		let txs = vec![
+			Transaction::new_transfer_to(testing::alice(), testing::bob().public()),
+			Transaction::new_transfer_to(testing::alice(), testing::bob().public()),
+			Transaction::new_transfer_to(testing::dave(), testing::dave().public()),
+		];
+		let pool = <VecPool<Transaction>>::from(txs);
+		let graph = AccessGraph::new(&Default::default(), &pool);
+
+		// the nonce of the origin is accessed as well.
+		assert_eq!(
+			graph.tx_keys,
+			vec![vec![0, 1, 2], vec![0, 1, 2], vec![3, 4]]
+		);
+		assert_eq!(
+			graph.key_txs,
+			vec![vec![0, 1], vec![0, 1], vec![0, 1], vec![2], vec![2]]
+		);
+		assert_eq!(graph.components(), vec![vec![0, 1], vec![2]]);
+	}
+
+	#[test]
+	fn connected_components_works() {
+		logging::init_logger();
+		let (load, _) = transaction_generator::bank(50, 10, 100);
+		let mut pool = <VecPool<Transaction>>::from(load);
+		let workers = vec![1, 2, 3];
+
+		// all the assertions are in the code.
+		ConnectedComponents.distribute(&Default::default(), &workers, &mut pool);
+		assert!(pool
+			.iter()
+			.all(|tx| matches!(tx.tag, ExecutionTag::Done(w) if workers.contains(&w))));
+	}
+
+	#[test]
+	fn connected_components_balances_by_weight() {
+		use runtime::{staking, OuterCall};
+
+		// two heavy bonds of alice in one component, and three light, independent transfers.
+		let bond = |id| {
+			let origin = testing::alice();
+			let call = OuterCall::Staking(staking::Call::TxBond(10, origin.public()));
+			transaction_generator::sign(id, call, id as Nonce, &origin)
+		};
+		let mut load = vec![bond(0), bond(1)];
+		load.extend((2..5).map(|id| {
+			transaction_generator::build_transfer(id, testing::random(), testing::random().public())
+		}));
+		let mut pool = <VecPool<Transaction>>::from(load);
+
+		// counting transactions would put a transfer next to the bonds, weights do not.
+		ConnectedComponents.distribute(&Default::default(), &[1, 2], &mut pool);
+		let tag_of = |id| pool.get(|t| t.id == id).unwrap().1.tag;
+		assert_eq!(tag_of(0), tag_of(1));
+		assert!((2..5).all(|id| tag_of(id) != tag_of(0)));
+	}
+
+	#[test]
+	fn connected_components_works_with_empty_pool() {
+		let mut pool = <VecPool<Transaction>>::new();
+		ConnectedComponents.distribute(&Default::default(), &[1, 2], &mut pool);
+		assert_eq!(pool.len(), 0);
+	}
+
+	fn balance_key(who: AccountId) -> StateKey {
+		<runtime::balances::BalanceOf<SequentialRuntime>>::key_for(who)
+	}
+
+	#[test]
+	fn execution_history_learns_hot_keys() {
+		let (alice, bob) = (testing::alice().public(), testing::bob().public());
+		let mut history = ExecutionHistory::default();
+
+		history.access(&[balance_key(alice), balance_key(bob)]);
+		history.record_conflict(0, &[balance_key(alice), balance_key(bob)]);
+		history.finish_block();
+		assert!(history.is_hot(&balance_key(alice)));
+		assert!(history.is_hot(&balance_key(bob)));
+
+		// still accessed, still hot.
+		history.access(&[balance_key(alice)]);
+		history.finish_block();
+		assert!(history.is_hot(&balance_key(alice)));
+		assert!(!history.is_hot(&balance_key(bob)));
+
+		// eventually forgotten.
+		(0..30).for_each(|_| history.finish_block());
+		assert!(history.conflicts.is_empty());
+	}
+
+	#[test]
+	fn execution_history_learns_badly_hinted_keys() {
+		let (alice, bob) = (testing::alice().public(), testing::bob().public());
+		let mut history = ExecutionHistory::default();
+		history.conflicts.insert(balance_key(alice), 1.0);
+		history.access(&[balance_key(alice), balance_key(bob)]);
+
+		// a conflict of a dedicated transaction whose keys are all hot is a miss.
+		history.dedicate(0);
+		history.record_conflict(0, &[balance_key(alice)]);
+		// if one of them is not hot, then that one is just a conflict.
+		history.dedicate(1);
+		history.record_conflict(1, &[balance_key(alice), balance_key(bob)]);
+		history.finish_block();
+
+		assert!(history.is_badly_hinted(&balance_key(alice)));
+		assert!(!history.is_badly_hinted(&balance_key(bob)));
+		assert!(history.is_hot(&balance_key(bob)));
+
+		// and retried after a while.
+		(0..10).for_each(|_| history.finish_block());
+		assert!(!history.is_badly_hinted(&balance_key(alice)));
+	}
+
+	#[test]
+	fn adaptive_routes_by_history() {
+		let hub = testing::bob().public();
+		let (dave, eve) = (testing::dave(), testing::eve().public());
+		let mut adaptive = Adaptive::new(Box::new(RoundRobin));
+		adaptive.history.conflicts.insert(balance_key(hub), 1.0);
+		adaptive
+			.history
+			.misses
+			.insert(balance_key(dave.public()), 1.0);
+
+		let mut pool = <VecPool<Transaction>>::from(vec![
+			transaction_generator::build_transfer(0, testing::random(), hub),
+			transaction_generator::build_transfer(1, dave, eve),
+			transaction_generator::build_transfer(2, testing::random(), eve),
+			transaction_generator::build_transfer(3, testing::random(), testing::random().public()),
+		]);
+		adaptive.distribute(&Default::default(), &[1, 2, 3], &mut pool);
+
+		let tags = pool.iter().map(|tx| tx.tag).collect::<Vec<_>>();
+		assert_eq!(
+			tags,
+			vec![
+				ExecutionTag::Done(3),
+				ExecutionTag::Orphan,
+				ExecutionTag::Done(1),
+				ExecutionTag::Done(2)
+			]
+		);
+		let history = adaptive.history().unwrap();
+		assert!(history.dedicated.contains(&0));
+		assert!(history.active.contains(&balance_key(eve)));
+	}
+
+	#[test]
+	fn adaptive_uses_all_workers_without_hot_keys() {
+		let (load, _) = transaction_generator::bank(50, 20, 100);
+		let mut pool = <VecPool<Transaction>>::from(load);
+		let ids = vec![1, 2, 3];
+		Adaptive::new(Box::new(RoundRobin)).distribute(
+			&Default::default(),
+			ids.as_ref(),
+			&mut pool,
+		);
+		for id in ids {
+			assert!(pool.iter().any(|t| t.tag == ExecutionTag::Done(id)));
+		}
+	}
+
+	#[test]
+	fn distributers_by_name() {
+		for name in DISTRIBUTERS.iter() {
+			let mut distributer = by_name::<VecPool<Transaction>>(name).unwrap();
+			let (load, _) = transaction_generator::bank(20, 10, 100);
+			let mut pool = <VecPool<Transaction>>::from(load);
+			distributer.distribute(&Default::default(), &[1, 2], &mut pool);
+			assert!(pool
+				.iter()
+				.all(|tx| matches!(tx.tag, ExecutionTag::Done(1) | ExecutionTag::Done(2))));
+		}
+
+		assert!(by_name::<VecPool<Transaction>>("Adaptive:MinCut").is_some());
+		assert!(by_name::<VecPool<Transaction>>("Adaptive:Foo").is_none());
+		assert!(by_name::<VecPool<Transaction>>("Foo").is_none());
+	}
 }
@@ -1,24 +1,34 @@
 use crate::{
-	types::{ExecutionStatus, ExecutionTag, Message, MessagePayload, TaskType, Transaction},
+	types::{
+		is_expired, ExecutionStatus, ExecutionTag, InvalidBlock, Message, MessagePayload, TaskType,
+		Transaction,
+	},
 	State,
 };
 use logging::log;
-use primitives::{ThreadId, TransactionId};
+use primitives::{StateKey, ThreadId, TransactionId};
 use runtime::{
 	ConcurrentRuntime, RuntimeDispatchError, RuntimeDispatchSuccess, RuntimeDispatchSuccessCount,
-	SequentialRuntime,
 };
 use std::{
-	collections::BTreeMap,
+	collections::{BTreeMap, HashMap, HashSet, VecDeque},
 	sync::{
 		mpsc::{Receiver, Sender},
 		Arc,
 	},
-	thread,
+	time::Instant,
 };
 
 const LOG_TARGET: &'static str = "worker";
 
+/// The default [`Worker::forward_budget`]. A transaction is forwarded at most once, and then it is
+/// orphaned.
+pub const DEFAULT_FORWARD_BUDGET: usize = 1;
+
+/// The outcome of a task of a worker. Fails with the first message that is not expected at that
+/// point, upon which the task is abandoned.
+type TaskResult<T = ()> = Result<T, Box<Message>>;
+
 /// The execution outcome of a transaction.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub(crate) enum ExecutionOutcome {
@@ -30,6 +40,66 @@ pub(crate) enum ExecutionOutcome {
 	Forwarded(ThreadId),
 	/// This transaction was forwarded to master as Orphan.
 	ForwardedToMaster,
+	/// This transaction was not executed due to a stale nonce, and was reported back to master.
+	Skipped,
+}
+
+/// The transactions that the master has assigned to a worker, which are not executed yet.
+#[derive(Debug, Default)]
+struct LocalQueue {
+	/// The transactions, along with their deduplicated access keys.
+	transactions: VecDeque<(Transaction, Vec<StateKey>)>,
+	/// Number of transactions in the queue that access each key.
+	key_usage: HashMap<StateKey, usize>,
+	/// The access keys of all transactions that have been popped. These are most likely tainted by
+	/// the worker already.
+	popped_keys: HashSet<StateKey>,
+}
+
+impl LocalQueue {
+	fn push(&mut self, tx: Transaction, mut keys: Vec<StateKey>) {
+		keys.sort();
+		keys.dedup();
+		keys.iter()
+			.for_each(|key| *self.key_usage.entry(key.clone()).or_default() += 1);
+		self.transactions.push_back((tx, keys));
+	}
+
+	fn pop(&mut self) -> Option<Transaction> {
+		let (tx, keys) = self.transactions.pop_front()?;
+		self.release(&keys);
+		self.popped_keys.extend(keys);
+		Some(tx)
+	}
+
+	fn remove(&mut self, index: usize) -> Option<Transaction> {
+		let (tx, keys) = self.transactions.remove(index)?;
+		self.release(&keys);
+		Some(tx)
+	}
+
+	fn release(&mut self, keys: &[StateKey]) {
+		keys.iter().for_each(|key| {
+			if let Some(usage) = self.key_usage.get_mut(key) {
+				*usage -= 1;
+			}
+		});
+	}
+
+	fn is_empty(&self) -> bool {
+		self.transactions.is_empty()
+	}
+
+	/// Indices of the transactions, except the first one, whose access keys are neither accessed by
+	/// any other queued transaction, nor by any popped one. The last ones come first.
+	fn independent(&self) -> impl Iterator<Item = usize> + '_ {
+		(1..self.transactions.len()).rev().filter(move |index| {
+			self.transactions[*index]
+				.1
+				.iter()
+				.all(|key| self.key_usage.get(key) == Some(&1) && !self.popped_keys.contains(key))
+		})
+	}
 }
 
 /// A worker thread.
@@ -44,16 +114,18 @@ pub struct Worker {
 	pub state: Arc<State>,
 	/// The runtime. This is used for authoring.
 	pub runtime: ConcurrentRuntime,
-	/// The master runtime. This is used for validation.
-	pub sequential_runtime: runtime::SequentialRuntime,
 	/// Channel to send messages to master.
 	pub to_master: Sender<Message>,
-	/// Channel to receive data from the master.
-	pub from_master: Receiver<Message>,
 	/// Map of channels to send messages to other workers.
 	pub to_others: BTreeMap<ThreadId, Sender<Message>>,
-	/// Channel to receive messages from other workers.
-	pub from_others: Receiver<Message>,
+	/// Channel to receive messages from both the master and other workers.
+	///
+	/// Messages of the master are told apart by their `from` being equal to `master_id`. Having a
+	/// single inbox allows the worker to block on it, instead of spinning on multiple channels.
+	pub inbox: Receiver<Message>,
+	/// The number of times that a transaction can be forwarded between workers before it is
+	/// forwarded to master as an orphan.
+	pub forward_budget: usize,
 }
 
 impl Worker {
@@ -63,120 +135,185 @@ impl Worker {
 		master_id: ThreadId,
 		state: Arc<State>,
 		to_master: Sender<Message>,
-		from_master: Receiver<Message>,
-		from_others: Receiver<Message>,
+		inbox: Receiver<Message>,
 	) -> Self {
 		let runtime = ConcurrentRuntime::new(state.clone(), id);
-		let sequential_runtime = SequentialRuntime::new(state.clone(), id);
 		Self {
 			id,
 			master_id,
 			state,
 			runtime,
-			sequential_runtime,
 			to_master,
-			from_master,
 			to_others: Default::default(),
-			from_others,
+			inbox,
+			forward_budget: DEFAULT_FORWARD_BUDGET,
 		}
 	}
 
-	/// Call [`Self::new`] with the current thread id.
-	pub fn new_from_thread(
-		master_id: ThreadId,
-		state: Arc<State>,
-		to_master: Sender<Message>,
-		from_master: Receiver<Message>,
-		from_others: Receiver<Message>,
-	) -> Self {
-		let id = thread::current().id().as_u64().into();
-		Self::new(id, master_id, state, to_master, from_master, from_others)
+	/// Wait to receive the btree map of all other workers.
+	///
+	/// Any other message is reported to master, and the worker should not run afterwards.
+	pub fn wait_finalize_setup(&mut self) -> TaskResult {
+		match self.inbox.recv().unwrap() {
+			Message {
+				payload: MessagePayload::FinalizeSetup(data),
+				..
+			} => {
+				self.to_others = data;
+				Ok(())
+			}
+			unexpected => {
+				self.report_unexpected(&unexpected);
+				Err(Box::new(unexpected))
+			}
+		}
 	}
 
-	/// Wait to receive the btree map of all other workers.
-	pub fn wait_finalize_setup(&mut self) {
-		match self.from_master.recv().unwrap().payload {
-			MessagePayload::FinalizeSetup(data) => self.to_others = data,
-			_ => panic!("Received unexpected message"),
-		};
+	/// Report a message that we did not expect back to master.
+	///
+	/// The master might be gone already, e.g. if it is dropped in the middle of a task, in which
+	/// case there is no one to report to.
+	fn report_unexpected(&self, message: &Message) {
+		log!(error, "Unexpected message {:?}.", message);
+		let _ = self
+			.to_master
+			.send(Message::new(MessagePayload::WorkerError, self.id));
 	}
 
 	/// Main logic of the worker.
 	///
-	/// The worker is always parked, and need to be unparked by the master. Once unparked, the
-	/// worker will wait for one message indicate the type of `Task` that need to be carried out.
-	/// Once the task is done, the worker parks loops back to parking itself.
+	/// The worker blocks on its inbox until the master sends one message indicating the type of
+	/// `Task` that needs to be carried out. Once the task is done, the worker loops back to waiting
+	/// for the next one.
+	///
+	/// Note that the worker must not park itself while idle: a blocking receive might consume the
+	/// unpark token of the thread.
+	///
+	/// An unexpected message is reported back to master, and the worker waits for the next task,
+	/// abandoning the current one if any. An unexpected `Terminate` is obeyed nonetheless.
 	pub fn run(self) {
 		loop {
 			log!(
 				debug,
-				"Worker thread task loop started. Going to park until task."
+				"Worker thread task loop started. Going to wait for a task."
 			);
-			// park self.
-			thread::park();
 
 			// look for a task.
-			let Message { payload, from: _ } = self.from_master.recv().unwrap();
+			let Message {
+				payload,
+				from: _from,
+			} = self.inbox.recv().unwrap();
+			debug_assert_eq!(_from, self.master_id);
 			log!(info, "Received task {:?}.", payload);
 
-			match payload {
+			let outcome = match payload {
 				MessagePayload::Task(t) => match t {
-					TaskType::Authoring => self.run_author(),
+					TaskType::Authoring(deadline) => self.run_author(deadline),
 					TaskType::Validating => self.run_validate(),
+					TaskType::Sync => {
+						self.to_master
+							.send(Message::new(MessagePayload::Synced, self.id))
+							.expect("Send to master should work; qed.");
+						Ok(())
+					}
 				},
 				MessagePayload::Terminate => break,
-				_ => panic!("Unexpected message"),
+				payload => Err(Box::new(Message::new(payload, _from))),
+			};
+
+			match outcome {
+				Ok(()) => (),
+				Err(unexpected) if matches!(unexpected.payload, MessagePayload::Terminate) => break,
+				Err(unexpected) => self.report_unexpected(&unexpected),
 			}
 		}
 	}
 
 	/// Run the worker thread logic in validating.
-	pub fn run_validate(&self) {
+	///
+	/// The transactions are executed with tainting enabled, where each key is tainted by the
+	/// bucket of the transaction. Transactions of a valid block that are assigned to different
+	/// buckets never access the same keys, hence any taint error indicates that the block is
+	/// invalid. In that case, the id of the first conflicting transaction is reported back to
+	/// master and the rest of the queue is skipped.
+	///
+	/// A transaction that is not assigned to any bucket is unexpected, just like any message other
+	/// than transactions and `TransactionDistributionDone`.
+	pub fn run_validate(&self) -> TaskResult {
 		// deplete the queue.
 		let mut outcomes = vec![];
+		let mut invalid = None;
 		loop {
-			if let Ok(Message {
+			let Message {
 				from: _from,
 				payload,
-			}) = self.from_master.try_recv()
-			{
-				debug_assert_eq!(_from, self.master_id);
-				log!(
-					trace,
-					"Message from master in authoring phase {:?}",
-					payload
-				);
-				match payload {
-					MessagePayload::Transaction(tx) => {
-						let Transaction {
-							function,
-							signature,
-							tag,
-							..
-						} = tx;
-						// transaction must be marked for me.
-						debug_assert!(
-							std::matches!(tag, ExecutionTag::Done(who) if who == self.id)
-						);
-						let origin = signature.0;
-						// we know that this transaction will not conflict with anyone else.
-						let outcome = self.sequential_runtime
-							.dispatch(function, origin)
-							.expect("Executing transaction in the validation phase by thread should never fail");
-						outcomes.push(outcome);
+			} = self.inbox.recv().unwrap();
+			debug_assert_eq!(_from, self.master_id);
+			log!(
+				trace,
+				"Message from master in validation phase {:?}",
+				payload
+			);
+			match payload {
+				MessagePayload::Transaction(tx) => {
+					if invalid.is_some() {
+						continue;
 					}
-					MessagePayload::TransactionDistributionDone => {
-						break;
+					let bucket = match tx.tag {
+						ExecutionTag::Done(bucket) => bucket,
+						_ => {
+							return Err(Box::new(Message::new(
+								MessagePayload::Transaction(tx),
+								_from,
+							)))
+						}
+					};
+					let Transaction {
+						id,
+						function,
+						signature,
+						nonce,
+						..
+					} = tx;
+					// taint with the bucket, not self. Multiple buckets might be assigned to us,
+					// and they must still not conflict with one another.
+					let runtime = ConcurrentRuntime::new(Arc::clone(&self.state), bucket);
+					let origin = signature.0;
+					match runtime.dispatch_transaction(function, origin, nonce) {
+						Ok(outcome) => outcomes.push(outcome),
+						Err(RuntimeDispatchError::Tainted(by_whom, _)) => {
+							log!(
+								warn,
+								"Transaction {} conflicted with bucket {} in validation.",
+								id,
+								by_whom
+							);
+							invalid = Some(InvalidBlock::Conflict(id));
+						}
+						Err(RuntimeDispatchError::BadNonce(expected)) => {
+							log!(
+								warn,
+								"Transaction {} has nonce {}, expected {}.",
+								id,
+								nonce,
+								expected
+							);
+							invalid = Some(InvalidBlock::BadNonce(id));
+						}
 					}
-					_ => panic!("Unexpected message type in validation."),
 				}
+				MessagePayload::TransactionDistributionDone => {
+					break;
+				}
+				payload => return Err(Box::new(Message::new(payload, _from))),
 			}
 		}
 
+		let message = MessagePayload::ValidationReport(invalid);
 		log!(
 			info,
 			"Sending report {:?}. From {} executed, {} were ok and {} were logic error.",
-			MessagePayload::ValidationReport,
+			message,
 			outcomes.len(),
 			outcomes.ok_count(),
 			outcomes.logic_error_count(),
@@ -184,69 +321,162 @@ impl Worker {
 
 		// report back to master and done.
 		self.to_master
-			.send(MessagePayload::ValidationReport.into())
+			.send(Message::new(message, self.id))
 			.expect("Broadcast should work");
+		Ok(())
 	}
 
-	/// Run the main worker thread logic in authoring. This will called after the initial unparking
-	/// of the master.
+	/// Run the main worker thread logic in authoring. This will called after the master sends the
+	/// authoring task.
 	///
-	/// It will loop and try and receive stuff from master until it is done, then it will read from
-	/// worker incoming queue until termination.
-	pub fn run_author(&self) {
+	/// It will loop and try and receive stuff from master until it is done, then it will execute
+	/// anything forwarded from other workers until the master signals termination.
+	///
+	/// Once the `deadline` passes, no new transaction is executed. They are all reported back to
+	/// master as skipped instead.
+	pub fn run_author(&self, deadline: Option<Instant>) -> TaskResult {
 		// execute everything from master.
-		self.deplete_master_queue();
+		let early_forwarded = self.deplete_master_queue(deadline)?;
 
-		// try and read termination signal from master, else execute anything from other workers.
+		// The transaction might have been handed over to us because we are idle. Ask the sender
+		// for more before executing it. This must happen before the execution is reported, so that
+		// the master cannot signal `TaskDone` before the request is received.
+		let execute_forwarded = |from: ThreadId, tx: Transaction| {
+			if is_expired(deadline) {
+				self.skip(tx);
+				return;
+			}
+			self.announce_idle(std::iter::once(from));
+			self.execute_or_forward(tx);
+		};
+
+		// the ones that were forwarded to us while we were still busy with the master's queue.
+		early_forwarded
+			.into_iter()
+			.for_each(|(from, tx)| execute_forwarded(from, tx));
+
+		// wait for the termination signal from master, while executing anything from other
+		// workers.
 		loop {
-			if let Ok(Message { payload, from: _ }) = self.from_master.try_recv() {
-				match payload {
-					MessagePayload::TaskDone => break,
-					_ => panic!("Unexpected message payload."),
+			let Message { payload, from } = self.inbox.recv().unwrap();
+			match payload {
+				MessagePayload::TaskDone if from == self.master_id => break,
+				MessagePayload::Transaction(tx) if from != self.master_id => {
+					execute_forwarded(from, tx)
 				}
-			}
-			if let Ok(Message { payload, from: _ }) = self.from_others.try_recv() {
-				match payload {
-					MessagePayload::Transaction(mut tx) => {
-						tx.exec_status = ExecutionStatus::Forwarded;
-						self.execute_or_forward(tx)
-					}
-					_ => panic!("Unexpected message payload"),
-				};
+				// we are idle ourselves, nothing to hand over.
+				MessagePayload::Idle if from != self.master_id => {}
+				payload => return Err(Box::new(Message::new(payload, from))),
 			}
 		}
+		Ok(())
 	}
 
 	/// Execute all the transactions in the queue from master until it is empty.
 	///
-	/// This should be called only once at the beginning of the execution.
-	fn deplete_master_queue(&self) {
+	/// This should be called only once at the beginning of the execution. Transactions that are
+	/// forwarded to us by other workers in the meantime are not executed, because they must come
+	/// after all of our own ones. They are returned instead, in the order of arrival, along with
+	/// their sender.
+	///
+	/// The transactions of the master are buffered locally as they arrive. If any other worker has
+	/// announced that it is idle, one of the buffered transactions that we have not started yet is
+	/// handed over to it (see [`Self::take_stealable`]). Handing over is accounted as forwarding.
+	///
+	/// Once the `deadline` passes, the rest of the queue is skipped and nothing is handed over
+	/// anymore.
+	fn deplete_master_queue(
+		&self,
+		deadline: Option<Instant>,
+	) -> TaskResult<Vec<(ThreadId, Transaction)>> {
 		let mut executed = 0;
 		let mut forwarded = 0;
 		let mut runtime_success_outputs = vec![];
+		let mut early_forwarded = vec![];
+		let mut queue = LocalQueue::default();
+		let mut idle_workers: VecDeque<ThreadId> = Default::default();
+		let mut distribution_done = false;
+
 		loop {
-			let Message { payload, from } = self.from_master.recv().unwrap();
-			debug_assert_eq!(from, self.master_id);
+			if queue.is_empty() && distribution_done {
+				break;
+			}
 
-			match payload {
-				MessagePayload::Transaction(tx) => {
-					let outcome = self.execute_or_forward(tx.clone());
-					match outcome {
-						ExecutionOutcome::Executed(inner) => {
-							executed += 1;
-							runtime_success_outputs.push(inner);
-						}
-						ExecutionOutcome::Forwarded(_) | ExecutionOutcome::ForwardedToMaster => {
-							forwarded += 1
-						}
+			// receive everything that has arrived so far. Only block if there is nothing to do.
+			let mut next = if queue.is_empty() {
+				Some(self.inbox.recv().unwrap())
+			} else {
+				self.inbox.try_recv().ok()
+			};
+			while let Some(Message { payload, from }) = next {
+				match payload {
+					MessagePayload::Transaction(tx) if from == self.master_id => {
+						let keys = self.runtime.validate(&tx.function, tx.signature.0);
+						queue.push(tx, keys)
+					}
+					MessagePayload::TransactionDistributionDone if from == self.master_id => {
+						distribution_done = true
 					}
+					MessagePayload::Transaction(tx) => early_forwarded.push((from, tx)),
+					MessagePayload::Idle => idle_workers.push_back(from),
+					payload => return Err(Box::new(Message::new(payload, from))),
+				};
+				next = self.inbox.try_recv().ok();
+			}
+
+			if is_expired(deadline) {
+				while let Some(tx) = queue.pop() {
+					self.skip(tx);
 				}
-				MessagePayload::TransactionDistributionDone => break,
-				_ => panic!("Unexpected message payload."),
-			};
+				continue;
+			}
+
+			// hand over one transaction to an idle worker, if we have more than just the next one.
+			if !idle_workers.is_empty() {
+				if let Some(tx) = self.take_stealable(&mut queue) {
+					let thief = idle_workers
+						.pop_front()
+						.expect("Checked to be non-empty; qed.");
+					let mut tx = tx;
+					tx.hops.push(self.id);
+					tx.exec_status = ExecutionStatus::Stolen;
+					log!(debug, "Handing over {:?} to idle worker {}.", tx, thief);
+					self.to_others
+						.get(&thief)
+						.expect("Must have queue to all other workers; qed.")
+						.send(Message::new(MessagePayload::Transaction(tx), self.id))
+						.expect("Send to others should work; qed.");
+					forwarded += 1;
+				}
+			}
+
+			if let Some(tx) = queue.pop() {
+				match self.execute_or_forward(tx) {
+					ExecutionOutcome::Executed(inner) => {
+						executed += 1;
+						runtime_success_outputs.push(inner);
+					}
+					ExecutionOutcome::Forwarded(_) | ExecutionOutcome::ForwardedToMaster => {
+						forwarded += 1
+					}
+					// skipped transactions of our own are not accounted in the report.
+					ExecutionOutcome::Skipped => {}
+				}
+			}
 		}
 
-		let message = MessagePayload::AuthoringReport(executed, forwarded).into();
+		// let others know that they can hand over their transactions to us. This must happen
+		// before the report, so that the master cannot signal `TaskDone` before it is received.
+		self.announce_idle(self.to_others.keys().cloned());
+
+		let message = Message::new(
+			MessagePayload::AuthoringReport(
+				executed,
+				forwarded,
+				runtime_success_outputs.logic_error_count(),
+			),
+			self.id,
+		);
 		log!(
 			info,
 			"Sending report {:?}. From {} executed, {} were ok and {} were logic error.",
@@ -258,26 +488,76 @@ impl Worker {
 		self.to_master
 			.send(message)
 			.expect("Sending to master cannot fail; qed");
+		Ok(early_forwarded)
+	}
+
+	/// Remove a transaction that can be handed over to another worker from the back of `queue`.
+	///
+	/// The first transaction of the queue is never handed over, since we are just about to execute
+	/// it. A transaction can only be handed over if none of its access keys are tainted by us.
+	/// Otherwise, it would inevitably be forwarded back to us. Moreover, none of its access keys
+	/// must be accessed by any other transaction in the queue, so that the two workers do not end
+	/// up forwarding transactions to one another.
+	///
+	/// Reading the taint of a key is as expensive as any other state access, hence it is only
+	/// checked for the transactions that are already known to be independent locally.
+	fn take_stealable(&self, queue: &mut LocalQueue) -> Option<Transaction> {
+		let index = queue.independent().find(|index| {
+			queue.transactions[*index]
+				.1
+				.iter()
+				.all(|key| self.state.unsafe_read_taint(key) != Some(self.id))
+		})?;
+		queue.remove(index)
+	}
+
+	/// Report a transaction that we will not execute back to master, either because the deadline
+	/// has passed or because its nonce is bad.
+	fn skip(&self, tx: Transaction) {
+		log!(trace, "Skipping {:?}.", tx);
+		self.to_master
+			.send(Message::new(
+				MessagePayload::WorkerSkipped(tx.id, tx.hops),
+				self.id,
+			))
+			.expect("Send to master should work; qed.");
+	}
+
+	/// Let all of the given workers, except ourself, know that we are idle.
+	fn announce_idle(&self, to: impl Iterator<Item = ThreadId>) {
+		to.filter(|id| *id != self.id).for_each(|id| {
+			self.to_others
+				.get(&id)
+				.expect("Must have queue to all other workers; qed.")
+				.send(Message::new(MessagePayload::Idle, self.id))
+				.expect("Send to others should work; qed.");
+		})
 	}
 
 	/// Tries to execute transaction.
 	///
 	/// If execution went okay, returns `Ok(())`, else, it returns the thread id of the owner of the
-	/// transaction.
+	/// transaction, or the expected nonce of its origin.
 	pub(crate) fn execute_transaction(&self, tx: Transaction) -> runtime::RuntimeDispatchResult {
 		let call = tx.function;
 		let origin = tx.signature.0;
-		self.runtime.dispatch(call, origin)
+		self.runtime.dispatch_transaction(call, origin, tx.nonce)
 	}
 
 	/// Tries to execute the transaction, else forward it to either another worker who owns it, or
-	/// the master of the transaction has already been forwarded.
+	/// the master.
+	///
+	/// A transaction is forwarded to master if the state is corrupt, if it has already been
+	/// forwarded `self.forward_budget` times, or if the owner has already attempted it, which would
+	/// otherwise make it go back and forth between the same workers.
 	///
 	/// This also reports to master if a transaction has been forwarded to us and we successfully
-	/// executed it.
+	/// executed it. A transaction with a stale nonce is reported back to master as skipped. One
+	/// with a future nonce is most likely waiting for another transaction of its origin, and is
+	/// forwarded to master.
 	///
-	/// NOTE: in case this forwards a transaction to another thread, it does not update the
-	/// exec_status field. The receiver should do so.
+	/// NOTE: in case this forwards a transaction to another thread, it marks it as `Forwarded`, and
+	/// adds ourselves to its hops.
 	///
 	/// This can this can never fail. Only errors will be communication, in which case it panics.
 	pub(crate) fn execute_or_forward(&self, tx: Transaction) -> ExecutionOutcome {
@@ -285,17 +565,24 @@ impl Worker {
 		let tid = tx.id;
 		let exec_status = tx.exec_status;
 		let rt_dispatch_result = self.execute_transaction(tx.clone());
+		let hops = || {
+			let mut hops = tx.hops.clone();
+			hops.push(self.id);
+			hops
+		};
 
 		let forward_to_master = |tid: TransactionId| -> ExecutionOutcome {
-			let msg = MessagePayload::WorkerOrphan(tid).into();
+			let msg = Message::new(MessagePayload::WorkerOrphan(tid, hops()), self.id);
 			self.to_master
 				.send(msg)
 				.expect("Send to master should work; qed.");
 			ExecutionOutcome::ForwardedToMaster
 		};
 
-		let forward_to_worker = |tx: Transaction, wid: ThreadId| -> ExecutionOutcome {
-			let msg = MessagePayload::Transaction(tx).into();
+		let forward_to_worker = |mut tx: Transaction, wid: ThreadId| -> ExecutionOutcome {
+			tx.hops = hops();
+			tx.exec_status = ExecutionStatus::Forwarded;
+			let msg = Message::new(MessagePayload::Transaction(tx), self.id);
 			self.to_others
 				.get(&wid)
 				.expect("Must have queue to all other workers; qed.")
@@ -304,8 +591,16 @@ impl Worker {
 			ExecutionOutcome::Forwarded(wid)
 		};
 
-		let report_execution = |tid: TransactionId| {
-			let msg = MessagePayload::WorkerExecuted(tid).into();
+		let report_execution = |tid: TransactionId, outcome: RuntimeDispatchSuccess| {
+			let msg = Message::new(
+				MessagePayload::WorkerExecuted(
+					tid,
+					outcome,
+					tx.hops.clone(),
+					exec_status == ExecutionStatus::Forwarded,
+				),
+				self.id,
+			);
 			self.to_master
 				.send(msg)
 				.expect("Send to master should work; qed.");
@@ -313,19 +608,22 @@ impl Worker {
 
 		let final_outcome = match rt_dispatch_result {
 			Err(RuntimeDispatchError::Tainted(by_whom, corrupt)) => {
-				if exec_status == ExecutionStatus::Initial {
-					if corrupt {
-						forward_to_master(tid)
-					} else {
-						forward_to_worker(tx.clone(), by_whom)
-					}
-				} else {
+				if corrupt || tx.hops.len() >= self.forward_budget || tx.hops.contains(&by_whom) {
 					forward_to_master(tid)
+				} else {
+					forward_to_worker(tx.clone(), by_whom)
 				}
 			}
+			Err(RuntimeDispatchError::BadNonce(expected)) if tx.nonce > expected => {
+				forward_to_master(tid)
+			}
+			Err(RuntimeDispatchError::BadNonce(_)) => {
+				self.skip(tx.clone());
+				ExecutionOutcome::Skipped
+			}
 			Ok(ok) => {
-				if exec_status == ExecutionStatus::Forwarded {
-					report_execution(tid);
+				if exec_status != ExecutionStatus::Initial {
+					report_execution(tid, ok);
 				}
 				ExecutionOutcome::Executed(ok)
 			}
@@ -339,31 +637,37 @@ impl Worker {
 	pub fn test_run(self) {
 		// send this to master.
 		self.to_master
-			.send(Message::new_from_thread(MessagePayload::Test(
-				b"FromWorker".to_vec(),
-			)))
+			.send(Message::new(
+				MessagePayload::Test(b"FromWorker".to_vec()),
+				self.id,
+			))
 			.unwrap();
 
-		// expect this from master.
-		let data = self.from_master.recv().unwrap().payload;
-		assert!(matches!(data, MessagePayload::Test(x) if x == b"FromMaster".to_vec()));
-
-		// send this to next worker.
-		self.to_others.iter().for_each(|(_, sender)| {
-			sender
-				.send(Message::new_from_thread(
-					crate::types::MessagePayload::Test(vec![self.id as u8]),
-				))
-				.unwrap();
-		});
-
+		// expect one message from master, and one from each worker, including ourself. Only exit
+		// once we have received from all workers, otherwise dropping our inbox might make their
+		// sends fail.
+		let mut received_from_master = false;
 		let mut num_received = 0;
 		let num_workers = self.to_others.len();
-		while num_received != (num_workers - 1) {
-			let message = self.from_others.recv().unwrap();
-			let Message { payload, from } = message;
-			assert!(matches!(payload, MessagePayload::Test(x) if x == vec![from as u8]));
-			num_received += 1;
+		while !received_from_master || num_received != num_workers {
+			let Message { payload, from } = self.inbox.recv().unwrap();
+			if from == self.master_id {
+				assert!(matches!(payload, MessagePayload::Test(x) if x == b"FromMaster".to_vec()));
+				received_from_master = true;
+
+				// send this to all workers.
+				self.to_others.iter().for_each(|(_, sender)| {
+					sender
+						.send(Message::new(
+							MessagePayload::Test(vec![self.id as u8]),
+							self.id,
+						))
+						.unwrap();
+				});
+			} else {
+				assert!(matches!(payload, MessagePayload::Test(x) if x == vec![from as u8]));
+				num_received += 1;
+			}
 		}
 	}
 }
@@ -373,21 +677,20 @@ mod worker_test_validation {
 	use super::*;
 	use crate::types::{transaction_generator, MessagePayload};
 	use primitives::*;
-	use runtime::balances::*;
+	use runtime::{balances::*, SequentialRuntime};
 	use std::{matches, sync::mpsc::channel};
 
 	const MASTER_ID: ThreadId = 99;
+	const WORKER_ID: ThreadId = 1;
 
 	fn test_worker(
 		initial_messages: Vec<Message>,
 	) -> (Worker, Receiver<Message>, Receiver<Message>) {
 		let state = State::new().as_arc();
-		let (from_master_tx, from_master_rx) = channel();
+		let (from_master_tx, inbox_rx) = channel();
 		let (to_master_tx, to_master_rx) = channel();
-		let (_, others_rx) = channel();
 		let (_, other_worker_rx) = channel();
-		let worker =
-			Worker::new_from_thread(MASTER_ID, state, to_master_tx, from_master_rx, others_rx);
+		let worker = Worker::new(WORKER_ID, MASTER_ID, state, to_master_tx, inbox_rx);
 
 		initial_messages.into_iter().for_each(|m| {
 			from_master_tx.send(m).unwrap();
@@ -413,50 +716,35 @@ mod worker_test_validation {
 			}))
 			.collect();
 
+		// the transactions are tagged with a bucket index, which need not be the id of the worker.
 		let (worker, _, master_rx) = test_worker(transactions);
-		assert_eq!(
-			worker.id, 1,
-			"The assumption of this test is that the worker's id will be 1."
-		);
-		transaction_generator::endow_account(
-			testing::alice().public(),
-			&worker.sequential_runtime,
-			100,
-		);
-		worker.run_validate();
+		let sequential_runtime = SequentialRuntime::new(Arc::clone(&worker.state), 0);
+		transaction_generator::endow_account(testing::alice().public(), &sequential_runtime, 100);
+		worker.run_validate().unwrap();
 
 		assert_eq!(
-			<BalanceOf<SequentialRuntime>>::read(
-				&worker.sequential_runtime,
-				testing::alice().public()
-			)
-			.unwrap()
-			.free(),
+			<BalanceOf<SequentialRuntime>>::read(&sequential_runtime, testing::alice().public())
+				.unwrap()
+				.free(),
 			80,
 		);
 		assert_eq!(
-			<BalanceOf<SequentialRuntime>>::read(
-				&worker.sequential_runtime,
-				testing::bob().public()
-			)
-			.unwrap()
-			.free(),
+			<BalanceOf<SequentialRuntime>>::read(&sequential_runtime, testing::bob().public())
+				.unwrap()
+				.free(),
 			10,
 		);
 		assert_eq!(
-			<BalanceOf<SequentialRuntime>>::read(
-				&worker.sequential_runtime,
-				testing::dave().public()
-			)
-			.unwrap()
-			.free(),
+			<BalanceOf<SequentialRuntime>>::read(&sequential_runtime, testing::dave().public())
+				.unwrap()
+				.free(),
 			10,
 		);
 
 		// We will send this back to maser.
 		assert!(matches!(
 			master_rx.recv().unwrap().payload,
-			MessagePayload::ValidationReport
+			MessagePayload::ValidationReport(None)
 		))
 	}
 }
@@ -464,23 +752,35 @@ mod worker_test_validation {
 #[cfg(test)]
 mod worker_test_authoring {
 	use super::*;
+	use crate::types::transaction_generator;
 	use primitives::*;
-	use runtime::balances::*;
+	use runtime::{balances::*, system::NonceOf};
 	use std::{matches, sync::mpsc::channel};
 
 	const OTHER_WORKER: ThreadId = 69;
+	// a worker that we have no channel to, only used in hops.
+	const FAR_WORKER: ThreadId = 42;
 	const MASTER_ID: ThreadId = 99;
+	const WORKER_ID: ThreadId = 1;
 
 	fn test_worker() -> (Worker, Receiver<Message>, Receiver<Message>) {
+		let (worker, _, other_worker_rx, to_master_rx) = test_worker_with_inbox();
+		(worker, other_worker_rx, to_master_rx)
+	}
+
+	fn test_worker_with_inbox() -> (
+		Worker,
+		Sender<Message>,
+		Receiver<Message>,
+		Receiver<Message>,
+	) {
 		let state = State::new().as_arc();
-		let (_, from_master_rx) = channel();
+		let (inbox_tx, inbox_rx) = channel();
 		let (to_master_tx, to_master_rx) = channel();
-		let (_, others_rx) = channel();
 		let (other_worker_tx, other_worker_rx) = channel();
-		let mut worker =
-			Worker::new_from_thread(MASTER_ID, state, to_master_tx, from_master_rx, others_rx);
+		let mut worker = Worker::new(WORKER_ID, MASTER_ID, state, to_master_tx, inbox_rx);
 		worker.to_others.insert(OTHER_WORKER, other_worker_tx);
-		(worker, other_worker_rx, to_master_rx)
+		(worker, inbox_tx, other_worker_rx, to_master_rx)
 	}
 
 	fn test_tx(origin: Pair, id: TransactionId) -> (Transaction, AccountId) {
@@ -506,7 +806,9 @@ mod worker_test_authoring {
 		// give alice some funds.
 		BalanceOf::write(&sequential_runtime, alice, 999.into()).unwrap();
 
-		// now alice has some funds.
+		// now alice has some funds. The failed transaction was still included, hence the next
+		// nonce.
+		let tx = transaction_generator::sign(tx.id, tx.function, 1, &testing::alice());
 		assert!(matches!(
 			worker.execute_or_forward(tx),
 			ExecutionOutcome::Executed(RuntimeDispatchSuccess::Ok)
@@ -545,8 +847,8 @@ mod worker_test_authoring {
 		let alice = testing::alice();
 		let (tx, alice) = test_tx(alice, 1);
 
-		// manually taint the storage item of bob to some other thread.
-		let alice_key = <BalanceOf<ConcurrentRuntime>>::key_for(alice);
+		// manually taint the nonce of alice, which is accessed first, to some other thread.
+		let alice_key = <NonceOf<ConcurrentRuntime>>::key_for(alice);
 		worker
 			.state
 			.unsafe_insert(&alice_key, state::StateValue::new_taint(OTHER_WORKER));
@@ -567,9 +869,10 @@ mod worker_test_authoring {
 		let alice = testing::alice();
 		let (mut tx, alice) = test_tx(alice, 1);
 		tx.exec_status = ExecutionStatus::Forwarded;
+		tx.hops = vec![FAR_WORKER];
 
-		// manually taint the storage item of alice to some other thread.
-		let alice_key = <BalanceOf<ConcurrentRuntime>>::key_for(alice);
+		// manually taint the nonce of alice, which is accessed first, to some other thread.
+		let alice_key = <NonceOf<ConcurrentRuntime>>::key_for(alice);
 		worker
 			.state
 			.unsafe_insert(&alice_key, state::StateValue::new_taint(OTHER_WORKER));
@@ -581,7 +884,151 @@ mod worker_test_authoring {
 
 		let incoming = master_rx.recv().unwrap();
 		assert_eq!(incoming.from, worker.id);
-		assert!(matches!(incoming.payload, MessagePayload::WorkerOrphan(_)))
+		assert!(matches!(
+			incoming.payload,
+			MessagePayload::WorkerOrphan(_, hops) if hops == vec![FAR_WORKER, WORKER_ID]
+		))
+	}
+
+	#[test]
+	fn will_forward_again_within_budget() {
+		let (mut worker, other_rx, _) = test_worker();
+		worker.forward_budget = 2;
+		let alice = testing::alice();
+		let (mut tx, alice) = test_tx(alice, 1);
+		tx.exec_status = ExecutionStatus::Forwarded;
+		tx.hops = vec![FAR_WORKER];
+
+		// manually taint the nonce of alice, which is accessed first, to some other thread.
+		let alice_key = <NonceOf<ConcurrentRuntime>>::key_for(alice);
+		worker
+			.state
+			.unsafe_insert(&alice_key, state::StateValue::new_taint(OTHER_WORKER));
+
+		assert!(matches!(
+			worker.execute_or_forward(tx),
+			ExecutionOutcome::Forwarded(x) if x == OTHER_WORKER
+		));
+
+		let incoming = other_rx.recv().unwrap();
+		assert!(matches!(
+			incoming.payload,
+			MessagePayload::Transaction(itx) if itx.hops == vec![FAR_WORKER, WORKER_ID]
+		))
+	}
+
+	#[test]
+	fn will_not_forward_back_to_previous_hop() {
+		let (mut worker, _, master_rx) = test_worker();
+		worker.forward_budget = 3;
+		let alice = testing::alice();
+		let (mut tx, alice) = test_tx(alice, 1);
+		tx.exec_status = ExecutionStatus::Forwarded;
+		tx.hops = vec![OTHER_WORKER];
+
+		// alice is tainted by the worker that forwarded it to us in the first place.
+		let alice_key = <NonceOf<ConcurrentRuntime>>::key_for(alice);
+		worker
+			.state
+			.unsafe_insert(&alice_key, state::StateValue::new_taint(OTHER_WORKER));
+
+		assert!(matches!(
+			worker.execute_or_forward(tx),
+			ExecutionOutcome::ForwardedToMaster
+		));
+		assert!(matches!(
+			master_rx.recv().unwrap().payload,
+			MessagePayload::WorkerOrphan(_, hops) if hops == vec![OTHER_WORKER, WORKER_ID]
+		))
+	}
+
+	#[test]
+	fn reports_unexpected_messages_to_master() {
+		let (worker, inbox_tx, _, master_rx) = test_worker_with_inbox();
+		let handle = std::thread::spawn(move || worker.run());
+
+		// only master can signal the end of the task.
+		inbox_tx
+			.send(Message::new(
+				MessagePayload::Task(TaskType::Authoring(None)),
+				MASTER_ID,
+			))
+			.unwrap();
+		inbox_tx
+			.send(Message::new(MessagePayload::TaskDone, OTHER_WORKER))
+			.unwrap();
+		assert!(matches!(
+			master_rx.recv().unwrap(),
+			Message { payload: MessagePayload::WorkerError, from } if from == WORKER_ID
+		));
+
+		// the task is abandoned, and the worker still obeys master.
+		inbox_tx
+			.send(Message::new(
+				MessagePayload::Task(TaskType::Sync),
+				MASTER_ID,
+			))
+			.unwrap();
+		assert!(matches!(
+			master_rx.recv().unwrap().payload,
+			MessagePayload::Synced
+		));
+		inbox_tx
+			.send(Message::new(MessagePayload::Terminate, MASTER_ID))
+			.unwrap();
+		handle.join().unwrap();
+	}
+
+	#[test]
+	fn hands_over_untainted_transactions_to_idle_workers() {
+		let (worker, inbox_tx, other_rx, master_rx) = test_worker_with_inbox();
+
+		// alice is already tainted by us.
+		let alice_key = <BalanceOf<ConcurrentRuntime>>::key_for(testing::alice().public());
+		worker
+			.state
+			.unsafe_insert(&alice_key, state::StateValue::new_taint(WORKER_ID));
+
+		let first =
+			transaction_generator::build_transfer(1, testing::eve(), testing::dave().public());
+		let untainted =
+			transaction_generator::build_transfer(2, testing::random(), testing::random().public());
+		let tainted =
+			transaction_generator::build_transfer(3, testing::alice(), testing::bob().public());
+
+		inbox_tx
+			.send(Message::new(MessagePayload::Idle, OTHER_WORKER))
+			.unwrap();
+		for tx in [first, untainted, tainted] {
+			inbox_tx
+				.send(Message::new(MessagePayload::Transaction(tx), MASTER_ID))
+				.unwrap();
+		}
+		inbox_tx
+			.send(Message::new(
+				MessagePayload::TransactionDistributionDone,
+				MASTER_ID,
+			))
+			.unwrap();
+
+		assert!(worker.deplete_master_queue(None).unwrap().is_empty());
+
+		// the last one is tainted by us, the first one is being executed, so the middle one is
+		// handed over. Then, we announce that we are idle.
+		assert!(matches!(
+			other_rx.recv().unwrap().payload,
+			MessagePayload::Transaction(tx) if tx.id == 2 && tx.hops == vec![WORKER_ID]
+		));
+		assert!(matches!(
+			other_rx.recv().unwrap().payload,
+			MessagePayload::Idle
+		));
+
+		// the handed over one is reported as forwarded. None of the executed ones had any funds.
+		assert!(matches!(
+			master_rx.recv().unwrap().payload,
+			MessagePayload::AuthoringReport(2, 1, 2)
+		));
 	}
 
 	#[test]
@@ -601,6 +1048,6 @@ mod worker_test_authoring {
 
 		// master should have received a notification now.
 		let msg = master_rx.recv().unwrap();
-		assert!(matches!(msg.payload, MessagePayload::WorkerExecuted(_)));
+		assert!(matches!(msg.payload, MessagePayload::WorkerExecuted(..)));
 	}
 }
@@ -0,0 +1,96 @@
+//! Utilities to store a block, along with the state on top of which it must be applied, on disk and
+//! read it back.
+//!
+//! This is mainly useful for debugging: a problematic authored block can be exported once, and
+//! later replayed through `validate_block` on another machine.
+
+use crate::types::Block;
+use parity_scale_codec::{Decode, Encode};
+use primitives::{Key, Value};
+use runtime::StateMap;
+use std::{fs, io, path::Path};
+
+/// Convert a state map into a list of key-value pairs, sorted by key.
+///
+/// Taints are not kept, they are meaningless outside of the execution that created them.
+fn state_to_pairs(state: &StateMap) -> Vec<(Key, Value)> {
+	let mut pairs = state
+		.iter()
+		.map(|(k, v)| (k.clone(), v.data()))
+		.collect::<Vec<_>>();
+	pairs.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+	pairs
+}
+
+/// Convert a list of key-value pairs back into a state map with no taints.
+fn pairs_to_state(pairs: Vec<(Key, Value)>) -> StateMap {
+	pairs
+		.into_iter()
+		.map(|(k, v)| (k, state::StateValue::new_data(v)))
+		.collect()
+}
+
+/// Write the given `block` and its `pre_state` to the file at `path`.
+///
+/// The file is overwritten if it already exists.
+pub fn export_block<P: AsRef<Path>>(
+	path: P,
+	block: &Block,
+	pre_state: &StateMap,
+) -> io::Result<()> {
+	let encoded = (block, state_to_pairs(pre_state)).encode();
+	fs::write(path, encoded)
+}
+
+/// Read a block and its pre-state back from the file at `path`.
+///
+/// This is the inverse of [`export_block`].
+pub fn import_block<P: AsRef<Path>>(path: P) -> io::Result<(Block, StateMap)> {
+	let encoded = fs::read(path)?;
+	let (block, pairs) = <(Block, Vec<(Key, Value)>)>::decode(&mut &*encoded)
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.what()))?;
+	Ok((block, pairs_to_state(pairs)))
+}
+
+#[cfg(test)]
+mod io_tests {
+	use super::*;
+	use crate::types::{transaction_generator, ExecutionTag};
+	use primitives::testing;
+	use runtime::InitialStateGenerate;
+	use state::StateEq;
+
+	#[test]
+	fn export_import_works() {
+		let mut transactions = transaction_generator::simple_alice_bob_dave();
+		transactions[0].set_done(1);
+		transactions[1].set_orphan();
+		let block = Block::from(transactions);
+
+		let pre_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				transaction_generator::endow_account(testing::alice().public(), rt, 100)
+			})
+			.build();
+
+		let path = std::env::temp_dir().join("export_import_works.block");
+		export_block(&path, &block, &pre_state).unwrap();
+		let (imported_block, imported_state) = import_block(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(imported_block, block);
+		assert_eq!(imported_block.transactions[0].tag, ExecutionTag::Done(1));
+		assert_eq!(imported_block.transactions[1].tag, ExecutionTag::Orphan);
+		assert!(imported_state.state_eq(pre_state));
+	}
+
+	#[test]
+	fn import_garbage_fails() {
+		let path = std::env::temp_dir().join("import_garbage_fails.block");
+		std::fs::write(&path, vec![1, 2, 3]).unwrap();
+		let outcome = import_block(&path);
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(outcome.unwrap_err().kind(), io::ErrorKind::InvalidData);
+	}
+}
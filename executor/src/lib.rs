@@ -1,37 +1,60 @@
-#![feature(thread_id_value)]
-#![feature(debug_non_exhaustive)]
-
+pub mod affinity;
 pub mod concurrent;
+pub mod io;
+pub mod optimistic;
 pub mod pool;
 pub mod sequential;
 pub mod types;
 
 use logging::log;
-use pool::VecPool;
+use pool::IndexedPool;
 use runtime::StateMap;
 use state::StateEq;
 use std::time::Duration;
-use types::{Block, Transaction};
+use types::{AuthoringStats, Block, BlockLimits, ExecutorError, Transaction};
 
 const LOG_TARGET: &'static str = "exec";
 
 /// The final state type of the application.
 pub type State = runtime::RuntimeState;
 /// The final pool type of the application.
-pub type Pool = VecPool<Transaction>;
+pub type Pool = IndexedPool<Transaction>;
 
 /// Something that can execute transaction, blocks etc.
 pub trait Executor {
 	/// Execute the given block.
 	///
-	/// The output is the final state after the execution.
+	/// The output is the final state after the execution, the block, and the statistics of the
+	/// execution. Returns an error if the executor failed to carry out the execution, in which
+	/// case it should not be used anymore.
 	fn author_block(
 		&mut self,
 		initial_transactions: Vec<Transaction>,
-	) -> (StateMap, Block, Duration);
+	) -> Result<(StateMap, Block, AuthoringStats), ExecutorError>;
+
+	/// Build a block out of the given transactions, within `limits`.
+	///
+	/// Only the longest prefix of the transactions that fits in the size and weight limits is
+	/// considered. Besides the output of [`Self::author_block`], the transactions that did not make
+	/// it into the block are returned. Neither the block nor the state contain any of them, hence
+	/// they can go back to the pool.
+	///
+	/// The default implementation ignores the deadline.
+	fn build_block(
+		&mut self,
+		transactions: Vec<Transaction>,
+		limits: BlockLimits,
+	) -> Result<(StateMap, Block, AuthoringStats, Vec<Transaction>), ExecutorError> {
+		let (transactions, rest) = limits.split(transactions);
+		self.author_block(transactions)
+			.map(|(state, block, stats)| (state, block, stats, rest))
+	}
 
 	/// Re-validate a block as it will be done by the validator.
-	fn validate_block(&mut self, block: Block) -> (StateMap, Duration);
+	///
+	/// Returns an error if the block is rejected, either because it is malformed or because the
+	/// executor failed to carry out the execution.
+	fn validate_block(&mut self, block: Block) -> Result<(StateMap, Duration), ExecutorError>;
 
 	/// Clean the internal state of the executor, whatever it may be.
 	fn clean(&mut self);
@@ -57,7 +80,14 @@ pub trait Executor {
 			);
 			self.apply_state(state)
 		}
-		let (authoring_state, block, authoring_time) = self.author_block(initial_transactions);
+		let (authoring_state, block, authoring_time) = match self.author_block(initial_transactions)
+		{
+			Ok((state, block, stats)) => (state, block, stats.total),
+			Err(why) => {
+				log!(error, "Failed to author block: {:?}", why);
+				return (false, Default::default(), Default::default());
+			}
+		};
 		log!(warn, "⏳ authoring took {:?}", authoring_time);
 		self.clean();
 
@@ -70,13 +100,21 @@ pub trait Executor {
 			);
 			self.apply_state(state)
 		}
-		let (validation_state, validation_time) = self.validate_block(block);
+		let outcome = self.validate_block(block);
 		self.clean();
-		log!(warn, "⏳ validation took {:?}", validation_time);
-		(
-			validation_state.state_eq(authoring_state),
-			authoring_time,
-			validation_time,
-		)
+		match outcome {
+			Ok((validation_state, validation_time)) => {
+				log!(warn, "⏳ validation took {:?}", validation_time);
+				(
+					validation_state.state_eq(authoring_state),
+					authoring_time,
+					validation_time,
+				)
+			}
+			Err(why) => {
+				log!(error, "Block was rejected in validation: {:?}", why);
+				(false, authoring_time, Default::default())
+			}
+		}
 	}
 }
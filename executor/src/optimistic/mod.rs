@@ -0,0 +1,500 @@
+pub mod scheduler;
+
+use crate::{types::*, Executor, State, StateMap};
+use logging::log;
+use primitives::*;
+use runtime::*;
+use scheduler::*;
+use state::multi_version::{MultiVersionState, Version};
+use std::{
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, Mutex,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+
+const LOG_TARGET: &'static str = "optimistic-exec";
+
+/// An optimistic executor.
+///
+/// All transactions are executed speculatively and in parallel on top of a multi-versioned state,
+/// without any prior knowledge about their access. Each transaction observes the writes of the
+/// transactions before it in the block. Once executed, the read-set of each transaction is
+/// validated, and the transaction is re-executed if it observed a stale version. Hence, the final
+/// state is the same as executing the block sequentially, in the same order, and no information
+/// other than the order needs to be included in the block.
+///
+/// Writes are kept in the multi-versioned state, and only the final value of each key is written
+/// to the backend state once the block is done.
+///
+/// Threads are spawned per task, and there is no notion of master or worker.
+pub struct OptimisticExecutor {
+	/// The state.
+	pub state: Arc<State>,
+	/// A sequential runtime on top of `state`, useful to build the initial state.
+	pub runtime: SequentialRuntime,
+	/// Number of threads used to execute a block.
+	threads: usize,
+}
+
+/// The shared data of a block being executed.
+struct BlockExecution<'a> {
+	/// The transactions, in the order of the block.
+	transactions: &'a [Transaction],
+	/// The scheduler.
+	scheduler: Scheduler,
+	/// The multi-versioned state of the block.
+	versions: Arc<MultiVersionState<Key, Value>>,
+	/// The outcome of the last incarnation of each transaction.
+	outcomes: Vec<Mutex<Option<RuntimeDispatchResult>>>,
+	/// Number of executions, including re-executions.
+	executions: AtomicUsize,
+}
+
+/// Halts the scheduler if the thread owning it panics, so that other threads do not wait forever
+/// for a task that will never finish.
+struct HaltOnPanic<'a>(&'a Scheduler);
+
+impl Drop for HaltOnPanic<'_> {
+	fn drop(&mut self) {
+		if thread::panicking() {
+			self.0.halt();
+		}
+	}
+}
+
+impl<'a> BlockExecution<'a> {
+	fn new(transactions: &'a [Transaction]) -> Self {
+		let len = transactions.len();
+		Self {
+			transactions,
+			scheduler: Scheduler::new(len),
+			versions: Arc::new(MultiVersionState::new(len)),
+			outcomes: (0..len).map(|_| Default::default()).collect(),
+			executions: AtomicUsize::new(0),
+		}
+	}
+
+	/// Pull tasks from the scheduler, until the block is done.
+	fn run(&self, runtime: OptimisticRuntime) {
+		let _guard = HaltOnPanic(&self.scheduler);
+		let mut task = None;
+		while !self.scheduler.done() {
+			task = match task {
+				Some(Task::Execute(version)) => self.execute(&runtime, version),
+				Some(Task::Validate(version)) => self.validate(version),
+				None => {
+					let next = self.scheduler.next_task();
+					if next.is_none() {
+						thread::yield_now();
+					}
+					next
+				}
+			};
+		}
+	}
+
+	fn execute(&self, runtime: &OptimisticRuntime, version: Version) -> Option<Task> {
+		let (index, _) = version;
+		let tx = &self.transactions[index];
+		loop {
+			self.executions.fetch_add(1, Ordering::Relaxed);
+			match runtime.dispatch_transaction(tx.function.clone(), tx.signature.0, tx.nonce, index)
+			{
+				SpeculativeDispatchOutcome::Blocked(blocking) => {
+					if self.scheduler.add_dependency(index, blocking) {
+						break None;
+					}
+					// the blocking transaction is already re-executed, try again.
+				}
+				SpeculativeDispatchOutcome::Executed {
+					result,
+					reads,
+					writes,
+				} => {
+					*self.outcomes[index].lock().unwrap() = Some(result);
+					let wrote_new_key = self.versions.record(version, reads, writes);
+					break self.scheduler.finish_execution(version, wrote_new_key);
+				}
+			}
+		}
+	}
+
+	fn validate(&self, version: Version) -> Option<Task> {
+		let (index, _) = version;
+		let aborted =
+			!self.versions.validate_read_set(index) && self.scheduler.try_validation_abort(version);
+		if aborted {
+			self.versions.convert_writes_to_estimates(index);
+		}
+		self.scheduler.finish_validation(index, aborted)
+	}
+}
+
+impl OptimisticExecutor {
+	/// Create a new optimistic executor that executes each block with `threads` threads.
+	pub fn new(threads: usize, initial_state: Option<State>) -> Self {
+		assert!(threads > 0, "At least one thread is needed.");
+		let state = initial_state.unwrap_or_default().as_arc();
+		let runtime = SequentialRuntime::new(Arc::clone(&state), threads as ThreadId);
+		Self {
+			state,
+			runtime,
+			threads,
+		}
+	}
+
+	/// Execute all of the given transactions, in order, and apply the final outcome to the state.
+	///
+	/// Returns the outcome of each transaction. The only possible error is a bad nonce, in which
+	/// case the transaction wrote nothing.
+	fn execute(
+		&self,
+		transactions: &[Transaction],
+	) -> Result<Vec<RuntimeDispatchResult>, ExecutorError> {
+		let block = BlockExecution::new(transactions);
+
+		thread::scope(|s| {
+			let handles = (0..self.threads)
+				.map(|i| {
+					let runtime = OptimisticRuntime::new(
+						Arc::clone(&self.state),
+						Arc::clone(&block.versions),
+						i as ThreadId,
+					);
+					let block = &block;
+					thread::Builder::new()
+						.name(format!("Optimistic#{}", i))
+						.spawn_scoped(s, move || block.run(runtime))
+						.expect("Failed to spawn a new thread.")
+				})
+				.collect::<Vec<_>>();
+
+			handles
+				.into_iter()
+				.enumerate()
+				.map(|(i, handle)| {
+					handle
+						.join()
+						.map_err(|_| ExecutorError::WorkerPanicked(i as ThreadId))
+				})
+				.collect::<Vec<_>>()
+				.into_iter()
+				.collect::<Result<(), _>>()
+		})?;
+
+		let outcomes = block
+			.outcomes
+			.into_iter()
+			.map(|o| {
+				o.into_inner()
+					.unwrap()
+					.expect("All transactions are executed once the block is done; qed.")
+			})
+			.collect::<Vec<_>>();
+		let successes = outcomes
+			.iter()
+			.filter_map(|outcome| outcome.ok())
+			.collect::<Vec<_>>();
+		log!(
+			info,
+			"Applied {} transactions with {} executions, {} were ok {} were error, {} had a bad nonce.",
+			outcomes.len(),
+			block.executions.load(Ordering::Relaxed),
+			successes.ok_count(),
+			successes.logic_error_count(),
+			outcomes.len() - successes.len(),
+		);
+
+		// apply the final writes to the state, also in parallel.
+		let writes = block.versions.snapshot();
+		let chunk_size = (writes.len() / self.threads).max(1);
+		thread::scope(|s| {
+			for chunk in writes.chunks(chunk_size) {
+				let state = &self.state;
+				s.spawn(move || {
+					chunk
+						.iter()
+						.for_each(|(k, v)| state.unsafe_insert_genesis_value(k, v.clone()))
+				});
+			}
+		});
+
+		Ok(outcomes)
+	}
+}
+
+impl Executor for OptimisticExecutor {
+	fn author_block(
+		&mut self,
+		initial_transactions: Vec<Transaction>,
+	) -> Result<(StateMap, Block, AuthoringStats), ExecutorError> {
+		self.build_block(initial_transactions, Default::default())
+			.map(|(state, block, stats, _)| (state, block, stats))
+	}
+
+	/// The deadline is ignored, but the transactions with a bad nonce are left out of the block.
+	/// They wrote nothing, so the rest of the block is not affected.
+	fn build_block(
+		&mut self,
+		transactions: Vec<Transaction>,
+		limits: BlockLimits,
+	) -> Result<(StateMap, Block, AuthoringStats, Vec<Transaction>), ExecutorError> {
+		log!(
+			info,
+			"📕 Authoring block with {} transactions.",
+			transactions.len(),
+		);
+		let start = Instant::now();
+		let (transactions, rest) = limits.split(transactions);
+		let outcomes = self.execute(&transactions)?;
+		let mut logic_errors = 0;
+		let mut included = Vec::with_capacity(transactions.len());
+		let mut unexecuted = vec![];
+		for (tx, outcome) in transactions.into_iter().zip(outcomes) {
+			match outcome {
+				Ok(outcome) => {
+					if let RuntimeDispatchSuccess::LogicError(_) = outcome {
+						logic_errors += 1;
+					}
+					included.push(tx);
+				}
+				Err(_) => unexecuted.push(tx),
+			}
+		}
+		let stats = AuthoringStats {
+			logic_errors,
+			unexecuted: unexecuted.len(),
+			total: start.elapsed(),
+			..Default::default()
+		};
+		unexecuted.extend(rest);
+		Ok((self.state.dump(), included.into(), stats, unexecuted))
+	}
+
+	fn validate_block(&mut self, block: Block) -> Result<(StateMap, Duration), ExecutorError> {
+		log!(
+			info,
+			"✅ Validating block with {} transactions. ",
+			block.transactions.len(),
+		);
+		let start = Instant::now();
+		crate::pool::verify_block(&block.transactions)?;
+		let outcomes = self.execute(&block.transactions)?;
+		if let Some((tx, _)) = block
+			.transactions
+			.iter()
+			.zip(outcomes)
+			.find(|(_, outcome)| outcome.is_err())
+		{
+			log!(warn, "Rejecting block due to bad nonce of {:?}.", tx);
+			return Err(InvalidBlock::BadNonce(tx.id).into());
+		}
+		Ok((self.state.dump(), start.elapsed()))
+	}
+
+	fn clean(&mut self) {
+		self.state.unsafe_clean();
+	}
+
+	fn apply_state(&mut self, state: StateMap) {
+		for (k, v) in state.into_iter() {
+			self.state.unsafe_insert(&k, v);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sequential::SequentialExecutor;
+	use crate::types::transaction_generator;
+	use parity_scale_codec::Encode;
+	use primitives::testing::*;
+	use runtime::balances::*;
+	use state::StateEq;
+
+	fn balance_of(state: &StateMap, who: AccountId) -> Value {
+		state
+			.get(&<BalanceOf<SequentialRuntime>>::key_for(who))
+			.unwrap()
+			.data()
+	}
+
+	#[test]
+	fn empty_block_works() {
+		let mut executor = OptimisticExecutor::new(2, None);
+		assert!(executor.author_and_validate(vec![], None).0);
+	}
+
+	#[test]
+	fn can_author_dependent_transactions() {
+		let mut executor = OptimisticExecutor::new(4, None);
+		transaction_generator::endow_account(alice().public(), &executor.runtime, 100);
+
+		// all of them conflict on alice.
+		let transactions = (0..8)
+			.map(|i| {
+				transaction_generator::build_transfer_with_nonce(
+					i,
+					alice(),
+					random().public(),
+					i as Nonce,
+				)
+			})
+			.chain(std::iter::once(
+				transaction_generator::build_transfer_with_nonce(8, alice(), bob().public(), 8),
+			))
+			.collect::<Vec<_>>();
+
+		let (state, block, _) = executor.author_block(transactions).unwrap();
+		assert_eq!(block.transactions.len(), 9);
+		assert_eq!(
+			balance_of(&state, alice().public()),
+			AccountBalance::from(10).encode().into()
+		);
+		assert_eq!(
+			balance_of(&state, bob().public()),
+			AccountBalance::from(10).encode().into()
+		);
+	}
+
+	#[test]
+	fn build_block_respects_size_limit() {
+		let mut executor = OptimisticExecutor::new(2, None);
+		transaction_generator::endow_account(alice().public(), &executor.runtime, 100);
+		let transactions = (0..4)
+			.map(|i| {
+				transaction_generator::build_transfer_with_nonce(
+					i,
+					alice(),
+					bob().public(),
+					i as Nonce,
+				)
+			})
+			.collect::<Vec<_>>();
+
+		let limits = BlockLimits {
+			max_transactions: Some(3),
+			..Default::default()
+		};
+		let (state, block, _, unexecuted) =
+			executor.build_block(transactions.clone(), limits).unwrap();
+		assert_eq!(block.transactions, transactions[..3].to_vec());
+		assert_eq!(unexecuted, transactions[3..].to_vec());
+		assert_eq!(
+			balance_of(&state, bob().public()),
+			AccountBalance::from(30).encode().into()
+		);
+	}
+
+	#[test]
+	fn transactions_with_bad_nonce_are_left_out() {
+		let mut executor = OptimisticExecutor::new(4, None);
+		transaction_generator::endow_account(alice().public(), &executor.runtime, 100);
+
+		// the second one reuses the nonce of the first one.
+		let transactions = vec![
+			transaction_generator::build_transfer_with_nonce(0, alice(), bob().public(), 0),
+			transaction_generator::build_transfer_with_nonce(1, alice(), dave().public(), 0),
+			transaction_generator::build_transfer_with_nonce(2, alice(), dave().public(), 1),
+		];
+
+		let (state, block, stats, unexecuted) = executor
+			.build_block(transactions.clone(), Default::default())
+			.unwrap();
+		assert_eq!(
+			block.transactions,
+			vec![transactions[0].clone(), transactions[2].clone()]
+		);
+		assert_eq!(stats.unexecuted, 1);
+		assert_eq!(unexecuted, vec![transactions[1].clone()]);
+		assert_eq!(
+			balance_of(&state, dave().public()),
+			AccountBalance::from(10).encode().into()
+		);
+
+		// a block that includes it is rejected.
+		let mut validator = OptimisticExecutor::new(4, None);
+		transaction_generator::endow_account(alice().public(), &validator.runtime, 100);
+		assert_eq!(
+			validator.validate_block(transactions.into()),
+			Err(InvalidBlock::BadNonce(1).into())
+		);
+	}
+
+	#[test]
+	fn tampered_transactions_are_rejected() {
+		let mut executor = OptimisticExecutor::new(4, None);
+		transaction_generator::endow_account(alice().public(), &executor.runtime, 100);
+		let transactions = transaction_generator::simple_alice_bob_dave();
+
+		// the call is signed, so redirecting the transfer invalidates the transaction.
+		let mut block: Block = transactions.clone().into();
+		block.transactions[0].function = transactions[1].function.clone();
+		assert_eq!(
+			executor.validate_block(block).map(|_| ()),
+			Err(InvalidBlock::BadSignature(transactions[0].id).into())
+		);
+	}
+
+	#[test]
+	fn logic_errors_depend_on_order() {
+		let mut executor = OptimisticExecutor::new(4, None);
+		transaction_generator::endow_account(alice().public(), &executor.runtime, 10);
+
+		// bob can only pay dave once alice has paid bob.
+		let transactions = vec![
+			transaction_generator::build_transfer(1, bob(), dave().public()),
+			transaction_generator::build_transfer(2, alice(), bob().public()),
+			transaction_generator::build_transfer_with_nonce(3, bob(), eve().public(), 1),
+		];
+
+		let (state, _, _) = executor.author_block(transactions).unwrap();
+		assert_eq!(
+			balance_of(&state, alice().public()),
+			AccountBalance::from(0).encode().into()
+		);
+		assert_eq!(
+			balance_of(&state, bob().public()),
+			AccountBalance::from(0).encode().into()
+		);
+		assert_eq!(
+			balance_of(&state, eve().public()),
+			AccountBalance::from(10).encode().into()
+		);
+		assert!(!state.contains_key(&<BalanceOf<SequentialRuntime>>::key_for(dave().public())));
+	}
+
+	#[test]
+	fn same_state_as_sequential() {
+		let (transactions, accounts) = transaction_generator::bank(20, 100, 10);
+		let initial_state = InitialStateGenerate::new()
+			.with_runtime(|rt| {
+				accounts
+					.iter()
+					.for_each(|acc| transaction_generator::endow_account(*acc, rt, 50))
+			})
+			.build();
+
+		let mut sequential = SequentialExecutor::new();
+		sequential.apply_state(initial_state.clone());
+		let (sequential_state, _, _) = sequential.author_block(transactions.clone()).unwrap();
+
+		let mut executor = OptimisticExecutor::new(4, None);
+		executor.apply_state(initial_state.clone());
+		let (optimistic_state, block, _) = executor.author_block(transactions).unwrap();
+		assert!(optimistic_state.state_eq(sequential_state));
+
+		// the nonces bumped by the first block are not part of the initial state.
+		executor.clean();
+
+		assert!(
+			executor
+				.author_and_validate(block.transactions, Some(initial_state))
+				.0
+		);
+	}
+}
@@ -0,0 +1,358 @@
+//! The collaborative scheduler of the optimistic executor.
+//!
+//! This follows the design of Block-STM. All threads pull tasks from two shared indices: the next
+//! transaction to execute, and the next transaction to validate. Both only move forward, unless an
+//! execution or validation forces them back to re-execute or re-validate some transactions.
+
+use state::multi_version::{Incarnation, TxIndex, Version};
+use std::{
+	cmp::min,
+	mem,
+	sync::{
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+		Mutex,
+	},
+};
+
+/// A task that a thread must carry out.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Task {
+	/// Execute the given version of a transaction.
+	Execute(Version),
+	/// Validate the read-set of the given version of a transaction.
+	Validate(Version),
+}
+
+/// Status of a transaction in the scheduler.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Status {
+	/// The given incarnation is ready to be executed.
+	ReadyToExecute(Incarnation),
+	/// The given incarnation is being executed.
+	Executing(Incarnation),
+	/// The given incarnation is executed, and might be validated.
+	Executed(Incarnation),
+	/// The given incarnation is aborted, and the next one is about to be ready.
+	Aborting(Incarnation),
+}
+
+/// The scheduler of a block of transactions.
+#[derive(Debug)]
+pub struct Scheduler {
+	/// Number of transactions in the block.
+	len: usize,
+	/// The next transaction to be executed.
+	execution_index: AtomicUsize,
+	/// The next transaction to be validated.
+	validation_index: AtomicUsize,
+	/// Number of times that any of the indices has been decreased.
+	decrease_count: AtomicUsize,
+	/// Number of tasks that are handed out and not yet finished.
+	active_tasks: AtomicUsize,
+	/// Set once all transactions are executed and validated.
+	done: AtomicBool,
+	/// Status of each transaction.
+	status: Vec<Mutex<Status>>,
+	/// The transactions that are blocked on each transaction.
+	dependencies: Vec<Mutex<Vec<TxIndex>>>,
+}
+
+impl Scheduler {
+	/// Create a new scheduler for a block of `len` transactions.
+	pub fn new(len: usize) -> Self {
+		Self {
+			len,
+			execution_index: AtomicUsize::new(0),
+			validation_index: AtomicUsize::new(0),
+			decrease_count: AtomicUsize::new(0),
+			active_tasks: AtomicUsize::new(0),
+			done: AtomicBool::new(len == 0),
+			status: (0..len)
+				.map(|_| Mutex::new(Status::ReadyToExecute(0)))
+				.collect(),
+			dependencies: (0..len).map(|_| Default::default()).collect(),
+		}
+	}
+
+	/// True if all the transactions are executed and validated.
+	pub fn done(&self) -> bool {
+		self.done.load(Ordering::SeqCst)
+	}
+
+	/// Stop handing out any further tasks, regardless of the transactions being done or not.
+	///
+	/// This should only be used if a thread fails, in which case the outcome of the block is
+	/// meaningless.
+	pub fn halt(&self) {
+		self.done.store(true, Ordering::SeqCst)
+	}
+
+	/// Get the next task, if any is available now.
+	pub fn next_task(&self) -> Option<Task> {
+		if self.validation_index.load(Ordering::SeqCst)
+			< self.execution_index.load(Ordering::SeqCst)
+		{
+			self.next_version_to_validate().map(Task::Validate)
+		} else {
+			self.next_version_to_execute().map(Task::Execute)
+		}
+	}
+
+	/// Register the transaction at `index` to be blocked on the transaction at `blocking`.
+	///
+	/// Returns false if `blocking` is already executed, in which case `index` should be executed
+	/// again right away. Otherwise, the execution task of `index` is over, and it will be resumed
+	/// once `blocking` is executed.
+	pub fn add_dependency(&self, index: TxIndex, blocking: TxIndex) -> bool {
+		let mut dependencies = self.dependencies[blocking].lock().unwrap();
+		if let Status::Executed(_) = *self.status[blocking].lock().unwrap() {
+			return false;
+		}
+
+		{
+			let mut status = self.status[index].lock().unwrap();
+			if let Status::Executing(incarnation) = *status {
+				*status = Status::Aborting(incarnation);
+			} else {
+				panic!("Transaction {} must be executing to be blocked.", index);
+			}
+		}
+		dependencies.push(index);
+		drop(dependencies);
+
+		self.active_tasks.fetch_sub(1, Ordering::SeqCst);
+		true
+	}
+
+	/// Finish the execution of the given version.
+	///
+	/// `wrote_new_key` should be true if the execution wrote to any key that the previous
+	/// incarnation did not write to. Might return a validation task of the same version.
+	pub fn finish_execution(
+		&self,
+		(index, incarnation): Version,
+		wrote_new_key: bool,
+	) -> Option<Task> {
+		*self.status[index].lock().unwrap() = Status::Executed(incarnation);
+
+		let dependencies = mem::take(&mut *self.dependencies[index].lock().unwrap());
+		self.resume_dependencies(dependencies);
+
+		if self.validation_index.load(Ordering::SeqCst) > index {
+			if wrote_new_key {
+				// all transactions after this one might have missed a new write.
+				self.decrease_validation_index(index);
+			} else {
+				return Some(Task::Validate((index, incarnation)));
+			}
+		}
+
+		self.active_tasks.fetch_sub(1, Ordering::SeqCst);
+		None
+	}
+
+	/// Try and abort the given version, after a failed validation.
+	///
+	/// Returns true if the version was aborted by this call. Only one thread can abort a version.
+	pub fn try_validation_abort(&self, (index, incarnation): Version) -> bool {
+		let mut status = self.status[index].lock().unwrap();
+		if *status == Status::Executed(incarnation) {
+			*status = Status::Aborting(incarnation);
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Finish the validation of the transaction at `index`.
+	///
+	/// If it was `aborted`, this might return the task to re-execute it.
+	pub fn finish_validation(&self, index: TxIndex, aborted: bool) -> Option<Task> {
+		if aborted {
+			self.set_ready(index);
+			// all transactions after this one must be validated again.
+			self.decrease_validation_index(index + 1);
+			if self.execution_index.load(Ordering::SeqCst) > index {
+				return self.try_incarnate(index).map(Task::Execute);
+			}
+		}
+
+		self.active_tasks.fetch_sub(1, Ordering::SeqCst);
+		None
+	}
+
+	fn next_version_to_execute(&self) -> Option<Version> {
+		if self.execution_index.load(Ordering::SeqCst) >= self.len {
+			self.check_done();
+			return None;
+		}
+
+		self.active_tasks.fetch_add(1, Ordering::SeqCst);
+		let index = self.execution_index.fetch_add(1, Ordering::SeqCst);
+		self.try_incarnate(index)
+	}
+
+	fn next_version_to_validate(&self) -> Option<Version> {
+		if self.validation_index.load(Ordering::SeqCst) >= self.len {
+			self.check_done();
+			return None;
+		}
+
+		self.active_tasks.fetch_add(1, Ordering::SeqCst);
+		let index = self.validation_index.fetch_add(1, Ordering::SeqCst);
+		if index < self.len {
+			if let Status::Executed(incarnation) = *self.status[index].lock().unwrap() {
+				return Some((index, incarnation));
+			}
+		}
+
+		self.active_tasks.fetch_sub(1, Ordering::SeqCst);
+		None
+	}
+
+	/// Start executing the transaction at `index`, if it is ready to be executed.
+	///
+	/// If not, the task that was accounted for it in `active_tasks` is over.
+	fn try_incarnate(&self, index: TxIndex) -> Option<Version> {
+		if index < self.len {
+			let mut status = self.status[index].lock().unwrap();
+			if let Status::ReadyToExecute(incarnation) = *status {
+				*status = Status::Executing(incarnation);
+				return Some((index, incarnation));
+			}
+		}
+
+		self.active_tasks.fetch_sub(1, Ordering::SeqCst);
+		None
+	}
+
+	fn set_ready(&self, index: TxIndex) {
+		let mut status = self.status[index].lock().unwrap();
+		if let Status::Aborting(incarnation) = *status {
+			*status = Status::ReadyToExecute(incarnation + 1);
+		} else {
+			panic!("Transaction {} must be aborting to be ready.", index);
+		}
+	}
+
+	fn resume_dependencies(&self, dependencies: Vec<TxIndex>) {
+		dependencies.iter().for_each(|index| self.set_ready(*index));
+		if let Some(first) = dependencies.into_iter().min() {
+			self.decrease_execution_index(first);
+		}
+	}
+
+	fn decrease_execution_index(&self, target: TxIndex) {
+		self.execution_index.fetch_min(target, Ordering::SeqCst);
+		self.decrease_count.fetch_add(1, Ordering::SeqCst);
+	}
+
+	fn decrease_validation_index(&self, target: TxIndex) {
+		self.validation_index.fetch_min(target, Ordering::SeqCst);
+		self.decrease_count.fetch_add(1, Ordering::SeqCst);
+	}
+
+	/// Mark the block as done, if both indices are past the end and no task is active.
+	///
+	/// `decrease_count` makes sure that neither index was decreased in between the reads.
+	fn check_done(&self) {
+		let observed = self.decrease_count.load(Ordering::SeqCst);
+		if min(
+			self.execution_index.load(Ordering::SeqCst),
+			self.validation_index.load(Ordering::SeqCst),
+		) >= self.len
+			&& self.active_tasks.load(Ordering::SeqCst) == 0
+			&& observed == self.decrease_count.load(Ordering::SeqCst)
+		{
+			self.done.store(true, Ordering::SeqCst);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn empty_block_is_done() {
+		let scheduler = Scheduler::new(0);
+		assert!(scheduler.done());
+		assert_eq!(scheduler.next_task(), None);
+	}
+
+	#[test]
+	fn executes_then_validates_in_order() {
+		let scheduler = Scheduler::new(2);
+		assert_eq!(scheduler.next_task(), Some(Task::Execute((0, 0))));
+		assert_eq!(scheduler.finish_execution((0, 0), true), None);
+
+		// validation is now behind execution.
+		assert_eq!(scheduler.next_task(), Some(Task::Validate((0, 0))));
+		assert_eq!(scheduler.finish_validation(0, false), None);
+
+		assert_eq!(scheduler.next_task(), Some(Task::Execute((1, 0))));
+		assert_eq!(scheduler.finish_execution((1, 0), true), None);
+		assert_eq!(scheduler.next_task(), Some(Task::Validate((1, 0))));
+		assert_eq!(scheduler.finish_validation(1, false), None);
+
+		assert!(!scheduler.done());
+		assert_eq!(scheduler.next_task(), None);
+		assert!(scheduler.done());
+	}
+
+	#[test]
+	fn aborted_validation_re_executes() {
+		let scheduler = Scheduler::new(2);
+		assert_eq!(scheduler.next_task(), Some(Task::Execute((0, 0))));
+		// 0 is not executed yet, hence there is nothing to validate.
+		assert_eq!(scheduler.next_task(), None);
+		assert_eq!(scheduler.next_task(), Some(Task::Execute((1, 0))));
+		assert_eq!(scheduler.finish_execution((1, 0), true), None);
+		assert_eq!(scheduler.finish_execution((0, 0), true), None);
+
+		assert_eq!(scheduler.next_task(), Some(Task::Validate((0, 0))));
+		assert_eq!(scheduler.finish_validation(0, false), None);
+		assert_eq!(scheduler.next_task(), Some(Task::Validate((1, 0))));
+
+		// only one abort can succeed.
+		assert!(scheduler.try_validation_abort((1, 0)));
+		assert!(!scheduler.try_validation_abort((1, 0)));
+		assert_eq!(
+			scheduler.finish_validation(1, true),
+			Some(Task::Execute((1, 1)))
+		);
+
+		// re-execution did not write anything new, validate right away.
+		assert_eq!(
+			scheduler.finish_execution((1, 1), false),
+			Some(Task::Validate((1, 1)))
+		);
+		assert_eq!(scheduler.finish_validation(1, false), None);
+
+		assert_eq!(scheduler.next_task(), None);
+		assert!(scheduler.done());
+	}
+
+	#[test]
+	fn blocked_transaction_resumes_after_dependency() {
+		let scheduler = Scheduler::new(2);
+		assert_eq!(scheduler.next_task(), Some(Task::Execute((0, 0))));
+		// 0 is not executed yet, hence there is nothing to validate.
+		assert_eq!(scheduler.next_task(), None);
+		assert_eq!(scheduler.next_task(), Some(Task::Execute((1, 0))));
+
+		// 1 is blocked on 0.
+		assert!(scheduler.add_dependency(1, 0));
+		assert_eq!(scheduler.next_task(), None);
+		assert!(!scheduler.done());
+
+		// once 0 is done, 1 is ready to be executed again, after 0 is validated.
+		assert_eq!(scheduler.finish_execution((0, 0), true), None);
+		assert_eq!(scheduler.next_task(), Some(Task::Validate((0, 0))));
+		assert_eq!(scheduler.finish_validation(0, false), None);
+		assert_eq!(scheduler.next_task(), Some(Task::Execute((1, 1))));
+
+		// 0 is now executed, hence 1 cannot be blocked on it anymore.
+		assert!(!scheduler.add_dependency(1, 0));
+	}
+}
@@ -1,5 +1,126 @@
-use crate::types;
-use std::iter::*;
+use crate::types::{self, IndexedTransaction, InvalidBlock, NoncedTransaction, PoolError};
+use primitives::{AccountId, Balance, Nonce, TransactionId};
+use runtime::{system::NonceOf, SequentialRuntime};
+use std::{
+	cmp::Reverse,
+	collections::{btree_map, BTreeMap, BTreeSet, BinaryHeap, HashMap, VecDeque},
+	iter::*,
+	sync::{
+		mpsc::{channel, SendError, Sender},
+		Arc, Mutex, OnceLock, PoisonError,
+	},
+	thread,
+};
+
+/// The minimum number of transactions that each thread verifies in `verify_parallel`.
+const MIN_VERIFY_CHUNK: usize = 64;
+
+/// A chunk of transactions to be verified by one of the `VERIFIERS`.
+type VerifyJob = Box<dyn FnOnce() + Send>;
+
+/// The threads that carry out `verify_parallel`, one per core, shared by all of the pools.
+///
+/// The chunks are not handed to the workers of an executor, since a pool is not tied to one, and
+/// the transactions of a block are verified before the workers have anything to do anyhow. Instead,
+/// these threads are spawned once, upon the first batch that is large enough, and then idle until
+/// the next one. They inherit the cpu affinity of the thread that spawns them, hence the master
+/// verifies before it pins itself.
+static VERIFIERS: OnceLock<Mutex<Sender<VerifyJob>>> = OnceLock::new();
+
+fn verifiers() -> &'static Mutex<Sender<VerifyJob>> {
+	VERIFIERS.get_or_init(|| {
+		let (jobs, inbox) = channel::<VerifyJob>();
+		let inbox = Arc::new(Mutex::new(inbox));
+		for i in 0..num_cpus::get() {
+			let inbox = Arc::clone(&inbox);
+			thread::Builder::new()
+				.name(format!("Verifier#{}", i))
+				.spawn(move || loop {
+					let job = inbox.lock().unwrap_or_else(PoisonError::into_inner).recv();
+					match job {
+						Ok(job) => job(),
+						Err(_) => break,
+					}
+				})
+				.expect("Failed to spawn a verification thread.");
+		}
+		Mutex::new(jobs)
+	})
+}
+
+/// Verify all of the given transactions, in batches that are spread over all of the cores by the
+/// `VERIFIERS`.
+///
+/// Returns whether each of them is sane, in order.
+fn verify_parallel<Tx: Clone + Send + 'static + types::VerifiableTransaction>(
+	txs: &[Tx],
+) -> Vec<bool> {
+	let chunk_size = (txs.len() / num_cpus::get()).max(MIN_VERIFY_CHUNK);
+	if txs.len() <= chunk_size {
+		return Tx::verify_batch(txs);
+	}
+
+	let (send, outcomes) = channel();
+	let chunks = txs.chunks(chunk_size).count();
+	for (index, chunk) in txs.chunks(chunk_size).enumerate() {
+		let chunk = chunk.to_vec();
+		let send = send.clone();
+		let job: VerifyJob = Box::new(move || {
+			let _ = send.send((index, Tx::verify_batch(&chunk)));
+		});
+		// the verifiers are only gone if all of them panicked; verify in place then.
+		let sent = verifiers()
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.send(job);
+		if let Err(SendError(job)) = sent {
+			job();
+		}
+	}
+	drop(send);
+
+	let mut outcomes = outcomes.iter().collect::<Vec<_>>();
+	assert_eq!(outcomes.len(), chunks, "Verification thread panicked.");
+	outcomes.sort_by_key(|(index, _)| *index);
+	outcomes.into_iter().flat_map(|(_, ok)| ok).collect()
+}
+
+/// Verify all of the transactions of a block with `verify_parallel`.
+///
+/// Returns the first transaction with an invalid signature as the reason to reject the block.
+pub(crate) fn verify_block<
+	Tx: Clone + Send + 'static + IndexedTransaction + types::VerifiableTransaction,
+>(
+	txs: &[Tx],
+) -> Result<(), InvalidBlock> {
+	match verify_parallel(txs)
+		.into_iter()
+		.zip(txs)
+		.find(|(ok, _)| !ok)
+	{
+		Some((_, tx)) => Err(InvalidBlock::BadSignature(tx.id())),
+		None => Ok(()),
+	}
+}
+
+/// Verify all of the given transactions with `verify_parallel`, and push the sane ones with
+/// `push`, in order.
+fn push_verified<Tx: Clone + Send + 'static + types::VerifiableTransaction>(
+	txs: &[Tx],
+	mut push: impl FnMut(Tx) -> Result<(), PoolError>,
+) -> Vec<Result<(), PoolError>> {
+	verify_parallel(txs)
+		.into_iter()
+		.zip(txs)
+		.map(|(ok, tx)| {
+			if ok {
+				push(tx.clone())
+			} else {
+				Err(PoolError::InvalidSignature)
+			}
+		})
+		.collect()
+}
 
 /// An ordered. transaction pool.
 pub trait TransactionPool<Tx>: From<Vec<Tx>> {
@@ -9,20 +130,30 @@ pub trait TransactionPool<Tx>: From<Vec<Tx>> {
 	/// Create a new transaction pool with the given capacity.
 	fn with_capacity(size: usize) -> Self;
 
+	/// Create a new transaction pool that holds at most `limit` transactions.
+	///
+	/// Once it is full, which transactions are kept is up to the pool. A new transaction that is
+	/// not admitted is rejected with [`PoolError::PoolFull`].
+	fn bounded(limit: usize) -> Self;
+
+	/// The maximum number of transactions of this pool, if any.
+	fn limit(&self) -> Option<usize>;
+
 	/// Insert a new transaction into the pool.
 	///
-	/// This will add the transaction to the end of the pool.
-	fn push_back(&mut self, tx: Tx);
+	/// This will add the transaction to the end of the pool, if it is sane.
+	fn push_back(&mut self, tx: Tx) -> Result<(), PoolError>;
 
 	/// Insert a batch of transactions
 	///
-	/// This will add all of the transactions to the end of the pool.
-	fn push_batch(&mut self, txs: &[Tx]);
+	/// This will add all of the sane transactions to the end of the pool, in order. Their
+	/// signatures are verified in batches, in parallel. Returns the outcome of each transaction.
+	fn push_batch(&mut self, txs: &[Tx]) -> Vec<Result<(), PoolError>>;
 
-	/// Insert a new transaction at the given index of the pool.
+	/// Insert a new transaction at the given index of the pool, if it is sane.
 	///
 	/// Panics if index > len.
-	fn insert(&mut self, index: usize, tx: Tx);
+	fn insert(&mut self, index: usize, tx: Tx) -> Result<(), PoolError>;
 
 	/// Remove the first element that matches the criteria from the pool, returning the removed
 	/// element.
@@ -42,6 +173,28 @@ pub trait TransactionPool<Tx>: From<Vec<Tx>> {
 	/// Will panic if index is out of bound.
 	fn remove_at(&mut self, at: usize) -> Tx;
 
+	/// Remove all the elements that match the criteria from the pool, returning them in order.
+	///
+	/// `search` is called exactly once for each transaction, in the order of the pool. The
+	/// remaining transactions keep their order.
+	fn extract(&mut self, search: impl FnMut(&Tx) -> bool) -> Vec<Tx>;
+
+	/// Get the transaction with the given id, if it exists.
+	fn get_by_id(&self, id: TransactionId) -> Option<&Tx>
+	where
+		Tx: IndexedTransaction,
+	{
+		self.get(|tx| tx.id() == id).map(|(_, tx)| tx)
+	}
+
+	/// Remove the transaction with the given id, if it exists.
+	fn remove_by_id(&mut self, id: TransactionId) -> Option<Tx>
+	where
+		Tx: IndexedTransaction,
+	{
+		self.remove(|tx| tx.id() == id)
+	}
+
 	/// Remove all.
 	fn clear(&mut self);
 
@@ -72,14 +225,29 @@ pub trait TransactionPool<Tx>: From<Vec<Tx>> {
 }
 
 /// A transaction pool implemented by a vector.
+///
+/// It has no notion of priority, hence once a bounded one is full, any new transaction is rejected.
 #[derive(Default, Debug)]
 pub struct VecPool<Tx> {
 	inner: Vec<Tx>,
+	limit: Option<usize>,
+}
+
+impl<Tx> VecPool<Tx> {
+	fn admit(&self) -> Result<(), PoolError> {
+		match self.limit {
+			Some(limit) if self.inner.len() >= limit => Err(PoolError::PoolFull),
+			_ => Ok(()),
+		}
+	}
 }
 
 impl<Tx> From<Vec<Tx>> for VecPool<Tx> {
 	fn from(v: Vec<Tx>) -> Self {
-		Self { inner: v }
+		Self {
+			inner: v,
+			limit: None,
+		}
 	}
 }
 
@@ -91,14 +259,19 @@ impl<Tx> IntoIterator for VecPool<Tx> {
 	}
 }
 
-pub struct PoolIter<'a, Tx>(std::slice::Iter<'a, Tx>);
+pub struct PoolIter<'a, Tx>(IterInner<'a, Tx>);
+
+enum IterInner<'a, Tx> {
+	Vec(std::slice::Iter<'a, Tx>),
+	Indexed(btree_map::Values<'a, u64, Tx>),
+}
 
 impl<'a, Tx> IntoIterator for &'a VecPool<Tx> {
 	type IntoIter = PoolIter<'a, Tx>;
 	type Item = &'a Tx;
 
 	fn into_iter(self) -> Self::IntoIter {
-		PoolIter(self.inner.iter())
+		PoolIter(IterInner::Vec(self.inner.iter()))
 	}
 }
 
@@ -106,18 +279,26 @@ impl<'a, Tx> Iterator for PoolIter<'a, Tx> {
 	type Item = &'a Tx;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		self.0.next()
+		match &mut self.0 {
+			IterInner::Vec(iter) => iter.next(),
+			IterInner::Indexed(iter) => iter.next(),
+		}
 	}
 }
 
-pub struct PoolIterMut<'a, Tx>(std::slice::IterMut<'a, Tx>);
+pub struct PoolIterMut<'a, Tx>(IterMutInner<'a, Tx>);
+
+enum IterMutInner<'a, Tx> {
+	Vec(std::slice::IterMut<'a, Tx>),
+	Indexed(btree_map::ValuesMut<'a, u64, Tx>),
+}
 
 impl<'a, Tx> IntoIterator for &'a mut VecPool<Tx> {
 	type IntoIter = PoolIterMut<'a, Tx>;
 	type Item = &'a mut Tx;
 
 	fn into_iter(self) -> Self::IntoIter {
-		PoolIterMut(self.inner.iter_mut())
+		PoolIterMut(IterMutInner::Vec(self.inner.iter_mut()))
 	}
 }
 
@@ -125,45 +306,79 @@ impl<'a, Tx> Iterator for PoolIterMut<'a, Tx> {
 	type Item = &'a mut Tx;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		self.0.next()
+		match &mut self.0 {
+			IterMutInner::Vec(iter) => iter.next(),
+			IterMutInner::Indexed(iter) => iter.next(),
+		}
 	}
 }
 
-impl<Tx: Clone + types::VerifiableTransaction> TransactionPool<Tx> for VecPool<Tx> {
+impl<Tx: Clone + Send + 'static + types::VerifiableTransaction> TransactionPool<Tx>
+	for VecPool<Tx>
+{
 	fn new() -> Self {
 		Self {
 			inner: Default::default(),
+			limit: None,
 		}
 	}
 
 	fn with_capacity(size: usize) -> Self {
 		Self {
 			inner: Vec::with_capacity(size),
+			limit: None,
 		}
 	}
 
-	fn push_back(&mut self, tx: Tx) {
+	fn bounded(limit: usize) -> Self {
+		Self {
+			inner: Vec::with_capacity(limit),
+			limit: Some(limit),
+		}
+	}
+
+	fn limit(&self) -> Option<usize> {
+		self.limit
+	}
+
+	fn push_back(&mut self, tx: Tx) -> Result<(), PoolError> {
 		if !tx.verify() {
-			panic!("Attempted to insert invalid transaction to the pool.")
+			return Err(PoolError::InvalidSignature);
 		}
+		self.admit()?;
 		self.inner.push(tx);
+		Ok(())
 	}
 
-	fn push_batch(&mut self, tx: &[Tx]) {
-		tx.into_iter().for_each(|t| self.push_back(t.clone()));
+	fn push_batch(&mut self, txs: &[Tx]) -> Vec<Result<(), PoolError>> {
+		push_verified(txs, |tx| {
+			self.admit()?;
+			self.inner.push(tx);
+			Ok(())
+		})
 	}
 
-	fn insert(&mut self, index: usize, tx: Tx) {
+	fn insert(&mut self, index: usize, tx: Tx) -> Result<(), PoolError> {
 		if !tx.verify() {
-			panic!("Attempted to insert invalid transaction to the pool.")
+			return Err(PoolError::InvalidSignature);
 		}
+		self.admit()?;
 		self.inner.insert(index, tx);
+		Ok(())
 	}
 
 	fn remove_at(&mut self, at: usize) -> Tx {
 		self.inner.remove(at)
 	}
 
+	fn extract(&mut self, mut search: impl FnMut(&Tx) -> bool) -> Vec<Tx> {
+		let (matched, rest) = std::mem::take(&mut self.inner)
+			.into_iter()
+			.partition(|tx| search(tx));
+		self.inner = rest;
+		matched
+	}
+
 	fn clear(&mut self) {
 		self.inner.clear();
 	}
@@ -197,18 +412,522 @@ impl<Tx: Clone + types::VerifiableTransaction> TransactionPool<Tx> for VecPool<T
 	}
 }
 
+/// A transaction pool indexed by the id of the transactions.
+///
+/// Transactions are kept in insertion order, and can be looked up and removed by their id in
+/// logarithmic time. The ids must be unique: a transaction with an id that is already in the pool
+/// is rejected with [`PoolError::Duplicate`].
+///
+/// Replacing a transaction by a higher paying one is up to [`ReadyPool`], by nonce.
+///
+/// The pool can optionally be bounded. Once it is full, a new transaction is only admitted if it
+/// has a higher priority than the lowest one in the pool, which is then evicted to make room for
+/// it. Otherwise, it is rejected with [`PoolError::PoolFull`]. Among transactions of equal priority,
+/// the most recent one is evicted first.
+///
+/// Note that the priority only decides which transactions are kept. The pool is still iterated,
+/// and hence drained into a block, in insertion order.
+///
+/// The id and priority of a transaction must not be changed while it is in the pool, e.g. through
+/// `get_mut` or `iter_mut`.
+#[derive(Debug)]
+pub struct IndexedPool<Tx> {
+	/// The transactions, by their insertion sequence number.
+	txs: BTreeMap<u64, Tx>,
+	/// The sequence number and priority of each transaction, by id.
+	index: HashMap<TransactionId, (u64, Balance)>,
+	/// The priority and sequence number of all transactions, lowest priority first.
+	by_priority: BTreeSet<(Balance, Reverse<u64>)>,
+	/// The sequence number of the next transaction.
+	next: u64,
+	/// The maximum number of transactions, if any.
+	limit: Option<usize>,
+	/// The number of transactions that have been evicted.
+	dropped: usize,
+}
+
+impl<Tx: IndexedTransaction> IndexedPool<Tx> {
+	/// The number of transactions that have been evicted to make room for higher priority ones.
+	///
+	/// This does not include the ones that were not admitted in the first place.
+	pub fn dropped(&self) -> usize {
+		self.dropped
+	}
+
+	fn unbounded() -> Self {
+		Self {
+			txs: Default::default(),
+			index: Default::default(),
+			by_priority: Default::default(),
+			next: 0,
+			limit: None,
+			dropped: 0,
+		}
+	}
+
+	/// Check if a new transaction with the given id and priority can be added to the pool,
+	/// possibly by evicting another one.
+	fn admit(&self, id: TransactionId, priority: Balance) -> Result<(), PoolError> {
+		if self.index.contains_key(&id) {
+			return Err(PoolError::Duplicate);
+		}
+		match (self.limit, self.by_priority.iter().next()) {
+			(Some(0), _) => Err(PoolError::PoolFull),
+			(Some(limit), Some((lowest, _))) if self.txs.len() >= limit && priority <= *lowest => {
+				Err(PoolError::PoolFull)
+			}
+			_ => Ok(()),
+		}
+	}
+
+	/// Evict the transactions with the lowest priority until the pool is within its bound.
+	fn evict(&mut self) {
+		if let Some(limit) = self.limit {
+			while self.txs.len() > limit {
+				let (_, Reverse(lowest)) = *self
+					.by_priority
+					.iter()
+					.next()
+					.expect("pool is not empty; qed");
+				self.remove_seq(lowest);
+				self.dropped += 1;
+			}
+		}
+	}
+
+	/// Push `tx` to the end of the pool, without verifying it.
+	///
+	/// Fails if `tx` is not admitted, in which case the pool is left intact.
+	fn push_unchecked(&mut self, tx: Tx) -> Result<(), PoolError> {
+		let id = tx.id();
+		let priority = tx.priority();
+		self.admit(id, priority)?;
+
+		let seq = self.next;
+		self.next += 1;
+		self.txs.insert(seq, tx);
+		self.index.insert(id, (seq, priority));
+		self.by_priority.insert((priority, Reverse(seq)));
+		self.evict();
+		Ok(())
+	}
+
+	fn remove_seq(&mut self, seq: u64) -> Tx {
+		let tx = self
+			.txs
+			.remove(&seq)
+			.expect("sequence numbers are always indexed; qed");
+		let (_, priority) = self
+			.index
+			.remove(&tx.id())
+			.expect("all transactions are indexed; qed");
+		self.by_priority.remove(&(priority, Reverse(seq)));
+		tx
+	}
+
+	fn seq_at(&self, at: usize) -> u64 {
+		*self.txs.keys().nth(at).expect("Index out of bound")
+	}
+}
+
+impl<Tx: IndexedTransaction> From<Vec<Tx>> for IndexedPool<Tx> {
+	/// Build a pool of `v`, without verifying it.
+	///
+	/// The ids must be unique. Otherwise, all but the first transaction with the same id are
+	/// dropped, which is a bug in debug builds.
+	fn from(v: Vec<Tx>) -> Self {
+		let mut pool = Self::unbounded();
+		v.into_iter().for_each(|tx| {
+			let outcome = pool.push_unchecked(tx);
+			debug_assert_eq!(outcome, Ok(()), "Transaction ids must be unique.");
+		});
+		pool
+	}
+}
+
+impl<Tx> IntoIterator for IndexedPool<Tx> {
+	type IntoIter = btree_map::IntoValues<u64, Tx>;
+	type Item = Tx;
+	fn into_iter(self) -> Self::IntoIter {
+		self.txs.into_values()
+	}
+}
+
+impl<'a, Tx> IntoIterator for &'a IndexedPool<Tx> {
+	type IntoIter = PoolIter<'a, Tx>;
+	type Item = &'a Tx;
+
+	fn into_iter(self) -> Self::IntoIter {
+		PoolIter(IterInner::Indexed(self.txs.values()))
+	}
+}
+
+impl<'a, Tx> IntoIterator for &'a mut IndexedPool<Tx> {
+	type IntoIter = PoolIterMut<'a, Tx>;
+	type Item = &'a mut Tx;
+
+	fn into_iter(self) -> Self::IntoIter {
+		PoolIterMut(IterMutInner::Indexed(self.txs.values_mut()))
+	}
+}
+
+impl<Tx: Clone + Send + 'static + types::VerifiableTransaction + IndexedTransaction>
+	TransactionPool<Tx> for IndexedPool<Tx>
+{
+	fn new() -> Self {
+		Self::unbounded()
+	}
+
+	fn with_capacity(size: usize) -> Self {
+		Self {
+			index: HashMap::with_capacity(size),
+			..Self::unbounded()
+		}
+	}
+
+	fn bounded(limit: usize) -> Self {
+		Self {
+			limit: Some(limit),
+			..Self::with_capacity(limit)
+		}
+	}
+
+	fn limit(&self) -> Option<usize> {
+		self.limit
+	}
+
+	fn push_back(&mut self, tx: Tx) -> Result<(), PoolError> {
+		if !tx.verify() {
+			return Err(PoolError::InvalidSignature);
+		}
+		self.push_unchecked(tx)
+	}
+
+	fn push_batch(&mut self, txs: &[Tx]) -> Vec<Result<(), PoolError>> {
+		push_verified(txs, |tx| self.push_unchecked(tx))
+	}
+
+	/// Insert a new transaction at the given index of the pool.
+	///
+	/// Unlike pushing to the back, this needs to re-sequence the entire pool.
+	fn insert(&mut self, index: usize, tx: Tx) -> Result<(), PoolError> {
+		if !tx.verify() {
+			return Err(PoolError::InvalidSignature);
+		}
+		self.admit(tx.id(), tx.priority())?;
+		let mut txs = std::mem::take(&mut self.txs)
+			.into_values()
+			.collect::<Vec<_>>();
+		txs.insert(index, tx);
+		self.index.clear();
+		self.by_priority.clear();
+		self.next = 0;
+
+		// re-sequence everything first, and only then evict the one that `tx` replaces.
+		let limit = self.limit.take();
+		txs.into_iter()
+			.try_for_each(|tx| self.push_unchecked(tx))
+			.expect("ids are checked to be unique; qed");
+		self.limit = limit;
+		self.evict();
+		Ok(())
+	}
+
+	fn remove_at(&mut self, at: usize) -> Tx {
+		let seq = self.seq_at(at);
+		self.remove_seq(seq)
+	}
+
+	fn extract(&mut self, mut search: impl FnMut(&Tx) -> bool) -> Vec<Tx> {
+		let matched = self
+			.txs
+			.iter()
+			.filter(|(_, tx)| search(tx))
+			.map(|(seq, _)| *seq)
+			.collect::<Vec<_>>();
+		matched
+			.into_iter()
+			.map(|seq| self.remove_seq(seq))
+			.collect()
+	}
+
+	fn clear(&mut self) {
+		self.txs.clear();
+		self.index.clear();
+		self.by_priority.clear();
+	}
+
+	fn len(&self) -> usize {
+		self.txs.len()
+	}
+
+	fn all(&self) -> Vec<Tx> {
+		self.txs.values().cloned().collect()
+	}
+
+	fn destruct(self) -> Vec<Tx> {
+		self.txs.into_values().collect()
+	}
+
+	fn get(&self, search: impl Fn(&Tx) -> bool) -> Option<(usize, &Tx)> {
+		self.txs.values().enumerate().find(|(_, tx)| search(tx))
+	}
+
+	fn get_mut(&mut self, search: impl Fn(&Tx) -> bool) -> Option<(usize, &mut Tx)> {
+		self.txs.values_mut().enumerate().find(|(_, tx)| search(tx))
+	}
+
+	fn get_by_id(&self, id: TransactionId) -> Option<&Tx> {
+		self.index.get(&id).map(|(seq, _)| &self.txs[seq])
+	}
+
+	fn remove_by_id(&mut self, id: TransactionId) -> Option<Tx> {
+		let (seq, _) = self.index.get(&id).copied()?;
+		Some(self.remove_seq(seq))
+	}
+
+	fn iter<'a>(&'a self) -> PoolIter<'a, Tx> {
+		self.into_iter()
+	}
+
+	fn iter_mut<'a>(&'a mut self) -> PoolIterMut<'a, Tx> {
+		self.into_iter()
+	}
+}
+
+/// The outcome of a successful submission to a [`ReadyPool`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Submitted {
+	/// The transaction can be included right away.
+	Ready,
+	/// The transaction waits for transactions of its sender with lower nonces.
+	Future,
+}
+
+/// The reason why a transaction is not admitted to a [`ReadyPool`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Rejected {
+	/// The signature of the transaction is invalid.
+	Invalid,
+	/// The nonce of the transaction has already been used by an included transaction.
+	Stale,
+	/// A transaction with the same sender and nonce, and a higher or equal tip, already exists.
+	Underpriced,
+}
+
+/// The transactions of a single sender in a [`ReadyPool`].
+#[derive(Debug)]
+struct SenderQueue<Tx> {
+	/// The order in which this sender was first seen, used to break ties between senders.
+	seq: u64,
+	/// The nonce of the sender, as of the last pruning.
+	nonce: Nonce,
+	/// The transactions that can be included right away. The nonce of the i-th one is
+	/// `self.nonce + i`.
+	ready: VecDeque<Tx>,
+	/// The transactions that wait for a missing nonce, by nonce.
+	future: BTreeMap<Nonce, Tx>,
+}
+
+impl<Tx: NoncedTransaction> SenderQueue<Tx> {
+	/// Move all the future transactions that no longer miss a nonce to the ready queue.
+	fn promote(&mut self) {
+		while let Some(tx) = self
+			.future
+			.remove(&(self.nonce + self.ready.len() as Nonce))
+		{
+			self.ready.push_back(tx);
+		}
+	}
+
+	/// Set the nonce of the sender to `nonce`, dropping all of the transactions below it and
+	/// re-computing the ready queue.
+	///
+	/// Returns the number of dropped transactions.
+	fn set_nonce(&mut self, nonce: Nonce) -> usize {
+		let mut all = std::mem::take(&mut self.future);
+		let start = self.nonce;
+		all.extend(
+			std::mem::take(&mut self.ready)
+				.into_iter()
+				.enumerate()
+				.map(|(i, tx)| (start + i as Nonce, tx)),
+		);
+		let before = all.len();
+		self.future = all.split_off(&nonce);
+		self.nonce = nonce;
+		self.promote();
+		before - self.future.len() - self.ready.len()
+	}
+
+	fn is_empty(&self) -> bool {
+		self.ready.is_empty() && self.future.is_empty()
+	}
+}
+
+/// A pool of transactions that are ordered by the nonce of their sender.
+///
+/// The transactions of each sender are split into a _ready_ queue, which has consecutive nonces
+/// starting from the current nonce of the sender, and a _future_ queue, of those that miss a
+/// nonce. Future transactions are promoted once the missing ones are submitted, or once their
+/// predecessors are included and the pool is pruned against the new state.
+///
+/// A transaction with the same sender and nonce as an existing one replaces it only if it has a
+/// strictly higher tip.
+#[derive(Debug)]
+pub struct ReadyPool<Tx> {
+	/// The transactions of each sender.
+	senders: HashMap<AccountId, SenderQueue<Tx>>,
+	/// The sequence number of the next new sender.
+	next: u64,
+}
+
+impl<Tx> Default for ReadyPool<Tx> {
+	fn default() -> Self {
+		Self {
+			senders: Default::default(),
+			next: 0,
+		}
+	}
+}
+
+impl<Tx: NoncedTransaction + types::VerifiableTransaction> ReadyPool<Tx> {
+	/// Create a new, empty pool.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Submit a new transaction to the pool.
+	///
+	/// The nonce of a sender that is not yet known is read from the state of `runtime`.
+	pub fn submit(&mut self, runtime: &SequentialRuntime, tx: Tx) -> Result<Submitted, Rejected> {
+		if !tx.verify() {
+			return Err(Rejected::Invalid);
+		}
+
+		let sender = tx.sender();
+		let nonce = match self.senders.get(&sender) {
+			Some(queue) => queue.nonce,
+			None => Self::nonce_of(runtime, sender),
+		};
+		if tx.nonce() < nonce {
+			return Err(Rejected::Stale);
+		}
+
+		let next = &mut self.next;
+		let queue = self.senders.entry(sender).or_insert_with(|| {
+			*next += 1;
+			SenderQueue {
+				seq: *next - 1,
+				nonce,
+				ready: Default::default(),
+				future: Default::default(),
+			}
+		});
+
+		let offset = (tx.nonce() - nonce) as usize;
+		if let Some(existing) = queue.ready.get_mut(offset) {
+			if tx.priority() <= existing.priority() {
+				return Err(Rejected::Underpriced);
+			}
+			*existing = tx;
+			Ok(Submitted::Ready)
+		} else if offset == queue.ready.len() {
+			queue.ready.push_back(tx);
+			queue.promote();
+			Ok(Submitted::Ready)
+		} else {
+			match queue.future.get(&tx.nonce()) {
+				Some(existing) if tx.priority() <= existing.priority() => {
+					Err(Rejected::Underpriced)
+				}
+				_ => {
+					queue.future.insert(tx.nonce(), tx);
+					Ok(Submitted::Future)
+				}
+			}
+		}
+	}
+
+	/// Prune the pool against the state of `runtime`, typically after a new block is applied.
+	///
+	/// All of the transactions whose nonce is used by now are dropped, and the future transactions
+	/// whose predecessors have been included are promoted. Returns the number of dropped
+	/// transactions.
+	pub fn prune(&mut self, runtime: &SequentialRuntime) -> usize {
+		let dropped = self
+			.senders
+			.iter_mut()
+			.map(|(sender, queue)| queue.set_nonce(Self::nonce_of(runtime, *sender)))
+			.sum();
+		self.senders.retain(|_, queue| !queue.is_empty());
+		dropped
+	}
+
+	/// All of the ready transactions, highest tip first.
+	///
+	/// The transactions of each sender are always in the order of their nonce, hence a transaction
+	/// can come after another one with a lower tip.
+	pub fn ready(&self) -> Vec<&Tx> {
+		let queues = self.senders.values().collect::<Vec<_>>();
+		let mut heads = queues
+			.iter()
+			.enumerate()
+			.filter_map(|(q, queue)| {
+				queue
+					.ready
+					.front()
+					.map(|tx| (tx.priority(), Reverse(queue.seq), q, 0))
+			})
+			.collect::<BinaryHeap<_>>();
+
+		let mut ready = Vec::with_capacity(self.ready_len());
+		while let Some((_, seq, q, i)) = heads.pop() {
+			ready.push(&queues[q].ready[i]);
+			if let Some(tx) = queues[q].ready.get(i + 1) {
+				heads.push((tx.priority(), seq, q, i + 1));
+			}
+		}
+		ready
+	}
+
+	/// Number of ready transactions.
+	pub fn ready_len(&self) -> usize {
+		self.senders.values().map(|queue| queue.ready.len()).sum()
+	}
+
+	/// Number of future transactions.
+	pub fn future_len(&self) -> usize {
+		self.senders.values().map(|queue| queue.future.len()).sum()
+	}
+
+	/// Number of all transactions.
+	pub fn len(&self) -> usize {
+		self.ready_len() + self.future_len()
+	}
+
+	/// True if there are no transactions in the pool.
+	pub fn is_empty(&self) -> bool {
+		self.senders.is_empty()
+	}
+
+	fn nonce_of(runtime: &SequentialRuntime, sender: AccountId) -> Nonce {
+		NonceOf::read(runtime, sender).expect("Sequential runtime cannot fail on read; qed.")
+	}
+}
+
 #[cfg(test)]
 mod vecpool_tests {
 	use super::*;
 
 	#[derive(Clone, Eq, PartialEq, Debug)]
-	struct TestTransaction {
-		id: u32,
-		signature: u32,
+	pub(super) struct TestTransaction {
+		pub(super) id: u32,
+		pub(super) signature: u32,
 	}
 
 	impl TestTransaction {
-		fn new(id: u32, signature: u32) -> Self {
+		pub(super) fn new(id: u32, signature: u32) -> Self {
 			Self { id, signature }
 		}
 	}
@@ -219,6 +938,16 @@ mod vecpool_tests {
 		}
 	}
 
+	impl IndexedTransaction for TestTransaction {
+		fn id(&self) -> TransactionId {
+			self.id
+		}
+
+		fn priority(&self) -> Balance {
+			self.signature as Balance
+		}
+	}
+
 	type Pool = VecPool<TestTransaction>;
 
 	#[test]
@@ -226,7 +955,7 @@ mod vecpool_tests {
 		let mut pool = Pool::new();
 
 		for i in (0..10).step_by(2) {
-			pool.push_back(TestTransaction::new(i, i));
+			pool.push_back(TestTransaction::new(i, i)).unwrap();
 		}
 
 		assert_eq!(
@@ -246,7 +975,7 @@ mod vecpool_tests {
 		let mut pool = Pool::new();
 
 		for i in (0..10).step_by(2) {
-			pool.push_back(TestTransaction::new(i, i));
+			pool.push_back(TestTransaction::new(i, i)).unwrap();
 		}
 
 		let (_, mut tx_mut) = pool.get_mut(|t| t.id == 8).unwrap();
@@ -261,7 +990,7 @@ mod vecpool_tests {
 		let mut pool = Pool::new();
 
 		for i in (0..6).step_by(2) {
-			pool.push_back(TestTransaction::new(i, i));
+			pool.push_back(TestTransaction::new(i, i)).unwrap();
 		}
 
 		let mut iter = pool.iter();
@@ -276,7 +1005,7 @@ mod vecpool_tests {
 		let mut pool = Pool::new();
 
 		for i in (0..6).step_by(2) {
-			pool.push_back(TestTransaction::new(i, i));
+			pool.push_back(TestTransaction::new(i, i)).unwrap();
 		}
 
 		let mut iter = pool.into_iter();
@@ -291,7 +1020,7 @@ mod vecpool_tests {
 		let mut pool = Pool::new();
 
 		for i in (0..10).step_by(2) {
-			pool.push_back(TestTransaction::new(i, i));
+			pool.push_back(TestTransaction::new(i, i)).unwrap();
 		}
 
 		pool.iter_mut().for_each(|mut t| t.signature += 1);
@@ -301,16 +1030,395 @@ mod vecpool_tests {
 	}
 
 	#[test]
-	#[should_panic(expected = "Attempted to insert invalid transaction to the pool.")]
+	fn push_batch_drops_invalid() {
+		let mut pool = Pool::new();
+		let txs = (0..6)
+			.map(|i| TestTransaction::new(i, i))
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			pool.push_batch(&txs),
+			(0..6)
+				.map(|i| if i % 2 == 0 {
+					Ok(())
+				} else {
+					Err(PoolError::InvalidSignature)
+				})
+				.collect::<Vec<_>>(),
+		);
+		assert_eq!(pool.iter().map(|t| t.id).collect::<Vec<_>>(), vec![0, 2, 4]);
+	}
+
+	#[test]
 	fn insert_invalid_will_fail() {
 		let mut pool = Pool::new();
-		pool.push_back(TestTransaction::new(1, 0));
+		assert_eq!(
+			pool.push_back(TestTransaction::new(1, 0)),
+			Err(PoolError::InvalidSignature)
+		);
+		assert_eq!(pool.len(), 0);
 	}
 
 	#[test]
-	#[should_panic(expected = "Attempted to insert invalid transaction to the pool.")]
 	fn insert_invalid_will_fail_2() {
 		let mut pool = Pool::new();
-		pool.insert(0, TestTransaction::new(1, 0));
+		assert_eq!(
+			pool.insert(0, TestTransaction::new(1, 0)),
+			Err(PoolError::InvalidSignature)
+		);
+		assert_eq!(pool.len(), 0);
+	}
+
+	#[test]
+	fn bounded_pool_rejects_when_full() {
+		let mut pool = Pool::bounded(2);
+		assert_eq!(pool.limit(), Some(2));
+		assert_eq!(
+			pool.push_batch(&[
+				TestTransaction::new(0, 0),
+				TestTransaction::new(2, 0),
+				TestTransaction::new(4, 9)
+			]),
+			vec![Ok(()), Ok(()), Err(PoolError::PoolFull)]
+		);
+		assert_eq!(
+			pool.insert(0, TestTransaction::new(6, 9)),
+			Err(PoolError::PoolFull)
+		);
+		assert_eq!(pool.iter().map(|t| t.id).collect::<Vec<_>>(), vec![0, 2]);
+	}
+}
+
+#[cfg(test)]
+mod indexedpool_tests {
+	use super::{vecpool_tests::TestTransaction, *};
+
+	type Pool = IndexedPool<TestTransaction>;
+
+	fn ids(pool: &Pool) -> Vec<u32> {
+		pool.iter().map(|t| t.id).collect()
+	}
+
+	#[test]
+	fn lookup_by_id_works() {
+		let mut pool = Pool::new();
+		for i in (0..10).step_by(2) {
+			pool.push_back(TestTransaction::new(i, i)).unwrap();
+		}
+
+		assert_eq!(pool.get_by_id(6), Some(&TestTransaction::new(6, 6)));
+		assert_eq!(pool.get_by_id(7), None);
+
+		assert_eq!(pool.remove_by_id(6), Some(TestTransaction::new(6, 6)));
+		assert_eq!(pool.remove_by_id(6), None);
+		assert_eq!(ids(&pool), vec![0, 2, 4, 8]);
+	}
+
+	#[test]
+	fn preserves_insertion_order() {
+		let mut pool = Pool::new();
+		for i in (0..10).step_by(2) {
+			pool.push_back(TestTransaction::new(i, 10 - i)).unwrap();
+		}
+
+		// removing and pushing back moves a transaction to the end, regardless of its priority.
+		let tx = pool.remove_by_id(2).unwrap();
+		pool.push_back(tx).unwrap();
+		assert_eq!(ids(&pool), vec![0, 4, 6, 8, 2]);
+
+		assert_eq!(pool.get(|t| t.id == 8).map(|(i, _)| i), Some(3));
+		assert_eq!(pool.remove_at(1).id, 4);
+		pool.insert(1, TestTransaction::new(10, 0)).unwrap();
+		assert_eq!(ids(&pool), vec![0, 10, 6, 8, 2]);
+		assert_eq!(pool.get_by_id(10).map(|t| t.signature), Some(0));
+		assert_eq!(
+			pool.destruct()
+				.into_iter()
+				.map(|t| t.id)
+				.collect::<Vec<_>>(),
+			vec![0, 10, 6, 8, 2]
+		);
+	}
+
+	#[test]
+	fn bounded_pool_evicts_lowest_priority() {
+		let mut pool = Pool::bounded(3);
+		pool.push_back(TestTransaction::new(0, 5)).unwrap();
+		pool.push_back(TestTransaction::new(2, 1)).unwrap();
+		pool.push_back(TestTransaction::new(4, 7)).unwrap();
+		assert_eq!(pool.dropped(), 0);
+
+		// 2 is the lowest.
+		pool.push_back(TestTransaction::new(6, 3)).unwrap();
+		assert_eq!(ids(&pool), vec![0, 4, 6]);
+		assert_eq!(pool.get_by_id(2), None);
+
+		// a new transaction with the lowest priority is not admitted, and nothing is evicted.
+		assert_eq!(
+			pool.push_back(TestTransaction::new(8, 3)),
+			Err(PoolError::PoolFull)
+		);
+		assert_eq!(
+			pool.insert(0, TestTransaction::new(8, 2)),
+			Err(PoolError::PoolFull)
+		);
+		assert_eq!(ids(&pool), vec![0, 4, 6]);
+		assert_eq!(pool.dropped(), 1);
+
+		pool.push_back(TestTransaction::new(10, 9)).unwrap();
+		assert_eq!(ids(&pool), vec![0, 4, 10]);
+		pool.insert(0, TestTransaction::new(12, 6)).unwrap();
+		assert_eq!(ids(&pool), vec![12, 4, 10]);
+		assert_eq!(pool.len(), 3);
+		assert_eq!(pool.dropped(), 3);
+	}
+
+	#[test]
+	fn bounded_pool_evicts_most_recent_among_equals() {
+		let mut pool = Pool::bounded(3);
+		pool.push_back(TestTransaction::new(0, 3)).unwrap();
+		pool.push_back(TestTransaction::new(2, 3)).unwrap();
+		pool.push_back(TestTransaction::new(4, 5)).unwrap();
+
+		pool.push_back(TestTransaction::new(6, 4)).unwrap();
+		assert_eq!(ids(&pool), vec![0, 4, 6]);
+		assert_eq!(pool.dropped(), 1);
+
+		let mut empty = Pool::bounded(0);
+		assert_eq!(
+			empty.push_batch(&[TestTransaction::new(0, 1)]),
+			vec![Err(PoolError::PoolFull)]
+		);
+		assert_eq!(empty.len(), 0);
+	}
+
+	#[test]
+	fn duplicate_ids_are_rejected() {
+		let mut pool = Pool::new();
+		pool.push_back(TestTransaction::new(0, 5)).unwrap();
+		pool.push_back(TestTransaction::new(2, 5)).unwrap();
+
+		// regardless of the priority.
+		assert_eq!(
+			pool.push_back(TestTransaction::new(0, 6)),
+			Err(PoolError::Duplicate)
+		);
+		assert_eq!(
+			pool.insert(0, TestTransaction::new(2, 6)),
+			Err(PoolError::Duplicate)
+		);
+		assert_eq!(
+			pool.push_batch(&[TestTransaction::new(4, 1), TestTransaction::new(4, 2)]),
+			vec![Ok(()), Err(PoolError::Duplicate)]
+		);
+		assert_eq!(ids(&pool), vec![0, 2, 4]);
+		assert_eq!(pool.get_by_id(0).map(|t| t.signature), Some(5));
+		assert_eq!(pool.get_by_id(4).map(|t| t.signature), Some(1));
+		assert_eq!(pool.dropped(), 0);
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "Transaction ids must be unique.")]
+	fn duplicate_ids_are_a_bug_in_conversion() {
+		let _ = Pool::from(vec![TestTransaction::new(0, 1), TestTransaction::new(0, 2)]);
+	}
+
+	#[test]
+	fn extract_keeps_order_and_bound() {
+		let mut pool = Pool::bounded(4);
+		for i in (0..8).step_by(2) {
+			pool.push_back(TestTransaction::new(i, i)).unwrap();
+		}
+
+		let extracted = pool.extract(|t| t.id % 4 == 0);
+		assert_eq!(
+			extracted.iter().map(|t| t.id).collect::<Vec<_>>(),
+			vec![0, 4]
+		);
+		assert_eq!(ids(&pool), vec![2, 6]);
+		assert_eq!(pool.get_by_id(4), None);
+		assert_eq!(pool.limit(), Some(4));
+	}
+
+	#[test]
+	fn push_batch_verifies_in_parallel() {
+		use crate::types::transaction_generator;
+
+		let mut txs = transaction_generator::random_transfers(MIN_VERIFY_CHUNK * 8)
+			.into_iter()
+			.map(|(tx, _, _)| tx)
+			.collect::<Vec<_>>();
+		txs[10].signature.1 = txs[11].signature.1;
+		txs[300].tip = 1;
+
+		let mut pool = IndexedPool::new();
+		let outcome = pool.push_batch(&txs);
+		assert_eq!(outcome[10], Err(PoolError::InvalidSignature));
+		assert_eq!(outcome[300], Err(PoolError::InvalidSignature));
+		assert_eq!(outcome.iter().filter(|o| o.is_ok()).count(), txs.len() - 2);
+
+		assert_eq!(pool.len(), txs.len() - 2);
+		assert!(pool.get_by_id(10).is_none());
+		assert!(pool.get_by_id(11).is_some());
+		assert_eq!(pool.iter().nth(10).map(|tx| tx.id), Some(11));
+	}
+
+	#[test]
+	fn parallel_and_single_verification_agree() {
+		use crate::types::{transaction_generator, VerifiableTransaction};
+
+		let mut txs = transaction_generator::random_transfers(MIN_VERIFY_CHUNK * 8)
+			.into_iter()
+			.map(|(tx, _, _)| tx)
+			.collect::<Vec<_>>();
+		txs[0].nonce = 1;
+		txs[MIN_VERIFY_CHUNK].signature.0 = txs[1].signature.0;
+		txs[MIN_VERIFY_CHUNK * 8 - 1].tip = 1;
+		let single = txs.iter().map(|tx| tx.verify()).collect::<Vec<_>>();
+		assert_eq!(single.iter().filter(|ok| !**ok).count(), 3);
+
+		// the verification threads are reused across batches, and by concurrent callers.
+		thread::scope(|s| {
+			for _ in 0..4 {
+				s.spawn(|| assert_eq!(verify_parallel(&txs), single));
+			}
+		});
+		assert_eq!(verify_parallel(&txs), single);
+	}
+
+	#[test]
+	fn tip_is_signed() {
+		use crate::types::{Transaction, VerifiableTransaction};
+		use primitives::testing;
+		use runtime::{balances, OuterCall};
+
+		let origin = testing::alice();
+		let call = OuterCall::Balances(balances::Call::Transfer(testing::bob().public(), 10));
+		let signature = origin.sign(&Transaction::signing_payload(&call, 0, 5));
+		let mut tx = Transaction::new_with_nonce(0, call, 0, 5, origin.public(), signature);
+		assert!(tx.verify());
+		assert_eq!(tx.priority(), 5);
+
+		tx.tip = 6;
+		assert!(!tx.verify());
+	}
+}
+
+#[cfg(test)]
+mod readypool_tests {
+	use super::*;
+	use crate::types::Transaction;
+	use primitives::{testing, Pair};
+	use runtime::{balances, OuterCall};
+
+	type Pool = ReadyPool<Transaction>;
+
+	fn tx(id: TransactionId, origin: &Pair, nonce: Nonce, tip: Balance) -> Transaction {
+		let call = OuterCall::Balances(balances::Call::Transfer(testing::dave().public(), 10));
+		let signature = origin.sign(&Transaction::signing_payload(&call, nonce, tip));
+		Transaction::new_with_nonce(id, call, nonce, tip, origin.public(), signature)
+	}
+
+	fn ids(txs: Vec<&Transaction>) -> Vec<TransactionId> {
+		txs.into_iter().map(|tx| tx.id).collect()
+	}
+
+	#[test]
+	fn future_is_promoted() {
+		let rt = SequentialRuntime::default();
+		let mut pool = Pool::new();
+		let alice = testing::alice();
+
+		assert_eq!(pool.submit(&rt, tx(2, &alice, 2, 0)), Ok(Submitted::Future));
+		assert_eq!(pool.submit(&rt, tx(1, &alice, 1, 0)), Ok(Submitted::Future));
+		assert_eq!((pool.ready_len(), pool.future_len()), (0, 2));
+		assert!(pool.ready().is_empty());
+
+		assert_eq!(pool.submit(&rt, tx(0, &alice, 0, 0)), Ok(Submitted::Ready));
+		assert_eq!((pool.ready_len(), pool.future_len()), (3, 0));
+		assert_eq!(ids(pool.ready()), vec![0, 1, 2]);
+	}
+
+	#[test]
+	fn same_nonce_is_replaced_by_higher_tip() {
+		let rt = SequentialRuntime::default();
+		let mut pool = Pool::new();
+		let alice = testing::alice();
+
+		assert_eq!(pool.submit(&rt, tx(0, &alice, 0, 5)), Ok(Submitted::Ready));
+		assert_eq!(
+			pool.submit(&rt, tx(1, &alice, 0, 5)),
+			Err(Rejected::Underpriced)
+		);
+		assert_eq!(pool.submit(&rt, tx(2, &alice, 0, 6)), Ok(Submitted::Ready));
+
+		assert_eq!(pool.submit(&rt, tx(3, &alice, 3, 5)), Ok(Submitted::Future));
+		assert_eq!(
+			pool.submit(&rt, tx(4, &alice, 3, 1)),
+			Err(Rejected::Underpriced)
+		);
+		assert_eq!(pool.submit(&rt, tx(5, &alice, 3, 9)), Ok(Submitted::Future));
+
+		assert_eq!(pool.len(), 2);
+		assert_eq!(ids(pool.ready()), vec![2]);
+	}
+
+	#[test]
+	fn invalid_and_stale_are_rejected() {
+		let rt = SequentialRuntime::default();
+		let mut pool = Pool::new();
+		let alice = testing::alice();
+		NonceOf::write(&rt, alice.public(), 2).unwrap();
+
+		let mut tampered = tx(0, &alice, 2, 1);
+		tampered.tip = 100;
+		assert_eq!(pool.submit(&rt, tampered), Err(Rejected::Invalid));
+		assert_eq!(pool.submit(&rt, tx(1, &alice, 1, 0)), Err(Rejected::Stale));
+		assert_eq!(pool.submit(&rt, tx(2, &alice, 2, 0)), Ok(Submitted::Ready));
+		assert_eq!(pool.len(), 1);
+	}
+
+	#[test]
+	fn prune_drops_included_and_promotes() {
+		let rt = SequentialRuntime::default();
+		let mut pool = Pool::new();
+		let alice = testing::alice();
+		let bob = testing::bob();
+
+		pool.submit(&rt, tx(0, &alice, 0, 0)).unwrap();
+		pool.submit(&rt, tx(1, &alice, 1, 0)).unwrap();
+		pool.submit(&rt, tx(3, &alice, 3, 0)).unwrap();
+		pool.submit(&rt, tx(4, &alice, 4, 0)).unwrap();
+		pool.submit(&rt, tx(10, &bob, 0, 0)).unwrap();
+
+		// alice's first three transactions are included, one of which is not in the pool.
+		NonceOf::write(&rt, alice.public(), 3).unwrap();
+		assert_eq!(pool.prune(&rt), 2);
+		assert_eq!((pool.ready_len(), pool.future_len()), (3, 0));
+		assert_eq!(pool.submit(&rt, tx(2, &alice, 2, 0)), Err(Rejected::Stale));
+
+		// bob's only transaction is included.
+		NonceOf::write(&rt, bob.public(), 1).unwrap();
+		assert_eq!(pool.prune(&rt), 1);
+		assert_eq!(ids(pool.ready()), vec![3, 4]);
+	}
+
+	#[test]
+	fn ready_is_ordered_by_tip_and_nonce() {
+		let rt = SequentialRuntime::default();
+		let mut pool = Pool::new();
+		let alice = testing::alice();
+		let bob = testing::bob();
+		let eve = testing::eve();
+
+		pool.submit(&rt, tx(0, &alice, 0, 1)).unwrap();
+		pool.submit(&rt, tx(1, &alice, 1, 10)).unwrap();
+		pool.submit(&rt, tx(2, &bob, 0, 5)).unwrap();
+		pool.submit(&rt, tx(3, &eve, 0, 1)).unwrap();
+		pool.submit(&rt, tx(4, &eve, 2, 100)).unwrap();
+
+		// alice's second transaction must wait for her first one, and eve's last one is not ready.
+		assert_eq!(ids(pool.ready()), vec![2, 0, 1, 3]);
 	}
 }
@@ -1,6 +1,6 @@
 use crate::{State, *};
 use runtime::*;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use types::*;
 
 const LOG_TARGET: &'static str = "seq-exec";
@@ -16,29 +16,40 @@ pub struct SequentialExecutor {
 
 impl SequentialExecutor {
 	pub fn new() -> Self {
-		let id = std::thread::current().id().as_u64().into();
 		let state = State::new().as_arc();
-		let runtime = SequentialRuntime::new(state, id);
+		let runtime = SequentialRuntime::new(state, 0);
 		Self { runtime }
 	}
 
-	fn apply_transaction(&self, transactions: Vec<Transaction>) {
-		let mut outcomes: Vec<RuntimeDispatchSuccess> = Vec::with_capacity(transactions.len());
-		for tx in transactions {
-			let call = tx.function;
+	/// Apply the given transactions in order, until the `deadline` passes.
+	///
+	/// Returns the outcome of each of the applied transactions. The only possible error is a bad
+	/// nonce, in which case nothing is written.
+	fn apply_transaction(
+		&self,
+		transactions: &[Transaction],
+		deadline: Option<Instant>,
+	) -> Vec<RuntimeDispatchResult> {
+		let mut outcomes = Vec::with_capacity(transactions.len());
+		for tx in transactions.iter().take_while(|_| !is_expired(deadline)) {
+			let call = tx.function.clone();
 			let origin = tx.signature.0;
-			let ok = self.runtime.dispatch(call, origin)
-				.expect("Sequential execution cannot fail on execute. This will at most be Ok(LogicError(..))");
-			outcomes.push(ok);
+			outcomes.push(self.runtime.dispatch_transaction(call, origin, tx.nonce));
 		}
 
+		let successes = outcomes
+			.iter()
+			.filter_map(|outcome| outcome.ok())
+			.collect::<Vec<_>>();
 		log!(
 			info,
-			"Applied {} transactions, {} were ok {} were error.",
+			"Applied {} transactions, {} were ok {} were error, {} had a bad nonce.",
 			outcomes.len(),
-			outcomes.ok_count(),
-			outcomes.logic_error_count(),
+			successes.ok_count(),
+			successes.logic_error_count(),
+			outcomes.len() - successes.len(),
 		);
+		outcomes
 	}
 }
 
@@ -46,31 +57,74 @@ impl Executor for SequentialExecutor {
 	fn author_block(
 		&mut self,
 		initial_transactions: Vec<Transaction>,
-	) -> (StateMap, Block, Duration) {
+	) -> Result<(StateMap, Block, AuthoringStats), ExecutorError> {
+		self.build_block(initial_transactions, Default::default())
+			.map(|(state, block, stats, _)| (state, block, stats))
+	}
+
+	fn build_block(
+		&mut self,
+		transactions: Vec<Transaction>,
+		limits: BlockLimits,
+	) -> Result<(StateMap, Block, AuthoringStats, Vec<Transaction>), ExecutorError> {
 		log!(
 			info,
 			"📕 Authoring block with {} transactions.",
-			initial_transactions.len(),
+			transactions.len(),
 		);
-		let start = std::time::Instant::now();
+		let start = Instant::now();
+		let (mut transactions, mut rest) = limits.split(transactions);
 		// simply apply the transactions, ony by fucking one.
-		self.apply_transaction(initial_transactions.clone());
-		(
+		let outcomes = self.apply_transaction(&transactions, limits.deadline_from(start));
+		let mut unexecuted = transactions.split_off(outcomes.len());
+		let mut logic_errors = 0;
+		let mut included = Vec::with_capacity(transactions.len());
+		// the ones with a bad nonce wrote nothing, and are left out of the block.
+		for (tx, outcome) in transactions.into_iter().zip(outcomes) {
+			match outcome {
+				Ok(outcome) => {
+					if let RuntimeDispatchSuccess::LogicError(_) = outcome {
+						logic_errors += 1;
+					}
+					included.push(tx);
+				}
+				Err(_) => unexecuted.push(tx),
+			}
+		}
+		let stats = AuthoringStats {
+			logic_errors,
+			unexecuted: unexecuted.len(),
+			total: start.elapsed(),
+			..Default::default()
+		};
+		unexecuted.append(&mut rest);
+		Ok((
 			self.runtime.state.dump(),
-			initial_transactions.into(),
-			start.elapsed(),
-		)
+			included.into(),
+			stats,
+			unexecuted,
+		))
 	}
 
-	fn validate_block(&mut self, block: Block) -> (StateMap, Duration) {
+	fn validate_block(&mut self, block: Block) -> Result<(StateMap, Duration), ExecutorError> {
 		log!(
 			info,
 			"✅ Validating block with {} transactions. ",
 			block.transactions.len(),
 		);
-		let start = std::time::Instant::now();
-		self.apply_transaction(block.transactions);
-		(self.runtime.state.dump(), start.elapsed())
+		let start = Instant::now();
+		crate::pool::verify_block(&block.transactions)?;
+		let outcomes = self.apply_transaction(&block.transactions, None);
+		if let Some((tx, _)) = block
+			.transactions
+			.iter()
+			.zip(outcomes)
+			.find(|(_, outcome)| outcome.is_err())
+		{
+			log!(warn, "Rejecting block due to bad nonce of {:?}.", tx);
+			return Err(InvalidBlock::BadNonce(tx.id).into());
+		}
+		Ok((self.runtime.state.dump(), start.elapsed()))
 	}
 
 	fn clean(&mut self) {
@@ -98,7 +152,7 @@ mod tests {
 
 		transaction_generator::endow_account(testing::alice().public(), &executor.runtime, 100);
 
-		let (state, block, _) = executor.author_block(transactions);
+		let (state, block, _) = executor.author_block(transactions).unwrap();
 		assert_eq!(block.transactions.len(), 2);
 		assert_eq!(
 			state
@@ -135,11 +189,11 @@ mod tests {
 		let transactions = transaction_generator::simple_alice_bob_dave();
 		transaction_generator::endow_account(testing::alice().public(), &executor.runtime, 100);
 
-		let (_, block, _) = executor.author_block(transactions);
+		let (_, block, _) = executor.author_block(transactions).unwrap();
 		executor.clean();
 
 		transaction_generator::endow_account(testing::alice().public(), &executor.runtime, 100);
-		let (validation_state, _) = executor.validate_block(block);
+		let (validation_state, _) = executor.validate_block(block).unwrap();
 		assert_eq!(
 			validation_state
 				.get(&<BalanceOf<SequentialRuntime>>::key_for(
@@ -187,4 +241,80 @@ mod tests {
 				.0
 		);
 	}
+
+	#[test]
+	fn can_build_block_within_limits() {
+		let mut executor = SequentialExecutor::new();
+		let transactions = transaction_generator::simple_alice_bob_dave();
+		transaction_generator::endow_account(testing::alice().public(), &executor.runtime, 100);
+
+		// each transfer weighs 6.
+		let limits = BlockLimits {
+			max_weight: Some(11),
+			..Default::default()
+		};
+		let (state, block, _, unexecuted) =
+			executor.build_block(transactions.clone(), limits).unwrap();
+		assert_eq!(block.transactions, transactions[..1].to_vec());
+		assert_eq!(unexecuted, transactions[1..].to_vec());
+		assert_eq!(
+			state
+				.get(&<BalanceOf<SequentialRuntime>>::key_for(
+					testing::alice().public()
+				))
+				.unwrap()
+				.data(),
+			AccountBalance::from(90).encode().into(),
+		);
+
+		// nothing is executed past the deadline.
+		let limits = BlockLimits {
+			deadline: Some(Duration::ZERO),
+			..Default::default()
+		};
+		let (_, block, stats, unexecuted) = executor.build_block(unexecuted, limits).unwrap();
+		assert!(block.transactions.is_empty());
+		assert_eq!(stats.unexecuted, 1);
+		assert_eq!(unexecuted, transactions[1..].to_vec());
+	}
+
+	#[test]
+	fn replayed_transactions_are_rejected() {
+		let mut executor = SequentialExecutor::new();
+		let transactions = transaction_generator::simple_alice_bob_dave();
+		transaction_generator::endow_account(testing::alice().public(), &executor.runtime, 100);
+
+		let (state, block, _) = executor.author_block(transactions.clone()).unwrap();
+		assert_eq!(block.transactions.len(), 2);
+
+		// the same transactions are left out of the next block, and nothing changes.
+		let (replay_state, replay, stats, unexecuted) = executor
+			.build_block(transactions.clone(), Default::default())
+			.unwrap();
+		assert!(replay.transactions.is_empty());
+		assert_eq!(stats.unexecuted, 2);
+		assert_eq!(unexecuted, transactions);
+		assert!(replay_state.state_eq(state));
+
+		// and a block that includes them again is rejected.
+		assert_eq!(
+			executor.validate_block(block),
+			Err(InvalidBlock::BadNonce(transactions[0].id).into())
+		);
+	}
+
+	#[test]
+	fn tampered_transactions_are_rejected() {
+		let mut executor = SequentialExecutor::new();
+		let transactions = transaction_generator::simple_alice_bob_dave();
+		transaction_generator::endow_account(testing::alice().public(), &executor.runtime, 100);
+
+		// the nonce is signed, so changing it after the fact invalidates the transaction.
+		let mut block: Block = transactions.clone().into();
+		block.transactions[1].nonce += 1;
+		assert_eq!(
+			executor.validate_block(block).map(|_| ()),
+			Err(InvalidBlock::BadSignature(transactions[1].id).into())
+		);
+	}
 }
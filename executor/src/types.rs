@@ -1,13 +1,14 @@
-use parity_scale_codec::Encode;
-use primitives::{ThreadId, TransactionId};
-use runtime::OuterCall;
+use parity_scale_codec::{Decode, Encode};
+use primitives::{AccountId, Balance, Nonce, ThreadId, TransactionId};
+use runtime::{Dispatchable, OuterCall, RuntimeDispatchSuccess, SequentialRuntime, Weight};
 use std::{
 	collections::BTreeMap,
 	fmt::{self, Debug, Formatter},
 	sync::mpsc::Sender,
+	time::{Duration, Instant},
 };
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
 /// A block of transaction.
 pub struct Block {
 	/// Transactions within the block.
@@ -20,12 +21,170 @@ impl From<Vec<Transaction>> for Block {
 	}
 }
 
+/// The limits of a block that is being built. See [`crate::Executor::build_block`].
+///
+/// Any limit that is not set is ignored.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct BlockLimits {
+	/// The time after which no new transaction is executed, since the start of the building.
+	pub deadline: Option<Duration>,
+	/// The maximum number of transactions in the block.
+	pub max_transactions: Option<usize>,
+	/// The maximum total weight of the transactions in the block.
+	pub max_weight: Option<Weight>,
+}
+
+impl BlockLimits {
+	/// Split `transactions` into the longest prefix that fits in the size and weight limits, and
+	/// the rest of them.
+	pub fn split(
+		&self,
+		mut transactions: Vec<Transaction>,
+	) -> (Vec<Transaction>, Vec<Transaction>) {
+		let max_weight = self.max_weight.unwrap_or(Weight::MAX);
+		let mut weight: Weight = 0;
+		let fits = transactions
+			.iter()
+			.take(self.max_transactions.unwrap_or(usize::MAX))
+			.take_while(|tx| {
+				weight = weight.saturating_add(
+					<OuterCall as Dispatchable<SequentialRuntime>>::weight(&tx.function),
+				);
+				weight <= max_weight
+			})
+			.count();
+		let rest = transactions.split_off(fits);
+		(transactions, rest)
+	}
+
+	/// The point in time at which the deadline passes, if building starts at `start`.
+	pub fn deadline_from(&self, start: Instant) -> Option<Instant> {
+		self.deadline.map(|deadline| start + deadline)
+	}
+}
+
+/// True if the given deadline is set and has passed.
+pub(crate) fn is_expired(deadline: Option<Instant>) -> bool {
+	deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// The reasons for which a block can be rejected in validation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InvalidBlock {
+	/// The given transaction accessed a key that was also accessed by a transaction assigned to
+	/// another thread.
+	Conflict(TransactionId),
+	/// The given transaction is neither tagged as executed by a bucket, nor as an orphan.
+	UnexpectedTag(TransactionId),
+	/// The nonce of the given transaction is not the nonce of its origin at that point.
+	BadNonce(TransactionId),
+	/// The signature of the given transaction is invalid.
+	BadSignature(TransactionId),
+}
+
+/// The reason why a transaction is not inserted into a [`crate::pool::TransactionPool`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PoolError {
+	/// The signature of the transaction is invalid.
+	InvalidSignature,
+	/// A transaction with the same id is already in the pool.
+	Duplicate,
+	/// The pool is full, and the transaction does not have a higher priority than any of the
+	/// ones in it.
+	PoolFull,
+}
+
+/// The reasons for which an executor can fail to carry out a task.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExecutorError {
+	/// The worker with the given id panicked while executing the task.
+	///
+	/// The executor is not usable anymore after this, and should be dropped.
+	WorkerPanicked(ThreadId),
+	/// The worker with the given id sent a message that is not expected at this point.
+	///
+	/// The executor is not usable anymore after this, and should be dropped.
+	UnexpectedMessage(ThreadId),
+	/// The worker with the given id received a message that it did not expect at this point.
+	///
+	/// The executor is not usable anymore after this, and should be dropped.
+	WorkerError(ThreadId),
+	/// The given transaction was not assigned to any of the workers by the distribution.
+	UnassignedTransaction(TransactionId),
+	/// The block is rejected in validation.
+	InvalidBlock(InvalidBlock),
+	/// A transaction is not accepted into the pool.
+	Pool(PoolError),
+}
+
+impl From<InvalidBlock> for ExecutorError {
+	fn from(why: InvalidBlock) -> Self {
+		Self::InvalidBlock(why)
+	}
+}
+
+impl From<PoolError> for ExecutorError {
+	fn from(why: PoolError) -> Self {
+		Self::Pool(why)
+	}
+}
+
+/// Statistics of a single worker thread while authoring a block.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct WorkerStats {
+	/// Number of transactions that the master distributed to this worker.
+	pub received: usize,
+	/// Number of transactions that this worker executed, including the ones forwarded to it by
+	/// other workers.
+	pub executed: usize,
+	/// Number of transactions that this worker forwarded to another worker or to the master.
+	pub forwarded: usize,
+	/// The core that this worker is pinned to, if any.
+	pub core: Option<crate::affinity::Core>,
+}
+
+/// Statistics of authoring a single block.
+///
+/// Executors that have no notion of workers or orphans leave the corresponding fields empty.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct AuthoringStats {
+	/// The statistics of each worker, by the id of the worker.
+	pub workers: BTreeMap<ThreadId, WorkerStats>,
+	/// The core that the master is pinned to, if any.
+	pub master_core: Option<crate::affinity::Core>,
+	/// Number of transactions that ended up in the orphan pool, including the pre-orphaned ones.
+	pub orphaned: usize,
+	/// Number of transactions that were sent to the orphan pool before the concurrent phase,
+	/// either because they accessed a hot key or because the distributer said so.
+	pub pre_orphaned: usize,
+	/// Number of orphans that were executed while the workers were still running.
+	pub pipelined_orphans: usize,
+	/// Number of transactions that were executed with a logical error.
+	pub logic_errors: usize,
+	/// Number of transactions that were not executed, either because the deadline passed or
+	/// because their nonce was bad at that point, and are left out of the block.
+	pub unexecuted: usize,
+	/// The time it took to distribute the transactions among the workers.
+	pub distribution: Duration,
+	/// The time of the concurrent phase, including the distribution.
+	pub concurrent_phase: Duration,
+	/// The time of the collection phase.
+	pub collection: Duration,
+	/// The time of the orphan phase.
+	pub orphan_phase: Duration,
+	/// The total wall-clock time of authoring the block.
+	pub total: Duration,
+}
+
 /// Status of a transaction.
 ///
 /// This is used to annotate the final status of a transaction.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Encode, Decode)]
 pub enum ExecutionTag {
 	/// Done by the given thread.
+	///
+	/// Within an authored block, this is the index of the bucket of the transaction instead. All
+	/// transactions in a bucket have been executed by the same thread.
 	Done(ThreadId),
 	/// Ended up being an orphan.
 	Orphan,
@@ -40,12 +199,14 @@ impl Default for ExecutionTag {
 }
 
 /// Execution status of a transaction.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Encode, Decode)]
 pub enum ExecutionStatus {
 	/// It has just been created.
 	Initial,
-	/// Has already been forwarded by one thread.
+	/// Has already been forwarded by at least one thread. See `Transaction::hops`.
 	Forwarded,
+	/// Has been handed over to an idle worker by its designated worker, without any conflict.
+	Stolen,
 }
 
 impl Default for ExecutionStatus {
@@ -55,7 +216,7 @@ impl Default for ExecutionStatus {
 }
 
 /// Opaque transaction type.
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
 pub struct Transaction {
 	/// The identifier of the transaction.
 	///
@@ -68,8 +229,20 @@ pub struct Transaction {
 	pub tag: ExecutionTag,
 	/// Execution status.
 	pub exec_status: ExecutionStatus,
+	/// The workers that forwarded this transaction, either to another worker or to master, in
+	/// order.
+	///
+	/// Within an authored block, these are bucket indices, same as the `Done(_)` tags.
+	pub hops: Vec<ThreadId>,
 	/// The function of the transaction. This should be executed by a runtime.
 	pub function: OuterCall,
+	/// The nonce of the origin that this transaction must be included with.
+	///
+	/// The executors only execute a transaction if this is the current nonce of its origin, and
+	/// bump it. Blocks with any other transaction are rejected in validation.
+	pub nonce: Nonce,
+	/// The tip offered by the origin, used to prioritize this transaction in the pool.
+	pub tip: Balance,
 	/// The signature of the transaction
 	pub signature: (primitives::AccountId, primitives::Signature),
 }
@@ -82,6 +255,14 @@ impl Transaction {
 	pub fn set_orphan(&mut self) {
 		self.tag = ExecutionTag::Orphan;
 	}
+
+	/// Forget everything about the execution of this transaction, so that it can go back to the
+	/// pool.
+	pub fn reset(&mut self) {
+		self.tag = ExecutionTag::NotExecuted;
+		self.exec_status = ExecutionStatus::Initial;
+		self.hops.clear();
+	}
 }
 
 /// An opaque transaction trait that can be verified.
@@ -90,14 +271,84 @@ pub trait VerifiableTransaction {
 	///
 	/// This should typically just check the signature.
 	fn verify(&self) -> bool;
+
+	/// Verify a batch of transactions, returning whether each of them is sane.
+	///
+	/// By default, each of them is verified separately.
+	fn verify_batch(txs: &[Self]) -> Vec<bool>
+	where
+		Self: Sized,
+	{
+		txs.iter().map(|tx| tx.verify()).collect()
+	}
+}
+
+/// A transaction that can be looked up by its id and ordered by its priority, as needed by
+/// `pool::IndexedPool`.
+pub trait IndexedTransaction {
+	/// The unique identifier of this transaction.
+	fn id(&self) -> TransactionId;
+
+	/// The priority of this transaction. Higher is better.
+	fn priority(&self) -> Balance;
+}
+
+/// A transaction that is ordered by a nonce of its sender, as needed by `pool::ReadyPool`.
+pub trait NoncedTransaction: IndexedTransaction {
+	/// The sender of this transaction.
+	fn sender(&self) -> AccountId;
+
+	/// The nonce of the sender that this transaction must be included with.
+	fn nonce(&self) -> Nonce;
+}
+
+impl NoncedTransaction for Transaction {
+	fn sender(&self) -> AccountId {
+		self.signature.0
+	}
+
+	fn nonce(&self) -> Nonce {
+		self.nonce
+	}
+}
+
+impl IndexedTransaction for Transaction {
+	fn id(&self) -> TransactionId {
+		self.id
+	}
+
+	fn priority(&self) -> Balance {
+		self.tip
+	}
 }
 
 impl VerifiableTransaction for Transaction {
 	fn verify(&self) -> bool {
 		let (origin, signature) = self.signature;
-		let payload = self.function.encode();
+		let payload = Self::signing_payload(&self.function, self.nonce, self.tip);
 		origin.verify(payload.as_ref(), &signature)
 	}
+
+	fn verify_batch(txs: &[Self]) -> Vec<bool> {
+		let payloads = txs
+			.iter()
+			.map(|tx| Self::signing_payload(&tx.function, tx.nonce, tx.tip))
+			.collect::<Vec<_>>();
+		let messages = payloads.iter().map(|p| p.as_ref()).collect::<Vec<_>>();
+		let signatures = txs.iter().map(|tx| tx.signature.1).collect::<Vec<_>>();
+		let origins = txs.iter().map(|tx| tx.signature.0).collect::<Vec<_>>();
+
+		if primitives::verify_batch(&messages, &signatures, &origins) {
+			vec![true; txs.len()]
+		} else {
+			// the batch does not tell which ones are invalid, so find them one by one.
+			messages
+				.iter()
+				.zip(txs)
+				.map(|(message, tx)| tx.signature.0.verify(message, &tx.signature.1))
+				.collect()
+		}
+	}
 }
 
 impl Debug for Transaction {
@@ -106,11 +357,17 @@ impl Debug for Transaction {
 			.field("id", &self.id)
 			.field("status", &self.tag)
 			.field("exec_status", &self.exec_status)
+			.field("hops", &self.hops)
+			.field("nonce", &self.nonce)
+			.field("tip", &self.tip)
 			.finish_non_exhaustive()
 	}
 }
 
 impl Transaction {
+	/// Create a new transaction with a zero nonce and no tip.
+	///
+	/// `signed_call` must be a signature over `Transaction::signing_payload(&call, 0, 0)`.
 	pub fn new(
 		id: TransactionId,
 		call: OuterCall,
@@ -122,10 +379,38 @@ impl Transaction {
 			function: call,
 			tag: ExecutionTag::NotExecuted,
 			exec_status: ExecutionStatus::Initial,
+			hops: vec![],
+			nonce: 0,
+			tip: 0,
 			signature: (origin, signed_call),
 		}
 	}
 
+	/// Create a new transaction with the given `nonce` that offers `tip`.
+	///
+	/// `signed_call` must be a signature over `Transaction::signing_payload(&call, nonce, tip)`.
+	pub fn new_with_nonce(
+		id: TransactionId,
+		call: OuterCall,
+		nonce: Nonce,
+		tip: Balance,
+		origin: primitives::AccountId,
+		signed_call: primitives::Signature,
+	) -> Self {
+		Self {
+			nonce,
+			tip,
+			..Self::new(id, call, origin, signed_call)
+		}
+	}
+
+	/// The payload that the origin of a transaction must sign.
+	///
+	/// The nonce is always signed, so that the transaction cannot be replayed with another one.
+	pub fn signing_payload(call: &OuterCall, nonce: Nonce, tip: Balance) -> Vec<u8> {
+		(call, nonce, tip).encode()
+	}
+
 	/// A test transfer from the given keypair to bob with the value of 999 and tx id of 99.
 	#[cfg(test)]
 	pub fn new_transfer(origin: primitives::Pair, id: TransactionId) -> Self {
@@ -135,7 +420,7 @@ impl Transaction {
 			testing::bob().public(),
 			999,
 		));
-		let signed_call = call.using_encoded(|payload| origin.sign(payload));
+		let signed_call = origin.sign(&Self::signing_payload(&call, 0, 0));
 		Self::new(id, call, origin.public(), signed_call)
 	}
 
@@ -145,37 +430,36 @@ impl Transaction {
 		const ID: u32 = 99;
 
 		let call = runtime::OuterCall::Balances(runtime::balances::Call::Transfer(dest, 999));
-		let signed_call = call.using_encoded(|payload| origin.sign(payload));
+		let signed_call = origin.sign(&Self::signing_payload(&call, 0, 0));
 		Self::new(ID, call, origin.public(), signed_call)
 	}
 }
 
+/// A message sent between the master and the workers.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Message {
+	/// The content of the message.
 	pub payload: MessagePayload,
+	/// The logical id of the sender.
 	pub from: ThreadId,
 }
 
 impl Message {
-	pub fn new_from_thread(payload: MessagePayload) -> Self {
-		let id = std::thread::current().id().as_u64().into();
-		Self { payload, from: id }
-	}
-}
-
-impl From<MessagePayload> for Message {
-	fn from(p: MessagePayload) -> Self {
-		Self::new_from_thread(p)
+	/// Create a new message with the given payload, sent by `from`.
+	pub fn new(payload: MessagePayload, from: ThreadId) -> Self {
+		Self { payload, from }
 	}
 }
 
 /// The type of the tasks that the master can order the worker to do.
 #[derive(Debug, Clone)]
 pub enum TaskType {
-	/// Authoring a new block.
-	Authoring,
+	/// Authoring a new block, with the given deadline after which no new transaction is executed.
+	Authoring(Option<Instant>),
 	/// Validating a block.
 	Validating,
+	/// Acknowledge with a `Synced` message right away. See `Master::sync`.
+	Sync,
 }
 
 #[derive(Debug, Clone)]
@@ -196,21 +480,41 @@ pub enum MessagePayload {
 	TransactionDistributionDone,
 	/// The outcome report of the initial phase of the authoring phase.
 	///
-	/// First inner values are the _executed_, _forwarded_ and _orphaned_ count respectively.
-	AuthoringReport(usize, usize),
+	/// Inner values are the _executed_, _forwarded_ and _logic error_ count respectively.
+	AuthoringReport(usize, usize, usize),
 	/// Same as `AuthoringReport`, but for validation phase.
 	///
-	/// There is no need to report back anything, just tell the master that you are done.
-	ValidationReport,
+	/// Contains the reason why the block is invalid, if any. That is, the first transaction that
+	/// conflicted with the transactions of another thread, or that had a bad nonce.
+	ValidationReport(Option<InvalidBlock>),
 	/// Report the execution of a transaction by a worker back to master.
 	///
 	/// This should only be used if the thread executing a transaction is not the original owner of
-	/// the transaction.
-	WorkerExecuted(TransactionId),
-	/// Report an orphan transaction back to the master.
-	WorkerOrphan(TransactionId),
+	/// the transaction. Also contains the workers that forwarded the transaction, in order, and
+	/// whether the last of them forwarded it due to a conflict, as opposed to handing it over to
+	/// an idle worker.
+	WorkerExecuted(TransactionId, RuntimeDispatchSuccess, Vec<ThreadId>, bool),
+	/// Report an orphan transaction back to the master, along with the workers that attempted it,
+	/// in order. The last one is the sender.
+	WorkerOrphan(TransactionId, Vec<ThreadId>),
+	/// Report a transaction that was not executed, because either the deadline passed or its
+	/// nonce was bad, along with the workers that forwarded it, in order.
+	///
+	/// The transaction is left out of the block.
+	WorkerSkipped(TransactionId, Vec<ThreadId>),
+	/// A worker is signaling to another worker that it has nothing left to execute.
+	///
+	/// The receiver may respond by handing over one of its transactions that it has not executed
+	/// yet, as a `Transaction` message.
+	Idle,
 	/// Master is signaling the end of the task.
 	TaskDone,
+	/// A worker acknowledging a `Sync` task.
+	Synced,
+	/// A worker signaling that it received a message that it did not expect.
+	///
+	/// The worker abandons its current task, if any, and waits for the next one.
+	WorkerError,
 	/// Master is signaling the termination of the thread.
 	///
 	/// This should be followed by the worker thread exiting and the master Joining.
@@ -239,15 +543,61 @@ pub mod transaction_generator {
 	use super::*;
 	use primitives::{testing::*, *};
 	use runtime::balances::BalanceOf;
+	use std::collections::HashMap;
+
+	/// The nonces of the next transactions of each account, starting from zero.
+	///
+	/// Useful to build multiple transactions of the same account, that can all be included.
+	#[derive(Debug, Default, Clone)]
+	pub struct Nonces(HashMap<AccountId, Nonce>);
+
+	impl Nonces {
+		/// The nonce of the next transaction of `who`.
+		pub fn next(&mut self, who: AccountId) -> Nonce {
+			let nonce = self.0.entry(who).or_default();
+			*nonce += 1;
+			*nonce - 1
+		}
+	}
+
+	/// Build a transaction of `call` with `nonce` and no tip, signed by `origin`.
+	pub fn sign(id: TransactionId, call: OuterCall, nonce: Nonce, origin: &Pair) -> Transaction {
+		sign_with_tip(id, call, nonce, 0, origin)
+	}
+
+	/// Same as [`sign`], offering `tip`.
+	pub fn sign_with_tip(
+		id: TransactionId,
+		call: OuterCall,
+		nonce: Nonce,
+		tip: Balance,
+		origin: &Pair,
+	) -> Transaction {
+		let signature = origin.sign(&Transaction::signing_payload(&call, nonce, tip));
+		Transaction::new_with_nonce(id, call, nonce, tip, origin.public(), signature)
+	}
 
-	/// Build a transfer from `origin` to `to`.
+	/// Build a transfer from `origin` to `to`, with a zero nonce.
 	///
 	/// Note that the id must be unique from the call site.
 	pub fn build_transfer(id: TransactionId, origin: Pair, to: Public) -> Transaction {
+		build_transfer_with_nonce(id, origin, to, 0)
+	}
+
+	/// Same as [`build_transfer`], with the given nonce.
+	pub fn build_transfer_with_nonce(
+		id: TransactionId,
+		origin: Pair,
+		to: Public,
+		nonce: Nonce,
+	) -> Transaction {
 		use runtime::balances::Call;
-		let call = OuterCall::Balances(Call::Transfer(to, 10));
-		let signature = call.using_encoded(|payload| origin.sign(payload));
-		Transaction::new(id, call, origin.public(), signature)
+		sign(
+			id,
+			OuterCall::Balances(Call::Transfer(to, 10)),
+			nonce,
+			&origin,
+		)
 	}
 
 	/// Give `who` a large amount of balance.
@@ -272,8 +622,8 @@ pub mod transaction_generator {
 	}
 
 	pub fn simple_alice_bob_dave() -> Vec<Transaction> {
-		let tx1 = build_transfer(1, alice(), bob().public());
-		let tx2 = build_transfer(2, alice(), dave().public());
+		let tx1 = build_transfer_with_nonce(1, alice(), bob().public(), 0);
+		let tx2 = build_transfer_with_nonce(2, alice(), dave().public(), 1);
 
 		vec![tx1, tx2]
 	}
@@ -282,7 +632,7 @@ pub mod transaction_generator {
 	///
 	/// This is pretty useful to demonstrate a common use case with orphans as well.
 	///
-	/// Accounts will be endowed.
+	/// The transfers of each account carry consecutive nonces, starting from zero.
 	pub fn bank(
 		members: usize,
 		transfers: usize,
@@ -291,6 +641,7 @@ pub mod transaction_generator {
 		use rand::seq::SliceRandom;
 
 		let accounts = (0..members).map(|_| testing::random()).collect::<Vec<_>>();
+		let mut nonces = Nonces::default();
 
 		let txs = (0..transfers)
 			.map(|i| {
@@ -301,9 +652,8 @@ pub mod transaction_generator {
 					to.public(),
 					amount,
 				));
-
-				let signed_call = call.using_encoded(|payload| from.sign(payload));
-				Transaction::new(i as TransactionId, call, from.public(), signed_call)
+				let nonce = nonces.next(from.public());
+				sign(i as TransactionId, call, nonce, from)
 			})
 			.collect::<Vec<Transaction>>();
 
@@ -1,3 +1,4 @@
+use parity_scale_codec::{Decode, Encode};
 use std::fmt::{Debug, Formatter, Result as FmtResult};
 
 // re-export crypto stuff.
@@ -14,6 +15,8 @@ pub type ThreadId = u64;
 pub type AccountId = Public;
 /// The balance type.
 pub type Balance = u128;
+/// The nonce of an account, i.e. the number of its transactions that have been included.
+pub type Nonce = u32;
 /// Identifier of a transaction.
 pub type TransactionId = u32;
 
@@ -94,7 +97,7 @@ impl<T: ?Sized + AsRef<[u8]>> HexDisplayExt for T {
 }
 
 /// A state key.
-#[derive(Clone, Eq, PartialEq, Hash, Default, Ord, PartialOrd)]
+#[derive(Clone, Eq, PartialEq, Hash, Default, Ord, PartialOrd, Encode, Decode)]
 pub struct StateKey(pub Vec<u8>);
 
 impl From<Vec<u8>> for StateKey {
@@ -116,7 +119,7 @@ impl Debug for StateKey {
 }
 
 /// A state value.
-#[derive(Clone, Eq, PartialEq, Hash, Default)]
+#[derive(Clone, Eq, PartialEq, Hash, Default, Encode, Decode)]
 pub struct StateValue(pub Vec<u8>);
 
 impl From<Vec<u8>> for StateValue {
@@ -140,7 +143,6 @@ impl Debug for StateValue {
 #[cfg(test)]
 mod primitive_tests {
 	use super::*;
-	use parity_scale_codec::{Decode, Encode};
 
 	#[test]
 	fn public_is_codec() {
@@ -149,4 +151,87 @@ mod primitive_tests {
 		assert_eq!(encoded, public.as_ref());
 		assert_eq!(Public::decode(&mut &*encoded).unwrap(), public);
 	}
+
+	#[test]
+	fn signature_is_codec() {
+		let signature = testing::alice().sign(b"Hello");
+		let encoded = signature.encode();
+		assert_eq!(encoded.len(), 64);
+		assert_eq!(Signature::decode(&mut &*encoded).unwrap(), signature);
+	}
+
+	#[test]
+	fn verify_batch_works() {
+		let pairs = (0..8).map(|_| testing::random()).collect::<Vec<_>>();
+		let messages = (0..8u8).map(|i| vec![i; 10]).collect::<Vec<_>>();
+		let mut signatures = pairs
+			.iter()
+			.zip(messages.iter())
+			.map(|(p, m)| p.sign(m))
+			.collect::<Vec<_>>();
+		let publics = pairs.iter().map(|p| p.public()).collect::<Vec<_>>();
+		let messages = messages.iter().map(|m| m.as_ref()).collect::<Vec<_>>();
+
+		assert!(verify_batch(&messages, &signatures, &publics));
+		assert!(verify_batch(&[], &[], &[]));
+
+		signatures[3] = signatures[4];
+		assert!(!verify_batch(&messages, &signatures, &publics));
+	}
+
+	#[test]
+	fn single_and_batch_verification_agree() {
+		use curve25519_dalek::{
+			constants::{ED25519_BASEPOINT_POINT, EIGHT_TORSION},
+			scalar::Scalar,
+			traits::IsIdentity,
+		};
+		use sha2::{Digest, Sha512};
+
+		// a public key with a small order component, whose signatures only satisfy the
+		// cofactored equation, unless `k` happens to be a multiple of 8.
+		let message = b"Hello";
+		let secret = Scalar::from(42u64);
+		let public = Public::from_bytes(
+			(secret * ED25519_BASEPOINT_POINT + EIGHT_TORSION[1])
+				.compress()
+				.as_bytes(),
+		)
+		.unwrap();
+		let signature = (1u64..)
+			.find_map(|nonce| {
+				let r = (Scalar::from(nonce) * ED25519_BASEPOINT_POINT).compress();
+				let k = Scalar::from_hash(
+					Sha512::new()
+						.chain(r.as_bytes())
+						.chain(public.as_ref())
+						.chain(message),
+				);
+				if (k * EIGHT_TORSION[1]).is_identity() {
+					return None;
+				}
+				let s = Scalar::from(nonce) + k * secret;
+				let bytes = [r.to_bytes(), s.to_bytes()].concat();
+				Some(Signature::decode(&mut &*bytes).unwrap())
+			})
+			.unwrap();
+
+		let alice = testing::alice();
+		let other = alice.sign(b"World");
+		assert!(public.verify(message, &signature));
+		assert!(verify_batch(&[message], &[signature], &[public]));
+		assert!(verify_batch(
+			&[b"World", message],
+			&[other, signature],
+			&[alice.public(), public]
+		));
+
+		// and both reject it once tampered with.
+		assert!(!public.verify(b"World", &signature));
+		assert!(!verify_batch(
+			&[b"World", b"World"],
+			&[other, signature],
+			&[alice.public(), public]
+		));
+	}
 }
@@ -89,6 +89,7 @@ decl_tx! {
 			<BalanceOf<R>>::key_for(dest.clone())
 		]
 	)]
+	#[weight = 6]
 	fn transfer(runtime, origin, dest: AccountId, value: Balance) {
 		// If we fail at this step, it is fine. We have not written anything yet.
 		let mut old_balance =
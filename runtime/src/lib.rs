@@ -1,13 +1,21 @@
 use logging::log;
 use parity_scale_codec::{Decode, Encode};
 use primitives::*;
-use state::{GenericState, TaintState};
-use std::{cell::RefCell, collections::HashMap, sync::Arc};
+use state::{
+	multi_version::{MultiVersionRead, MultiVersionState, ReadDescriptor, TxIndex},
+	GenericState, TaintState,
+};
+use std::{
+	cell::{Cell, RefCell},
+	collections::HashMap,
+	sync::Arc,
+};
 
 pub mod balances;
 mod macros;
 pub mod staking;
 pub mod state_generator;
+pub mod system;
 
 // re-export paste for macros.
 #[doc(hidden)]
@@ -46,6 +54,8 @@ pub enum DispatchError {
 	Tainted(ThreadId, bool),
 	/// The transaction had a logical error.
 	LogicError(&'static str),
+	/// The nonce of the transaction is not the given nonce of its origin.
+	BadNonce(Nonce),
 }
 
 use count_of::CountOf;
@@ -61,6 +71,10 @@ pub enum RuntimeDispatchSuccess {
 pub enum RuntimeDispatchError {
 	/// The dispatch attempted at modifying a state key which has been tainted.
 	Tainted(ThreadId, bool),
+	/// The nonce of the transaction is not the given nonce of its origin.
+	///
+	/// Nothing is written in this case, and the transaction must not be included in a block.
+	BadNonce(Nonce),
 }
 
 /// The result of a dispatch.
@@ -71,7 +85,7 @@ pub(crate) type DispatchResult = Result<(), DispatchError>;
 /// The final result of the execution of a dispatch.
 ///
 /// This is similar to `DispatchResult`, except that a logical error is technically `Ok`.
-/// Only error is due to tainting.
+/// Only errors are due to tainting or a bad nonce.
 pub type RuntimeDispatchResult = Result<RuntimeDispatchSuccess, RuntimeDispatchError>;
 
 /// Conversion trait between `DispatchResult` and `RuntimeDispatchResult`.
@@ -87,10 +101,55 @@ impl ToRuntimeDispatchResult for DispatchResult {
 			Err(DispatchError::Tainted(whom, orphan)) => {
 				Err(RuntimeDispatchError::Tainted(whom, orphan))
 			}
+			Err(DispatchError::BadNonce(expected)) => Err(RuntimeDispatchError::BadNonce(expected)),
 		}
 	}
 }
 
+/// Dispatch `call` of `origin`, and call `discard` to drop its writes upon a logical error.
+///
+/// If a `nonce` is given, it is checked before the call, exactly like
+/// [`SequentialRuntime::dispatch_transaction`] does, and bumped afterwards, even upon a logical
+/// error, such that a transaction cannot be included twice either way.
+fn dispatch_with_nonce<R: ModuleRuntime + DispatchPermission>(
+	runtime: &R,
+	call: OuterCall,
+	origin: AccountId,
+	nonce: Option<Nonce>,
+	discard: impl FnOnce(),
+) -> DispatchResult {
+	if let Some(nonce) = nonce {
+		system::check_nonce(runtime, origin, nonce)?;
+	}
+	let result = <OuterCall as Dispatchable<R>>::dispatch::<R>(call, runtime, origin);
+	// the nonce is accessed already, hence the owner of any other key does not own it.
+	let result = match (nonce, result) {
+		(Some(_), Err(DispatchError::Tainted(whom, _))) => Err(DispatchError::Tainted(whom, true)),
+		(_, result) => result,
+	};
+	match result {
+		Err(DispatchError::Tainted(..)) | Err(DispatchError::BadNonce(_)) => return result,
+		Err(DispatchError::LogicError(_)) => discard(),
+		Ok(_) => (),
+	}
+	match nonce {
+		Some(_) => system::note_included(runtime, origin).and(result),
+		None => result,
+	}
+}
+
+/// The access hints of `call`, along with the nonce of its `origin`, which is accessed by any
+/// transaction.
+fn validate_transaction<R: ModuleRuntime>(
+	runtime: &R,
+	call: &OuterCall,
+	origin: AccountId,
+) -> ValidationResult {
+	let mut keys = <OuterCall as Dispatchable<R>>::validate(call, runtime, origin);
+	keys.push(system::NonceOf::<R>::key_for(origin));
+	keys
+}
+
 /// Extension trait to convert the result of storage operations to another result with
 /// `DispatchError` in the `Err` variant.
 trait UnwrapStorageOp<T> {
@@ -120,6 +179,14 @@ impl<T> UnwrapStorageOp<T> for Result<T, primitives::ThreadId> {
 /// The result of the validation of a dispatchable.
 pub type ValidationResult = Vec<primitives::Key>;
 
+/// The estimated cost of a dispatchable.
+///
+/// Measured in units of a single state read; a state write is worth two reads.
+pub type Weight = u64;
+
+/// The weight of a dispatchable that does not declare one.
+pub const DEFAULT_WEIGHT: Weight = 1;
+
 /// Anything that can be dispatched.
 ///
 /// Both the inner call and the outer call will be of type Dispatchable.
@@ -133,6 +200,11 @@ pub trait Dispatchable<R: ModuleRuntime> {
 	///
 	/// This should be cheap and return potentially some useful metadata about the dispatchable.
 	fn validate(&self, _: &R, _: AccountId) -> ValidationResult;
+
+	/// The estimated cost of dispatching this dispatchable.
+	///
+	/// Like `validate`, this should be cheap.
+	fn weight(&self) -> Weight;
 }
 
 /// Marker trait for those who have permission to dispatch.
@@ -196,13 +268,36 @@ impl ConcurrentRuntime {
 	///
 	/// Note that this will use a fresh new cache for the dispatch, and then
 	pub fn dispatch(&self, call: OuterCall, origin: AccountId) -> RuntimeDispatchResult {
+		self.dispatch_inner(call, origin, None)
+	}
+
+	/// Dispatch the call of a transaction of `origin` with `nonce`.
+	///
+	/// Same as [`Self::dispatch`], except that the nonce of `origin` must be `nonce`, and it is
+	/// bumped.
+	pub fn dispatch_transaction(
+		&self,
+		call: OuterCall,
+		origin: AccountId,
+		nonce: Nonce,
+	) -> RuntimeDispatchResult {
+		self.dispatch_inner(call, origin, Some(nonce))
+	}
+
+	fn dispatch_inner(
+		&self,
+		call: OuterCall,
+		origin: AccountId,
+		nonce: Option<Nonce>,
+	) -> RuntimeDispatchResult {
 		// the cache must always be empty at the beginning of a dispatch.
 		debug_assert_eq!(self.cache.borrow().keys().len(), 0);
 
 		log!(trace, "ConcurrentRuntime executing {:?}. ", call);
 		// execute
-		let dispatch_result =
-			<OuterCall as Dispatchable<Self>>::dispatch::<Self>(call, self, origin);
+		let dispatch_result = dispatch_with_nonce(self, call, origin, nonce, || {
+			self.cache.borrow_mut().clear()
+		});
 
 		log!(
 			trace,
@@ -211,9 +306,9 @@ impl ConcurrentRuntime {
 			self.cache.borrow().len()
 		);
 
-		// only commit if result is ok. Note that logic error will also ignore all writes.
+		// only commit if result is ok or a logic error, of which only the nonce is left.
 		match dispatch_result {
-			Ok(_) => self.commit_cache(),
+			Ok(_) | Err(DispatchError::LogicError(_)) => self.commit_cache(),
 			_ => (),
 		};
 
@@ -231,9 +326,14 @@ impl ConcurrentRuntime {
 		});
 	}
 
-	/// Validate a call.
+	/// Validate the call of a transaction. The nonce of `origin` is accessed as well.
 	pub fn validate(&self, call: &OuterCall, origin: AccountId) -> ValidationResult {
-		<OuterCall as Dispatchable<Self>>::validate(call, self, origin)
+		validate_transaction(self, call, origin)
+	}
+
+	/// The estimated cost of a call.
+	pub fn weight(&self, call: &OuterCall) -> Weight {
+		<OuterCall as Dispatchable<Self>>::weight(call)
 	}
 }
 
@@ -306,9 +406,33 @@ impl SequentialRuntime {
 			.to_runtime_dispatch_result()
 	}
 
-	/// Validate a call.
+	/// Dispatch the call of a transaction of `origin` with `nonce`.
+	///
+	/// Same as [`Self::dispatch`], except that the nonce of `origin` must be `nonce`, and it is
+	/// bumped. Writes are not cached here, hence the nonce is checked before the call.
+	pub fn dispatch_transaction(
+		&self,
+		call: OuterCall,
+		origin: AccountId,
+		nonce: Nonce,
+	) -> RuntimeDispatchResult {
+		system::check_nonce(self, origin, nonce)
+			.and_then(|_| {
+				let result =
+					<OuterCall as Dispatchable<Self>>::dispatch::<Self>(call, self, origin);
+				system::note_included(self, origin).and(result)
+			})
+			.to_runtime_dispatch_result()
+	}
+
+	/// Validate the call of a transaction. The nonce of `origin` is accessed as well.
 	pub fn validate(&self, call: &OuterCall, origin: AccountId) -> ValidationResult {
-		<OuterCall as Dispatchable<Self>>::validate(call, self, origin)
+		validate_transaction(self, call, origin)
+	}
+
+	/// The estimated cost of a call.
+	pub fn weight(&self, call: &OuterCall) -> Weight {
+		<OuterCall as Dispatchable<Self>>::weight(call)
 	}
 }
 
@@ -336,6 +460,181 @@ impl ModuleRuntime for SequentialRuntime {
 	}
 }
 
+/// The outcome of a speculative dispatch of an [`OptimisticRuntime`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SpeculativeDispatchOutcome {
+	/// The dispatch is done, based on the given reads. The writes are not yet applied anywhere.
+	///
+	/// A logical error will have no writes, other than the nonce of the origin of a transaction.
+	/// The result is never `Tainted`, which is reported as `Blocked` instead.
+	Executed {
+		result: RuntimeDispatchResult,
+		reads: Vec<ReadDescriptor<Key>>,
+		writes: Vec<(Key, Value)>,
+	},
+	/// The dispatch read a key that is about to be re-written by the transaction at the given
+	/// index. It must be dispatched again once that transaction is re-executed.
+	Blocked(TxIndex),
+}
+
+/// A runtime that executes transactions speculatively on top of a multi-versioned state. Each
+/// dispatch observes the writes of the transactions before it in the block, and reports back its
+/// reads and writes, so that it can be validated and re-executed if need be.
+///
+/// This should be used within the threads of the optimistic executor.
+pub struct OptimisticRuntime {
+	/// The backend state, only read from if no version of a key exists.
+	state: Arc<RuntimeState>,
+	/// The multi-versioned state of the block being executed.
+	versions: Arc<MultiVersionState<Key, Value>>,
+	/// Index of the transaction that is being dispatched.
+	index: Cell<TxIndex>,
+	/// Reads of the current dispatch.
+	reads: RefCell<Vec<ReadDescriptor<Key>>>,
+	/// Writes of the current dispatch.
+	cache: RefCell<HashMap<Key, Value>>,
+	/// Id of the thread.
+	id: ThreadId,
+}
+
+impl DispatchPermission for OptimisticRuntime {}
+
+impl OptimisticRuntime {
+	/// Create a new runtime.
+	pub fn new(
+		state: Arc<RuntimeState>,
+		versions: Arc<MultiVersionState<Key, Value>>,
+		id: ThreadId,
+	) -> Self {
+		Self {
+			state,
+			versions,
+			id,
+			index: Default::default(),
+			reads: Default::default(),
+			cache: Default::default(),
+		}
+	}
+
+	/// Dispatch a call, as the transaction at `index` of the block.
+	pub fn dispatch(
+		&self,
+		call: OuterCall,
+		origin: AccountId,
+		index: TxIndex,
+	) -> SpeculativeDispatchOutcome {
+		self.dispatch_inner(call, origin, None, index)
+	}
+
+	/// Dispatch the call of a transaction of `origin` with `nonce`, as the transaction at `index`
+	/// of the block.
+	///
+	/// Same as [`Self::dispatch`], except that the nonce of `origin` must be `nonce`, and it is
+	/// bumped.
+	pub fn dispatch_transaction(
+		&self,
+		call: OuterCall,
+		origin: AccountId,
+		nonce: Nonce,
+		index: TxIndex,
+	) -> SpeculativeDispatchOutcome {
+		self.dispatch_inner(call, origin, Some(nonce), index)
+	}
+
+	fn dispatch_inner(
+		&self,
+		call: OuterCall,
+		origin: AccountId,
+		nonce: Option<Nonce>,
+		index: TxIndex,
+	) -> SpeculativeDispatchOutcome {
+		debug_assert_eq!(self.cache.borrow().keys().len(), 0);
+		self.index.set(index);
+
+		log!(
+			trace,
+			"OptimisticRuntime executing {:?} at {}.",
+			call,
+			index
+		);
+		let dispatch_result = dispatch_with_nonce(self, call, origin, nonce, || {
+			self.cache.borrow_mut().clear()
+		})
+		.to_runtime_dispatch_result();
+
+		// the outcome is only as valid as the reads that lead to it.
+		let reads = self.reads.replace(Default::default());
+		let writes = self.cache.replace(Default::default());
+		match dispatch_result {
+			Ok(_) => SpeculativeDispatchOutcome::Executed {
+				result: dispatch_result,
+				reads,
+				writes: writes.into_iter().collect(),
+			},
+			Err(RuntimeDispatchError::BadNonce(_)) => SpeculativeDispatchOutcome::Executed {
+				result: dispatch_result,
+				reads,
+				writes: vec![],
+			},
+			Err(RuntimeDispatchError::Tainted(blocking, _)) => {
+				SpeculativeDispatchOutcome::Blocked(blocking as TxIndex)
+			}
+		}
+	}
+
+	/// Validate the call of a transaction. The nonce of `origin` is accessed as well.
+	pub fn validate(&self, call: &OuterCall, origin: AccountId) -> ValidationResult {
+		validate_transaction(self, call, origin)
+	}
+
+	/// The estimated cost of a call.
+	pub fn weight(&self, call: &OuterCall) -> Weight {
+		<OuterCall as Dispatchable<Self>>::weight(call)
+	}
+}
+
+impl ModuleRuntime for OptimisticRuntime {
+	const LIMITED: bool = true;
+
+	fn thread_id(&self) -> ThreadId {
+		self.id
+	}
+
+	/// Read a key.
+	///
+	/// If the key is blocked by another transaction, the index of that transaction is returned as
+	/// the error.
+	fn read(&self, key: &Key) -> Result<Value, ThreadId> {
+		if let Some(value) = self.cache.borrow().get(key) {
+			return Ok(value.clone());
+		}
+
+		let (version, value) = match self.versions.read(key, self.index.get()) {
+			MultiVersionRead::Versioned(version, value) => (Some(version), value),
+			MultiVersionRead::Storage => {
+				(None, self.state.unsafe_read_value(key).unwrap_or_default())
+			}
+			MultiVersionRead::Dependency(blocking) => return Err(blocking as ThreadId),
+		};
+		self.reads.borrow_mut().push(ReadDescriptor {
+			key: key.clone(),
+			version,
+		});
+		Ok(value)
+	}
+
+	fn write(&self, key: &Key, value: Value) -> Result<(), ThreadId> {
+		self.cache.borrow_mut().insert(key.clone(), value);
+		Ok(())
+	}
+
+	fn mutate(&self, key: &Key, update: impl Fn(&mut Value)) -> Result<(), ThreadId> {
+		let mut old = self.read(key)?;
+		update(&mut old);
+		self.write(key, old)
+	}
+}
+
 #[cfg(test)]
 mod concurrent_runtime_test {
 	use super::*;
@@ -429,6 +728,51 @@ mod concurrent_runtime_test {
 		// now it is also in state
 		assert_eq!(state.read(&k1, 1).unwrap(), vec![1].into());
 	}
+
+	#[test]
+	fn dispatch_transaction_checks_and_bumps_nonce() {
+		let state = RuntimeState::new().as_arc();
+		let rt = ConcurrentRuntime::new(state, 1);
+		let alice = testing::alice().public();
+		let call = OuterCall::Balances(balances::Call::Transfer(testing::bob().public(), 10));
+
+		// a logical error is still included, hence the nonce is bumped.
+		assert!(matches!(
+			rt.dispatch_transaction(call.clone(), alice, 0),
+			Ok(RuntimeDispatchSuccess::LogicError(_))
+		));
+		assert_eq!(system::NonceOf::read(&rt, alice), Ok(1));
+
+		// neither a replay nor a future nonce is accepted.
+		assert_eq!(
+			rt.dispatch_transaction(call.clone(), alice, 0),
+			Err(RuntimeDispatchError::BadNonce(1))
+		);
+		assert_eq!(
+			rt.dispatch_transaction(call, alice, 2),
+			Err(RuntimeDispatchError::BadNonce(1))
+		);
+		assert_eq!(system::NonceOf::read(&rt, alice), Ok(1));
+	}
+
+	#[test]
+	fn bad_nonce_is_checked_before_the_call() {
+		let state = RuntimeState::new().as_arc();
+		let rt = ConcurrentRuntime::new(Arc::clone(&state), 1);
+		let alice = testing::alice().public();
+		let call = OuterCall::Balances(balances::Call::Transfer(testing::bob().public(), 10));
+
+		// only the nonce is read, and it is not materialized.
+		assert_eq!(
+			rt.dispatch_transaction(call, alice, 1),
+			Err(RuntimeDispatchError::BadNonce(0))
+		);
+		assert!(state.dump().is_empty());
+		assert_eq!(
+			state.unsafe_read_taint(&balances::BalanceOf::<ConcurrentRuntime>::key_for(alice)),
+			None
+		);
+	}
 }
 
 #[cfg(test)]
@@ -464,4 +808,146 @@ mod sequential_runtime_test {
 		assert!(rt.write(&k1, vec![1, 2, 3].into()).is_ok());
 		assert!(rt.mutate(&k1, |val| val.0.push(99)).is_ok());
 	}
+
+	#[test]
+	fn replayed_transaction_writes_nothing() {
+		let rt = SequentialRuntime::new(RuntimeState::new().as_arc(), 1);
+		let alice = testing::alice().public();
+		let bob = testing::bob().public();
+		balances::BalanceOf::write(&rt, alice, 100.into()).unwrap();
+
+		let call = OuterCall::Balances(balances::Call::Transfer(bob, 10));
+		assert_eq!(
+			rt.dispatch_transaction(call.clone(), alice, 0),
+			Ok(RuntimeDispatchSuccess::Ok)
+		);
+		assert_eq!(
+			rt.dispatch_transaction(call, alice, 0),
+			Err(RuntimeDispatchError::BadNonce(1))
+		);
+		assert_eq!(balances::BalanceOf::read(&rt, bob), Ok(10.into()));
+		assert_eq!(system::NonceOf::read(&rt, alice), Ok(1));
+	}
+}
+
+#[cfg(test)]
+mod optimistic_runtime_test {
+	use super::*;
+	use state::multi_version::ReadDescriptor;
+
+	fn setup() -> (
+		Arc<RuntimeState>,
+		Arc<MultiVersionState<Key, Value>>,
+		OptimisticRuntime,
+	) {
+		let state = RuntimeState::new().as_arc();
+		let versions = Arc::new(MultiVersionState::new(4));
+		let rt = OptimisticRuntime::new(Arc::clone(&state), Arc::clone(&versions), 1);
+		(state, versions, rt)
+	}
+
+	#[test]
+	fn reads_versions_and_storage() {
+		let (state, versions, rt) = setup();
+		let k1: StateKey = vec![1u8].into();
+		let k2: StateKey = vec![2u8].into();
+		state.unsafe_insert_genesis_value(&k1, vec![1].into());
+		versions.record((1, 0), vec![], vec![(k2.clone(), vec![2].into())]);
+
+		rt.index.set(2);
+		assert_eq!(rt.read(&k1).unwrap(), vec![1].into());
+		assert_eq!(rt.read(&k2).unwrap(), vec![2].into());
+		assert_eq!(
+			*rt.reads.borrow(),
+			vec![
+				ReadDescriptor {
+					key: k1,
+					version: None
+				},
+				ReadDescriptor {
+					key: k2.clone(),
+					version: Some((1, 0))
+				},
+			]
+		);
+
+		// writes are only cached.
+		assert!(rt.write(&k2, vec![3].into()).is_ok());
+		assert_eq!(rt.read(&k2).unwrap(), vec![3].into());
+		assert_eq!(rt.reads.borrow().len(), 2);
+		assert_eq!(
+			versions.read(&k2, 2),
+			MultiVersionRead::Versioned((1, 0), vec![2].into())
+		);
+	}
+
+	#[test]
+	fn reading_estimate_blocks() {
+		let (_, versions, rt) = setup();
+		let k1: StateKey = vec![1u8].into();
+		versions.record((1, 0), vec![], vec![(k1.clone(), vec![1].into())]);
+		versions.convert_writes_to_estimates(1);
+
+		rt.index.set(2);
+		assert_eq!(rt.read(&k1), Err(1));
+		rt.index.set(1);
+		assert!(rt.read(&k1).is_ok());
+	}
+
+	#[test]
+	fn dispatch_reports_reads_and_writes() {
+		let (_, versions, rt) = setup();
+		let alice = testing::alice().public();
+		let bob = testing::bob().public();
+		versions.record(
+			(0, 0),
+			vec![],
+			vec![(
+				<balances::BalanceOf<OptimisticRuntime>>::key_for(alice),
+				balances::AccountBalance::from(100).encode().into(),
+			)],
+		);
+
+		let call = OuterCall::Balances(balances::Call::Transfer(bob, 10));
+		match rt.dispatch(call.clone(), alice, 1) {
+			SpeculativeDispatchOutcome::Executed {
+				result,
+				reads,
+				writes,
+			} => {
+				assert_eq!(result, Ok(RuntimeDispatchSuccess::Ok));
+				assert_eq!(reads.len(), 2);
+				assert_eq!(reads[0].version, Some((0, 0)));
+				assert_eq!(reads[1].version, None);
+				assert_eq!(writes.len(), 2);
+			}
+			_ => panic!("dispatch should not be blocked"),
+		}
+
+		// bob has nothing to transfer, hence it is a logical error with no writes.
+		let call = OuterCall::Balances(balances::Call::Transfer(alice, 10));
+		match rt.dispatch(call.clone(), bob, 1) {
+			SpeculativeDispatchOutcome::Executed { result, writes, .. } => {
+				assert!(matches!(result, Ok(RuntimeDispatchSuccess::LogicError(_))));
+				assert!(writes.is_empty());
+			}
+			_ => panic!("dispatch should not be blocked"),
+		}
+
+		// the nonce is written even upon a logical error, but not if it is bad.
+		match rt.dispatch_transaction(call.clone(), bob, 0, 1) {
+			SpeculativeDispatchOutcome::Executed { result, writes, .. } => {
+				assert!(matches!(result, Ok(RuntimeDispatchSuccess::LogicError(_))));
+				assert_eq!(writes.len(), 1);
+			}
+			_ => panic!("dispatch should not be blocked"),
+		}
+		match rt.dispatch_transaction(call, bob, 1, 1) {
+			SpeculativeDispatchOutcome::Executed { result, writes, .. } => {
+				assert_eq!(result, Err(RuntimeDispatchError::BadNonce(0)));
+				assert!(writes.is_empty());
+			}
+			_ => panic!("dispatch should not be blocked"),
+		}
+	}
 }
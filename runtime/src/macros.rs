@@ -34,6 +34,16 @@ macro_rules! decl_outer_call {
 					)*
 				}
 			}
+
+			fn weight(&self) -> $crate::Weight {
+				match self {
+					$(
+						$outer_call_name::$module_name(inner_call) => {
+							<$inner_call_path as $crate::Dispatchable<R>>::weight(inner_call)
+						}
+					)*
+				}
+			}
 		}
 	};
 }
@@ -41,6 +51,33 @@ macro_rules! decl_outer_call {
 #[macro_export]
 macro_rules! decl_tx {
 	// parse a complete transaction.
+	(
+		@PARSED [ $($parsed_functions:tt)* ]
+		#[access = $access:tt]
+		#[weight = $weight:expr]
+		fn $name:ident(
+			$runtime:ident,
+			$origin:ident
+			$(, $arg_name:ident : $arg_type:ty)* $(,)?
+		) { $( $impl:tt )* }
+		$( $rest:tt )*
+	) => {
+		$crate::decl_tx! {
+			@PARSED [
+				$( $parsed_functions )*
+				#[access = $access]
+				#[weight = $weight]
+				fn $name(
+					$runtime,
+					$origin
+					$(, $arg_name : $arg_type)*
+				) {  $( $impl )* }
+			]
+			$( $rest )*
+		}
+	};
+
+	// parse a transaction without weight.
 	(
 		@PARSED [ $($parsed_functions:tt)* ]
 		#[access = $access:tt]
@@ -55,6 +92,7 @@ macro_rules! decl_tx {
 			@PARSED [
 				$( $parsed_functions )*
 				#[access = $access]
+				#[weight = $crate::DEFAULT_WEIGHT]
 				fn $name(
 					$runtime,
 					$origin
@@ -68,6 +106,7 @@ macro_rules! decl_tx {
 	// parse a transaction without access.
 	(
 		@PARSED [ $($parsed_functions:tt)* ]
+		#[weight = $weight:expr]
 		fn $name:ident(
 			$runtime:ident,
 			$origin:ident
@@ -79,6 +118,32 @@ macro_rules! decl_tx {
 			@PARSED [
 				$( $parsed_functions )*
 				#[access = (|_| Default::default())]
+				#[weight = $weight]
+				fn $name(
+					$runtime,
+					$origin
+					$(, $arg_name : $arg_type)*
+				) {  $( $impl )* }
+			]
+			$( $rest )*
+		}
+	};
+
+	// parse a transaction without access and weight.
+	(
+		@PARSED [ $($parsed_functions:tt)* ]
+		fn $name:ident(
+			$runtime:ident,
+			$origin:ident
+			$(, $arg_name:ident : $arg_type:ty)* $(,)?
+		) { $( $impl:tt )* }
+		$( $rest:tt )*
+	) => {
+		$crate::decl_tx! {
+			@PARSED [
+				$( $parsed_functions )*
+				#[access = (|_| Default::default())]
+				#[weight = $crate::DEFAULT_WEIGHT]
 				fn $name(
 					$runtime,
 					$origin
@@ -94,6 +159,7 @@ macro_rules! decl_tx {
 		@PARSED [
 			$(
 				#[access = $access:tt]
+				#[weight = $weight:expr]
 				fn $name:ident(
 					$runtime:ident,
 					$origin:ident
@@ -129,6 +195,7 @@ macro_rules! decl_tx {
 			@IMPL_DISPATCHABLE
 			$(
 				#[access = $access]
+				#[weight = $weight]
 				fn $name(
 					$(, $arg_name : $arg_type)*
 				)
@@ -183,6 +250,7 @@ macro_rules! decl_tx {
 		@IMPL_DISPATCHABLE
 		$(
 			#[access = $access:tt]
+			#[weight = $weight:expr]
 			fn $name:ident(
 				$(, $arg_name:ident : $arg_type:ty)* $(,)?
 			)
@@ -216,6 +284,15 @@ macro_rules! decl_tx {
 						)*
 					}
 				}
+
+				#[allow(unused)]
+				fn weight(&self) -> $crate::Weight {
+					match self {
+						$(
+							Self::[<$name:camel>]( $($arg_name),* ) => $weight,
+						)*
+					}
+				}
 			}
 		}
 	};
@@ -429,3 +506,69 @@ mod tests_storage_macros {
 		assert_eq!(TestValue::read(&rt), Ok(vec![1, 3, 999]));
 	}
 }
+
+#[cfg(test)]
+#[allow(dead_code)]
+mod tests_tx_macros {
+	use crate::{Dispatchable, SequentialRuntime, Weight, DEFAULT_WEIGHT};
+	use parity_scale_codec::{Decode, Encode};
+	use primitives::AccountId;
+
+	const MODULE: &'static str = "test";
+
+	decl_storage_map!(TestMap, "map", u8, u32);
+
+	decl_tx! {
+		#[access = (|_| vec![<TestMap<R>>::key_for(*key)])]
+		#[weight = 3]
+		fn with_both(rt, _origin, key: u8) {
+			TestMap::write(rt, key, 1).unwrap();
+			Ok(())
+		}
+
+		#[access = (|_| vec![<TestMap<R>>::key_for(*key)])]
+		fn with_access(rt, _origin, key: u8) {
+			TestMap::write(rt, key, 2).unwrap();
+			Ok(())
+		}
+
+		#[weight = 2 * (*count as Weight)]
+		fn with_weight(rt, _origin, count: u32) {
+			TestMap::write(rt, 0, count).unwrap();
+			Ok(())
+		}
+
+		fn with_none(_rt, _origin) {
+			Ok(())
+		}
+	}
+
+	fn weight_of(call: Call) -> Weight {
+		<Call as Dispatchable<SequentialRuntime>>::weight(&call)
+	}
+
+	#[test]
+	fn weight_works() {
+		assert_eq!(weight_of(Call::WithBoth(1)), 3);
+		assert_eq!(weight_of(Call::WithAccess(1)), DEFAULT_WEIGHT);
+		assert_eq!(weight_of(Call::WithWeight(5)), 10);
+		assert_eq!(weight_of(Call::WithNone()), DEFAULT_WEIGHT);
+	}
+
+	#[test]
+	#[cfg(not(feature = "no-access"))]
+	fn access_is_kept_with_weight() {
+		let state = crate::RuntimeState::new().as_arc();
+		let rt = SequentialRuntime::new(state, 0);
+		let origin = primitives::testing::alice().public();
+		assert_eq!(
+			Call::WithBoth(1).validate(&rt, origin),
+			vec![<TestMap<SequentialRuntime>>::key_for(1)]
+		);
+		assert_eq!(
+			Call::WithAccess(2).validate(&rt, origin),
+			vec![<TestMap<SequentialRuntime>>::key_for(2)]
+		);
+		assert!(Call::WithWeight(1).validate(&rt, origin).is_empty());
+	}
+}
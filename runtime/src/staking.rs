@@ -39,6 +39,7 @@ decl_tx! {
 		<Ledger<R>>::key_for(origin.clone()),
 		<Bonded<R>>::key_for(controller.clone()),
 	])]
+	#[weight = 8]
 	fn tx_bond(rt, stash, amount: Balance, controller: AccountId) {
 		// check already bonded.
 		if Ledger::exists(rt, stash).or_forward()? {
@@ -64,6 +65,7 @@ decl_tx! {
 		<BalanceOf<R>>::key_for(origin.clone()),
 		<Ledger<R>>::key_for(origin.clone()),
 	])]
+	#[weight = 6]
 	fn tx_bond_extra(rt, stash, amount: Balance) {
 		let mut ledger = Ledger::read(rt, stash).or_forward()?;
 		if ledger == StakingLedger::default() {
@@ -85,6 +87,7 @@ decl_tx! {
 		<BalanceOf<R>>::key_for(origin.clone()),
 		<Ledger<R>>::key_for(origin.clone()),
 	])]
+	#[weight = 6]
 	fn tx_unbond(rt, stash, amount: Balance) {
 		let mut ledger = Ledger::read(rt, stash).or_forward()?;
 
@@ -114,6 +117,7 @@ decl_tx! {
 		<Ledger<R>>::key_for(origin.clone()),
 		<Bonded<R>>::key_for(ctrl.clone()),
 	])]
+	#[weight = 7]
 	fn tx_set_controller(rt, stash, ctrl: AccountId) {
 		let mut ledger = Ledger::read(rt, stash).or_forward()?;
 
@@ -142,6 +146,7 @@ decl_tx! {
 	#[access = (|origin: Public| vec![
 		<Bonded<R>>::key_for(origin),
 	])]
+	#[weight = 4]
 	fn tx_validate(rt, ctrl) {
 		let stash = Bonded::read(rt, ctrl).or_forward()?;
 		if stash == Default::default() {
@@ -150,7 +155,9 @@ decl_tx! {
 
 		let mut ledger = Ledger::read(rt, stash).or_orphan()?;
 		if ledger == StakingLedger::default() {
-			panic!("Ledger must exist when Bonded exists.");
+			return Err(DispatchError::LogicError(
+				"Ledger must exist when Bonded exists.",
+			));
 		}
 
 		// update ledger.
@@ -164,6 +171,7 @@ decl_tx! {
 	#[access = (|origin: Public| vec![
 		<Bonded<R>>::key_for(origin),
 	])]
+	#[weight = 6]
 	fn tx_nominate(rt, ctrl, targets: Vec<AccountId>) {
 		let stash = Bonded::read(rt, ctrl).or_forward()?;
 		if stash == Default::default() {
@@ -172,7 +180,9 @@ decl_tx! {
 
 		let mut ledger = Ledger::read(rt, stash).or_orphan()?;
 		if ledger == StakingLedger::default() {
-			panic!("Ledger must exist when Bonded exists.");
+			return Err(DispatchError::LogicError(
+				"Ledger must exist when Bonded exists.",
+			));
 		}
 
 		// write targets.
@@ -189,6 +199,7 @@ decl_tx! {
 	#[access = (|origin: Public| vec![
 		<Bonded<R>>::key_for(origin),
 	])]
+	#[weight = 6]
 	fn tx_chill(rt, ctrl) {
 		let stash = Bonded::read(rt, ctrl).or_forward()?;
 		if stash == Default::default() {
@@ -197,7 +208,9 @@ decl_tx! {
 
 		let mut ledger = Ledger::read(rt, stash).or_orphan()?;
 		if ledger == StakingLedger::default() {
-			panic!("Ledger must exist when Bonded exists.");
+			return Err(DispatchError::LogicError(
+				"Ledger must exist when Bonded exists.",
+			));
 		}
 
 		// clear potentially the nominations.
@@ -1 +1,33 @@
+use crate::{decl_storage_map, DispatchError, DispatchResult, ModuleRuntime, UnwrapStorageOp};
+use parity_scale_codec::{Decode, Encode};
+use primitives::*;
 
+const MODULE: &str = "system";
+
+decl_storage_map!(NonceOf, "nonce_of", AccountId, Nonce);
+
+/// Fail with `BadNonce` if `nonce` is not the current nonce of `who`.
+///
+/// The nonce of an account is the number of its transactions that have been included so far, and
+/// the nonce that its next transaction should carry.
+///
+/// The nonce is accessed before the call of the transaction is dispatched, hence a tainted nonce
+/// forwards the transaction to its owner.
+pub(crate) fn check_nonce<R: ModuleRuntime>(
+	runtime: &R,
+	who: AccountId,
+	nonce: Nonce,
+) -> DispatchResult {
+	let expected = NonceOf::read(runtime, who).or_forward()?;
+	if nonce != expected {
+		return Err(DispatchError::BadNonce(expected));
+	}
+	Ok(())
+}
+
+/// Note that a transaction of `who` has been included in a block.
+///
+/// This bumps the nonce of `who`, so that the same transaction cannot be included again.
+pub(crate) fn note_included<R: ModuleRuntime>(runtime: &R, who: AccountId) -> DispatchResult {
+	NonceOf::mutate(runtime, who, |nonce| *nonce += 1).or_orphan()
+}
@@ -19,17 +19,19 @@ const WRITE_DELAY: std::time::Duration = std::time::Duration::from_millis(2);
 macro_rules! sleep_read {
 	() => {
 		#[cfg(not(test))]
-		std::thread::sleep(READ_DELAY);
+		std::thread::sleep($crate::READ_DELAY);
 	};
 }
 
 macro_rules! write_sleep {
 	() => {
 		#[cfg(not(test))]
-		std::thread::sleep(WRITE_DELAY);
+		std::thread::sleep($crate::WRITE_DELAY);
 	};
 }
 
+pub mod multi_version;
+
 /// Extension trait to check the equality of two state dumps.
 ///
 /// This can be done by means of state root in case of merklized state, or others if a simpler state
@@ -50,7 +52,8 @@ impl<K: KeyT, V: ValueT, T: TaintT> StateEq for StateType<K, V, T> {
 pub trait GenericState<K, V, T> {
 	/// Read the state entry at `key`.
 	///
-	/// - If the key does not exist, it will try and taint it, and return `Ok(Default)`.
+	/// - If the key does not exist, it will try and taint it, and return `Ok(Default)`. The key
+	///   still has no value afterwards, until it is written.
 	/// 	- This will first create a read lock, then a write lock.
 	/// - If the key exists, and the taint is equal to `current`, then Ok(value) is returned.
 	/// 	- This will only require read locks.
@@ -104,7 +107,9 @@ impl<T: Clone + Copy + Debug + Eq + PartialEq> TaintT for T {}
 #[derive(Default, Debug, Clone)]
 pub struct StateValue<V, T> {
 	/// The data itself.
-	data: RefCell<V>,
+	///
+	/// `None` if the key is only tainted, i.e. it has been read but it was never written.
+	data: RefCell<Option<V>>,
 	/// The taint associated with the data.
 	taint: Option<T>,
 }
@@ -121,13 +126,19 @@ impl<V: ValueT, T> Eq for StateValue<V, T> {}
 unsafe impl<V, T> Sync for StateValue<V, T> {}
 
 impl<V: ValueT, T: TaintT> StateValue<V, T> {
+	/// The data, or the default value if the key was never written.
 	pub fn data(&self) -> V {
-		self.data.borrow().clone()
+		self.data.borrow().clone().unwrap_or_default()
+	}
+
+	/// If this value was ever written, as opposed to only being tainted.
+	pub fn is_written(&self) -> bool {
+		self.data.borrow().is_some()
 	}
 
 	pub fn new(value: V, taint: T) -> Self {
 		Self {
-			data: value.into(),
+			data: Some(value).into(),
 			taint: Some(taint),
 		}
 	}
@@ -141,7 +152,7 @@ impl<V: ValueT, T: TaintT> StateValue<V, T> {
 
 	pub fn new_data(value: V) -> Self {
 		Self {
-			data: value.into(),
+			data: Some(value).into(),
 			taint: None,
 		}
 	}
@@ -206,11 +217,18 @@ impl<K: KeyT, V: ValueT, T: TaintT> TaintState<K, V, T> {
 
 	/// Return a dump of the state at this point in time as a HashMap.
 	///
+	/// Keys that are only tainted, i.e. read but never written, are left out. They have no value.
+	///
 	/// Note that this copied all the data to na new hashmap and hence is an expensive operation.
 	pub fn dump(&self) -> StateType<K, V, T> {
 		self.backend
 			.read()
-			.map(|g| g.clone())
+			.map(|g| {
+				g.iter()
+					.filter(|(_, v)| v.is_written())
+					.map(|(k, v)| (k.clone(), v.clone()))
+					.collect()
+			})
 			.expect("dumping state should work")
 	}
 
@@ -240,7 +258,7 @@ impl<K: KeyT, V: ValueT, T: TaintT> TaintState<K, V, T> {
 
 	/// Unsafe implementation of read. This will not respect the tainting of the key.
 	pub fn unsafe_read_value(&self, key: &K) -> Option<V> {
-		self.unsafe_read(key).map(|e| e.data.clone().into_inner())
+		self.unsafe_read(key).and_then(|e| e.data.into_inner())
 	}
 
 	/// Unsafe implementation of read. This will not respect the tainting of the key.
@@ -266,7 +284,7 @@ impl<K: KeyT, V: ValueT, T: TaintT> GenericState<K, V, T> for TaintState<K, V, T
 			if let Some(owner) = maybe_owner {
 				// 1. if entry exists and it has a taint.
 				if owner == current {
-					Ok(entry.data.borrow().clone())
+					Ok(entry.data())
 				} else {
 					Err(owner)
 				}
@@ -287,7 +305,7 @@ impl<K: KeyT, V: ValueT, T: TaintT> GenericState<K, V, T> for TaintState<K, V, T
 					}
 				} else {
 					entry.taint = Some(current);
-					Ok(entry.data.borrow().clone())
+					Ok(entry.data())
 				}
 			}
 		} else {
@@ -324,7 +342,7 @@ impl<K: KeyT, V: ValueT, T: TaintT> GenericState<K, V, T> for TaintState<K, V, T
 			if let Some(owner) = entry.taint {
 				// 1. if entry exists and it has a taint.
 				if owner == current {
-					*entry.data.borrow_mut() = value;
+					*entry.data.borrow_mut() = Some(value);
 					Ok(())
 				} else {
 					Err(owner)
@@ -346,7 +364,7 @@ impl<K: KeyT, V: ValueT, T: TaintT> GenericState<K, V, T> for TaintState<K, V, T
 					}
 				} else {
 					// we have the write lock and the entry does not have taint. Taint and write.
-					*entry.data.borrow_mut() = value;
+					*entry.data.borrow_mut() = Some(value);
 					entry.taint = Some(current);
 					Ok(())
 				}
@@ -560,14 +578,14 @@ mod test_state {
 		assert_eq!(
 			*dump.get(&10).unwrap(),
 			StateValue {
-				data: 10u32.into(),
+				data: Some(10u32).into(),
 				taint: None,
 			}
 		);
 		assert_eq!(
 			*dump.get(&11).unwrap(),
 			StateValue {
-				data: 11u32.into(),
+				data: Some(11u32).into(),
 				taint: Some(1),
 			}
 		);
@@ -583,14 +601,14 @@ mod test_state {
 		assert_eq!(
 			*dump.get(&10).unwrap(),
 			StateValue {
-				data: 10u32.into(),
+				data: Some(10u32).into(),
 				taint: None,
 			}
 		);
 		assert_eq!(
 			*dump.get(&11).unwrap(),
 			StateValue {
-				data: 11u32.into(),
+				data: Some(11u32).into(),
 				taint: Some(1),
 			}
 		);
@@ -605,14 +623,14 @@ mod test_state {
 		assert_eq!(
 			state.unsafe_read(&10).unwrap(),
 			StateValue {
-				data: 10u32.into(),
+				data: Some(10u32).into(),
 				taint: None,
 			}
 		);
 		assert_eq!(
 			state.unsafe_read(&11).unwrap(),
 			StateValue {
-				data: 11u32.into(),
+				data: Some(11u32).into(),
 				taint: Some(1),
 			}
 		);
@@ -658,4 +676,22 @@ mod test_state {
 
 		assert!(dump1.state_eq(dump2));
 	}
+
+	#[test]
+	fn reading_missing_keys_does_not_materialize_them() {
+		let state = TestState::new();
+		state.unsafe_insert(&10u32, StateValue::new_data(10u32));
+		assert_eq!(state.read(&11, 1), Ok(0));
+		assert_eq!(state.unsafe_read_taint(&11), Some(1));
+		assert_eq!(state.unsafe_read_value(&11), None);
+
+		let dump = state.dump();
+		assert_eq!(dump.len(), 1);
+		assert!(dump.state_eq(state.dump()));
+
+		// but writing the default value does.
+		state.write(&11, 0, 1).unwrap();
+		assert_eq!(state.unsafe_read_value(&11), Some(0));
+		assert!(!dump.state_eq(state.dump()));
+	}
 }
@@ -0,0 +1,270 @@
+//! A multi-versioned state, used for optimistic execution of a block.
+//!
+//! Each entry is versioned by the index of the transaction within the block that wrote it, and the
+//! incarnation of that transaction, i.e. the number of times that it has been re-executed. A
+//! transaction always observes the writes of the closest transaction before it, hence the outcome
+//! of the execution is the same as executing the block sequentially, once all the read-sets are
+//! valid.
+
+use crate::{KeyT, ValueT};
+use std::{
+	collections::{hash_map::HashMap, BTreeMap},
+	sync::{Mutex, RwLock},
+};
+
+/// Index of a transaction within a block.
+pub type TxIndex = usize;
+
+/// The number of times that a transaction has been re-executed.
+pub type Incarnation = usize;
+
+/// The version of a write: the transaction that wrote it, and its incarnation at the time.
+pub type Version = (TxIndex, Incarnation);
+
+/// An entry written into the multi-version state.
+#[derive(Debug, Clone)]
+enum Entry<V> {
+	/// A value written by the given incarnation of a transaction.
+	Written(Incarnation, V),
+	/// The transaction that wrote this is being re-executed, and will likely write to it again.
+	Estimate,
+}
+
+/// The outcome of a read from the multi-version state.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum MultiVersionRead<V> {
+	/// The value written by the closest transaction before the reader, with its version.
+	Versioned(Version, V),
+	/// No transaction before the reader has written to the key. The value must be read from the
+	/// backend storage.
+	Storage,
+	/// The closest transaction before the reader that wrote to the key is being re-executed. The
+	/// reader should wait for it.
+	Dependency(TxIndex),
+}
+
+/// A read done by a transaction, kept so that it can be validated later.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ReadDescriptor<K> {
+	/// The key that was read.
+	pub key: K,
+	/// The version that was observed, or `None` if it was read from the backend storage.
+	pub version: Option<Version>,
+}
+
+/// A multi-versioned state for a block of `len` transactions.
+///
+/// This is only an overlay. Reads that do not hit any version must be served from the backend
+/// storage by the caller.
+#[derive(Debug)]
+pub struct MultiVersionState<K: KeyT, V: ValueT> {
+	/// All the versions of each key.
+	data: RwLock<HashMap<K, BTreeMap<TxIndex, Entry<V>>>>,
+	/// The keys written by the last incarnation of each transaction.
+	last_written: Vec<Mutex<Vec<K>>>,
+	/// The reads done by the last incarnation of each transaction.
+	last_read: Vec<Mutex<Vec<ReadDescriptor<K>>>>,
+}
+
+impl<K: KeyT, V: ValueT> MultiVersionState<K, V> {
+	/// Create a new multi-version state for a block of `len` transactions.
+	pub fn new(len: usize) -> Self {
+		Self {
+			data: Default::default(),
+			last_written: (0..len).map(|_| Default::default()).collect(),
+			last_read: (0..len).map(|_| Default::default()).collect(),
+		}
+	}
+
+	/// Record the outcome of the execution of the given `version` of a transaction.
+	///
+	/// The writes of any previous incarnation that are not present in `writes` anymore are removed.
+	/// Returns true if any key that was not written by the previous incarnation is written now.
+	pub fn record(
+		&self,
+		(index, incarnation): Version,
+		reads: Vec<ReadDescriptor<K>>,
+		writes: Vec<(K, V)>,
+	) -> bool {
+		*self.last_read[index].lock().unwrap() = reads;
+
+		let mut last_written = self.last_written[index].lock().unwrap();
+		let mut data = self.data.write().unwrap();
+		let new_keys = writes.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>();
+
+		for stale in last_written.iter().filter(|k| !new_keys.contains(k)) {
+			if let Some(versions) = data.get_mut(stale) {
+				versions.remove(&index);
+			}
+		}
+
+		let wrote_new_key = new_keys.iter().any(|k| !last_written.contains(k));
+		for (key, value) in writes {
+			data.entry(key)
+				.or_default()
+				.insert(index, Entry::Written(incarnation, value));
+		}
+
+		*last_written = new_keys;
+		wrote_new_key
+	}
+
+	/// Mark all the writes of the last incarnation of the transaction at `index` as estimates.
+	///
+	/// This should be called when the transaction is aborted and is about to be re-executed.
+	pub fn convert_writes_to_estimates(&self, index: TxIndex) {
+		let last_written = self.last_written[index].lock().unwrap();
+		let mut data = self.data.write().unwrap();
+		for key in last_written.iter() {
+			if let Some(entry) = data.get_mut(key).and_then(|v| v.get_mut(&index)) {
+				*entry = Entry::Estimate;
+			}
+		}
+	}
+
+	/// Read the value of `key`, as seen by the transaction at `index`.
+	///
+	/// Reading a version has the same cost as reading from the backend storage, so that executors
+	/// remain comparable.
+	pub fn read(&self, key: &K, index: TxIndex) -> MultiVersionRead<V> {
+		let outcome = self.read_unmetered(key, index);
+		if let MultiVersionRead::Versioned(_, _) = outcome {
+			sleep_read!();
+		}
+		outcome
+	}
+
+	/// Check if all the reads of the last incarnation of the transaction at `index` would still
+	/// observe the same versions.
+	pub fn validate_read_set(&self, index: TxIndex) -> bool {
+		self.last_read[index].lock().unwrap().iter().all(|read| {
+			match (self.read_unmetered(&read.key, index), read.version) {
+				(MultiVersionRead::Versioned(current, _), Some(observed)) => current == observed,
+				(MultiVersionRead::Storage, None) => true,
+				_ => false,
+			}
+		})
+	}
+
+	/// The latest value of every key that has been written.
+	///
+	/// This should only be called once all transactions are executed and validated, in which case
+	/// there cannot be any estimates left.
+	pub fn snapshot(&self) -> Vec<(K, V)> {
+		self.data
+			.read()
+			.unwrap()
+			.iter()
+			.filter_map(|(key, versions)| {
+				versions.values().next_back().map(|entry| match entry {
+					Entry::Written(_, value) => (key.clone(), value.clone()),
+					Entry::Estimate => panic!("Estimate left in a finalized state: {:?}", key),
+				})
+			})
+			.collect()
+	}
+
+	fn read_unmetered(&self, key: &K, index: TxIndex) -> MultiVersionRead<V> {
+		let data = self.data.read().unwrap();
+		match data
+			.get(key)
+			.and_then(|versions| versions.range(..index).next_back())
+		{
+			Some((writer, Entry::Written(incarnation, value))) => {
+				MultiVersionRead::Versioned((*writer, *incarnation), value.clone())
+			}
+			Some((writer, Entry::Estimate)) => MultiVersionRead::Dependency(*writer),
+			None => MultiVersionRead::Storage,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	type TestState = MultiVersionState<u32, u32>;
+
+	fn read(key: u32, version: Option<Version>) -> ReadDescriptor<u32> {
+		ReadDescriptor { key, version }
+	}
+
+	#[test]
+	fn reads_closest_lower_version() {
+		let state = TestState::new(4);
+		assert_eq!(state.read(&1, 2), MultiVersionRead::Storage);
+
+		state.record((0, 0), vec![], vec![(1, 10)]);
+		state.record((2, 0), vec![], vec![(1, 12)]);
+
+		assert_eq!(state.read(&1, 0), MultiVersionRead::Storage);
+		assert_eq!(state.read(&1, 1), MultiVersionRead::Versioned((0, 0), 10));
+		assert_eq!(state.read(&1, 2), MultiVersionRead::Versioned((0, 0), 10));
+		assert_eq!(state.read(&1, 3), MultiVersionRead::Versioned((2, 0), 12));
+		assert_eq!(state.read(&2, 3), MultiVersionRead::Storage);
+	}
+
+	#[test]
+	fn record_reports_new_keys_and_removes_stale_ones() {
+		let state = TestState::new(2);
+		assert!(state.record((0, 0), vec![], vec![(1, 10), (2, 20)]));
+		assert!(!state.record((0, 1), vec![], vec![(1, 11)]));
+
+		assert_eq!(state.read(&1, 1), MultiVersionRead::Versioned((0, 1), 11));
+		assert_eq!(state.read(&2, 1), MultiVersionRead::Storage);
+
+		assert!(state.record((0, 2), vec![], vec![(1, 12), (3, 30)]));
+	}
+
+	#[test]
+	fn estimates_are_dependencies() {
+		let state = TestState::new(2);
+		state.record((0, 0), vec![], vec![(1, 10)]);
+		state.convert_writes_to_estimates(0);
+
+		assert_eq!(state.read(&1, 1), MultiVersionRead::Dependency(0));
+
+		state.record((0, 1), vec![], vec![(1, 11)]);
+		assert_eq!(state.read(&1, 1), MultiVersionRead::Versioned((0, 1), 11));
+	}
+
+	#[test]
+	fn validate_read_set_works() {
+		let state = TestState::new(3);
+		state.record((0, 0), vec![], vec![(1, 10)]);
+		state.record((2, 0), vec![read(1, Some((0, 0))), read(2, None)], vec![]);
+		assert!(state.validate_read_set(2));
+
+		// a new incarnation of the writer invalidates the read.
+		state.record((0, 1), vec![], vec![(1, 11)]);
+		assert!(!state.validate_read_set(2));
+
+		state.record((2, 1), vec![read(1, Some((0, 1))), read(2, None)], vec![]);
+		assert!(state.validate_read_set(2));
+
+		// a new write by a transaction in between invalidates the storage read.
+		state.record((1, 0), vec![], vec![(2, 21)]);
+		assert!(!state.validate_read_set(2));
+
+		// and so does an estimate.
+		state.record(
+			(2, 2),
+			vec![read(1, Some((0, 1))), read(2, Some((1, 0)))],
+			vec![],
+		);
+		state.convert_writes_to_estimates(1);
+		assert!(!state.validate_read_set(2));
+	}
+
+	#[test]
+	fn snapshot_has_latest_values() {
+		let state = TestState::new(3);
+		state.record((0, 0), vec![], vec![(1, 10), (2, 20)]);
+		state.record((2, 0), vec![], vec![(1, 12)]);
+		state.record((1, 0), vec![], vec![(1, 11)]);
+
+		let mut snapshot = state.snapshot();
+		snapshot.sort();
+		assert_eq!(snapshot, vec![(1, 12), (2, 20)]);
+	}
+}